@@ -140,22 +140,79 @@ impl From<server::AttributeValue> for AttributeValue {
     }
 }
 
+api_type!(
+#[derive(Default)]
+/// The overall status of an element.
+enum HealthStatus {
+    /// Everything is fine.
+    Up,
+    /// The element is online, but something isn't quite right (e.g. it is slow or full).
+    Degraded,
+    /// The element is offline.
+    Down,
+    #[default]
+    /// The status of the element isn't known.
+    Unknown,
+});
+#[cfg(feature = "server-support")]
+impl From<server::HealthStatus> for HealthStatus {
+    fn from(value: server::HealthStatus) -> Self {
+        match value {
+            server::HealthStatus::Up => Self::Up,
+            server::HealthStatus::Degraded => Self::Degraded,
+            server::HealthStatus::Down => Self::Down,
+            server::HealthStatus::Unknown => Self::Unknown,
+        }
+    }
+}
+api_type!(
+#[derive(Default)]
+/// The health of an element.
+struct Health {
+    /// The overall status.
+    status: HealthStatus,
+    /// Why the element has this status.
+    reason: Option<String>,
+});
+impl Health {
+    /// Whether the element is online (up or degraded).
+    #[must_use]
+    pub const fn is_online(&self) -> bool {
+        matches!(self.status, HealthStatus::Up | HealthStatus::Degraded)
+    }
+}
+#[cfg(feature = "server-support")]
+impl From<server::Health> for Health {
+    fn from(value: server::Health) -> Self {
+        Self {
+            status: value.status.into(),
+            reason: value.reason,
+        }
+    }
+}
 api_type!(
 /// The state of a single element.
 struct State {
-    /// Whether the element is currently online.
+    /// Whether the element is currently online (up or degraded).
     online: bool,
+    /// The health of the element.
+    health: Health,
     /// The attributes of the element.
     attributes: HashMap<String, AttributeValue>,
+    /// Whether the element keeps going online and offline.
+    #[serde(default)]
+    flapping: bool,
 });
 #[cfg(feature = "server-support")]
 impl From<server::State> for State {
     fn from(value: server::State) -> Self {
         Self {
-            online: value.online,
+            online: value.online(),
+            health: value.health.into(),
             attributes: value.attributes.into_iter()
                 .map(|(k, v)| (k, v.into()))
-                .collect()
+                .collect(),
+            flapping: value.flapping,
         }
     }
 }
@@ -171,9 +228,309 @@ impl From<HashMap<String, server::State>> for States {
             .collect())
     }
 }
+api_type!(
+/// A single recorded notification.
+struct HistoryEntry {
+    /// When the notification was sent out.
+    timestamp: chrono::DateTime<chrono::Utc>,
+    /// The notification (in the same format as it is sent via websockets).
+    message: websocket::Message,
+});
+#[cfg(feature = "server-support")]
+impl From<server::HistoryEntry> for HistoryEntry {
+    fn from(value: server::HistoryEntry) -> Self {
+        Self {
+            timestamp: value.timestamp,
+            message: value.notification.into(),
+        }
+    }
+}
+api_type!(
+/// Recorded notifications, ordered by the time they were sent out.
+struct History(Vec<HistoryEntry>)
+);
+#[cfg(feature = "server-support")]
+impl From<Vec<server::HistoryEntry>> for History {
+    fn from(value: Vec<server::HistoryEntry>) -> Self {
+        History(value.into_iter()
+            .map(Into::into)
+            .collect())
+    }
+}
+api_type!(
+/// Availability statistics of an element over a time frame.
+struct Uptime {
+    /// The start of the time frame.
+    start: chrono::DateTime<chrono::Utc>,
+    /// The end of the time frame.
+    end: chrono::DateTime<chrono::Utc>,
+    /// The fraction (`0.0..=1.0`) of the time frame the element was online (`None` if unknown).
+    uptime: Option<f32>,
+    /// How long the element was online.
+    online: chrono::Duration,
+    /// How long the element was offline.
+    offline: chrono::Duration,
+    /// The number of times the element went offline.
+    outages: usize,
+    /// The average time it took the element to come back online.
+    mean_time_to_recovery: Option<chrono::Duration>,
+});
+#[cfg(feature = "server-support")]
+impl From<server::Uptime> for Uptime {
+    fn from(value: server::Uptime) -> Self {
+        Self {
+            start: value.start,
+            end: value.end,
+            uptime: value.uptime,
+            online: value.online,
+            offline: value.offline,
+            outages: value.outages,
+            mean_time_to_recovery: value.mean_time_to_recovery,
+        }
+    }
+}
+api_type!(
+/// Uptime statistics of elements (Hashmap maps element_id -> time frame -> statistics).
+struct UptimeReport(HashMap<String, HashMap<String, Uptime>>)
+);
+api_type!(
+/// An outage of an element.
+struct Incident {
+    /// The unique id of the incident.
+    id: u64,
+    /// The id of the element that went offline.
+    element_id: String,
+    /// The id of the component that reported the element as offline.
+    component_id: String,
+    /// When the element went offline.
+    start: chrono::DateTime<chrono::Utc>,
+    /// When the element came back online (`None` while the incident is still open).
+    end: Option<chrono::DateTime<chrono::Utc>>,
+    /// Who acknowledged the incident (if anyone).
+    acknowledged: Option<Acknowledgement>,
+    /// Notes added to the incident.
+    notes: Vec<IncidentNote>,
+    /// How many steps of the escalation policy were taken.
+    #[serde(default)]
+    escalation_steps: usize,
+});
+api_type!(
+/// Marks that someone is taking care of an [`Incident`].
+struct Acknowledgement {
+    /// Who acknowledged the incident.
+    by: String,
+    /// When the incident was acknowledged.
+    timestamp: chrono::DateTime<chrono::Utc>,
+});
+api_type!(
+/// A note added to an [`Incident`].
+struct IncidentNote {
+    /// Who wrote the note.
+    author: String,
+    /// When the note was added.
+    timestamp: chrono::DateTime<chrono::Utc>,
+    /// The content of the note.
+    text: String,
+});
+#[cfg(feature = "server-support")]
+impl From<server::Incident> for Incident {
+    fn from(value: server::Incident) -> Self {
+        Self {
+            id: value.id,
+            element_id: value.element_id,
+            component_id: value.component_id,
+            start: value.start,
+            end: value.end,
+            acknowledged: value.acknowledged.map(|ack| Acknowledgement {
+                by: ack.by,
+                timestamp: ack.timestamp,
+            }),
+            notes: value.notes.into_iter()
+                .map(|note| IncidentNote {
+                    author: note.author,
+                    timestamp: note.timestamp,
+                    text: note.text,
+                })
+                .collect(),
+            escalation_steps: value.escalation_steps,
+        }
+    }
+}
+api_type!(
+/// A list of [`Incident`]s.
+struct Incidents(Vec<Incident>)
+);
+api_type!(
+/// Body for acknowledging an [`Incident`].
+struct AcknowledgeRequest {
+    /// Who acknowledges the incident.
+    by: String,
+});
+api_type!(
+/// Body for adding a note to an [`Incident`].
+struct NoteRequest {
+    /// Who writes the note.
+    author: String,
+    /// The content of the note.
+    text: String,
+});
+api_type!(
+/// A message of a notification provider waiting to be (or that ultimately failed to be) delivered.
+struct Delivery {
+    /// The unique id of the delivery.
+    id: u64,
+    /// The id of the provider that sends out the delivery.
+    provider: String,
+    /// The provider specific content of the delivery (as JSON).
+    payload: String,
+    /// When the delivery was queued.
+    created: chrono::DateTime<chrono::Utc>,
+    /// How often sending the delivery was attempted.
+    attempts: u32,
+    /// When the next attempt is due.
+    next_attempt: chrono::DateTime<chrono::Utc>,
+    /// Why the last attempt failed.
+    last_error: Option<String>,
+});
+#[cfg(feature = "server-support")]
+impl From<server::Delivery> for Delivery {
+    fn from(value: server::Delivery) -> Self {
+        Self {
+            id: value.id,
+            provider: value.provider,
+            payload: value.payload.to_string(),
+            created: value.created,
+            attempts: value.attempts,
+            next_attempt: value.next_attempt,
+            last_error: value.last_error,
+        }
+    }
+}
+api_type!(
+/// The delivery queue of the server.
+struct Deliveries {
+    /// Deliveries that are waiting to be sent (or retried).
+    pending: Vec<Delivery>,
+    /// Deliveries that ultimately failed.
+    dead: Vec<Delivery>,
+});
+api_type!(
+/// What reloading the config did to a single component (instance).
+enum ReloadResult {
+    /// The component was reconfigured with its new config.
+    Applied,
+    /// The config of the component didn't change.
+    Unchanged,
+    /// The component was added.
+    Added,
+    /// The component was removed.
+    Removed,
+    /// The new config of the component is invalid or the component couldn't be (re)configured.
+    Failed(String),
+});
+#[cfg(feature = "server-support")]
+impl From<server::ReloadResult> for ReloadResult {
+    fn from(value: server::ReloadResult) -> Self {
+        match value {
+            server::ReloadResult::Applied => Self::Applied,
+            server::ReloadResult::Unchanged => Self::Unchanged,
+            server::ReloadResult::Added => Self::Added,
+            server::ReloadResult::Removed => Self::Removed,
+            server::ReloadResult::Failed(e) => Self::Failed(e),
+        }
+    }
+}
+api_type!(
+/// The outcome of reloading the config.
+struct ReloadReport {
+    /// When the config was reloaded.
+    timestamp: chrono::DateTime<chrono::Utc>,
+    /// Whether the new config was applied (it is kept if anything is invalid).
+    applied: bool,
+    /// Why the new config wasn't applied (if it wasn't).
+    error: Option<String>,
+    /// What happened to each component (by instance name).
+    components: std::collections::BTreeMap<String, ReloadResult>,
+});
+#[cfg(feature = "server-support")]
+impl From<server::ReloadReport> for ReloadReport {
+    fn from(value: server::ReloadReport) -> Self {
+        Self {
+            timestamp: value.timestamp,
+            applied: value.applied,
+            error: value.error,
+            components: value.components.into_iter()
+                .map(|(instance, result)| (instance, result.into()))
+                .collect(),
+        }
+    }
+}
+api_type!(
+/// The dependencies between the loaded components.
+struct DependencyGraph {
+    /// The instances each loaded instance depends on (by instance name).
+    dependencies: std::collections::BTreeMap<String, Vec<String>>,
+    /// All loaded instances in the order they are initialized in (dependencies first). They are
+    /// shut down in the reverse order.
+    init_order: Vec<String>,
+});
+#[cfg(feature = "server-support")]
+impl From<server::DependencyGraph> for DependencyGraph {
+    fn from(value: server::DependencyGraph) -> Self {
+        Self {
+            dependencies: value.dependencies,
+            init_order: value.init_order,
+        }
+    }
+}
+api_type!(
+/// How well a notification provider keeps up with the published notifications. Counts are in
+/// batches of notifications (and escalations).
+struct SubscriberMetrics {
+    /// How many are waiting to be handed to the provider.
+    queued: usize,
+    /// The most that were waiting at once.
+    max_queued: usize,
+    /// How many were handed to the provider.
+    delivered: u64,
+    /// How often the queue was collapsed because the provider fell too far behind.
+    collapsed: u64,
+    /// How many notifications (not batches) were dropped when collapsing the queue.
+    dropped: u64,
+    /// How long the last one handed to the provider waited for it.
+    last_lag: chrono::Duration,
+    /// The longest any of them waited to be handed to the provider.
+    max_lag: chrono::Duration,
+});
+#[cfg(feature = "server-support")]
+impl From<server::SubscriberMetrics> for SubscriberMetrics {
+    fn from(value: server::SubscriberMetrics) -> Self {
+        Self {
+            queued: value.queued,
+            max_queued: value.max_queued,
+            delivered: value.delivered,
+            collapsed: value.collapsed,
+            dropped: value.dropped,
+            last_lag: value.last_lag,
+            max_lag: value.max_lag,
+        }
+    }
+}
+api_type!(
+/// How well each notification provider (by instance) keeps up with the published notifications.
+struct NotificationBus(std::collections::BTreeMap<String, SubscriberMetrics>)
+);
+#[cfg(feature = "server-support")]
+impl From<std::collections::BTreeMap<String, server::SubscriberMetrics>> for NotificationBus {
+    fn from(value: std::collections::BTreeMap<String, server::SubscriberMetrics>) -> Self {
+        Self(value.into_iter()
+            .map(|(instance, metrics)| (instance, metrics.into()))
+            .collect())
+    }
+}
 /// Types that are used when communicating via websockets.
 pub mod websocket {
-    use crate::AttributeValue;
+    use crate::{AttributeValue, Health};
 
     api_type!(
     /// A single websocket message.
@@ -186,22 +543,47 @@ pub mod websocket {
         reason: MessageReason
     });
     api_type!(
+    /// Multiple [`Message`]s that belong together (e.g. multiple attributes of an element changing at
+    /// once) and should be applied at once.
+    ///
+    /// Sent as a JSON array instead of a single [`Message`].
+    struct Batch(Vec<Message>)
+    );
+    api_type!(
     /// The reasons why a [`Message`] was sent.
     enum MessageReason {
         /// Something happened to the online status.
         OnlineStatus(OnlineStatusChange),
         /// Something happened to one of the attributes.
         Attribute(AttributeMessage),
+        /// The element started (`true`) or stopped (`false`) going online and offline repeatedly.
+        Flapping(bool),
+        /// An incident of the element was escalated (only recorded in the history).
+        Escalation(EscalationMessage),
+    });
+    api_type!(
+    /// infos about an escalated incident.
+    struct EscalationMessage {
+        /// The name of the escalation policy.
+        policy: String,
+        /// The index of the step that was taken (starting at 0).
+        step: usize,
+        /// The id of the incident.
+        incident: u64,
+        /// When the element went offline.
+        down_since: chrono::DateTime<chrono::Utc>,
+        /// The target of the step.
+        target: Option<String>,
     });
     api_type!(
-    /// Changes that can happen to the online status.
+    /// Changes that can happen to the online status (and health).
     enum OnlineStatusChange {
         /// It (the element) was deleted.
         Delete,
-        /// The element was created.
-        Create(bool),
-        /// The element was changed.
-        Change(bool),
+        /// The element was created with the given health.
+        Create(Health),
+        /// The health of the element changed.
+        Change(Health),
     });
     api_type!(
     /// infos for messages when an attribute was changed.
@@ -235,8 +617,17 @@ pub mod websocket {
     impl From<server::NotificationReason> for MessageReason {
         fn from(value: server::NotificationReason) -> Self {
             match value {
-                server::NotificationReason::OnlineStatusChanged(new) => Self::OnlineStatus(OnlineStatusChange::Change(new)),
-                server::NotificationReason::NewElement(state) => Self::OnlineStatus(OnlineStatusChange::Create(state)),
+                server::NotificationReason::HealthChanged(change) => Self::OnlineStatus(OnlineStatusChange::Change(change.new.into())),
+                server::NotificationReason::NewElement(health) => Self::OnlineStatus(OnlineStatusChange::Create(health.into())),
+                server::NotificationReason::FlappingStarted(_) => Self::Flapping(true),
+                server::NotificationReason::FlappingStopped(_) => Self::Flapping(false),
+                server::NotificationReason::Escalation(escalation) => Self::Escalation(EscalationMessage {
+                    policy: escalation.policy,
+                    step: escalation.step,
+                    incident: escalation.incident,
+                    down_since: escalation.down_since,
+                    target: escalation.target,
+                }),
                 server::NotificationReason::AttributeCreated(id, val) => Self::Attribute(AttributeMessage {
                     attribute_id: id,
                     change: AttributeChange::Create(val.into()),
@@ -248,7 +639,8 @@ pub mod websocket {
                 server::NotificationReason::DeleteAttribute(id, _) => Self::Attribute(AttributeMessage {
                     attribute_id: id,
                     change: AttributeChange::Delete,
-                })
+                }),
+                server::NotificationReason::DeleteElement(_) => Self::OnlineStatus(OnlineStatusChange::Delete),
             }
         }
     }
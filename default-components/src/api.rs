@@ -1,27 +1,43 @@
 use std::collections::HashMap;
 use axum::body::Body;
+use axum::http::Method;
+use axum::http::request::Parts;
 use utils::Never;
 use api_types::{ApiResponse, ServerError};
-use server::ComponentHandle;
+use server::{ComponentHandle, NotificationReason, Uptime};
 use crate::filters::{AttributeIdMatcher, SingleFilter};
 
 fn default_path() -> String { "api/".to_string() }
 
 
-#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+#[schemars(rename="ApiConfig")]
+/// Configuration of the [`Api`].
 pub struct Config {
     #[serde(default="default_path")]
+    /// The prefix of the API paths (mind the leading `/`).
     path: String,
 
     #[serde(default)]
     #[serde(alias="attribute-filter",
         alias="filter_attribute", alias="filter_attributes", alias="filter-attribute", alias="filter-attributes")]
+    #[schemars(extend("x-aliases" = ["attribute-filter", "filter_attribute", "filter_attributes", "filter-attribute", "filter-attributes"]))]
+    /// Filters the attributes that are included in the responses.
     attribute_filter: SingleFilter<AttributeIdMatcher>,
 
     #[serde(default)]
     #[serde(alias="element-filter",
         alias="filter_element", alias="filter_elements", alias="filter-element", alias="filter-elements")]
+    #[schemars(extend("x-aliases" = ["element-filter", "filter_element", "filter_elements", "filter-element", "filter-elements"]))]
+    /// Filters the elements based on their ids.
     element_filter: SingleFilter<String>,
+
+    #[serde(default)]
+    #[serde(alias="auth_token", alias="auth-token")]
+    #[schemars(extend("x-aliases" = ["auth_token", "auth-token"]))]
+    /// Bearer token required for modifying routes (e.g. acknowledging incidents).
+    /// Modifying routes are disabled if not set.
+    token: Option<String>,
 }
 impl Default for Config {
     fn default() -> Self {
@@ -29,16 +45,49 @@ impl Default for Config {
             path: default_path(),
             attribute_filter: SingleFilter::default(),
             element_filter: SingleFilter::default(),
+            token: None,
         }
     }
 }
 
+/// The maximum size of request bodies.
+const MAX_BODY_SIZE: usize = 64 * 1024;
+
+#[derive(Clone, Debug, Default, serde::Deserialize)]
+/// Query parameters of the `/history` and `/uptime` routes.
+///
+/// If neither `since` nor `until` are set, `/uptime` reports the [`Uptime::ROLLING_WINDOWS`].
+struct TimeFrameQuery {
+    #[serde(alias="element_id", alias="element-id", alias="id")]
+    element: Option<String>,
+    #[serde(alias="from", alias="start")]
+    since: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(alias="to", alias="end")]
+    until: Option<chrono::DateTime<chrono::Utc>>,
+}
+#[derive(Clone, Debug, Default, serde::Deserialize)]
+/// Query parameters of the `/incidents` route.
+struct IncidentQuery {
+    #[serde(alias="element_id", alias="element-id", alias="id")]
+    element: Option<String>,
+    /// only return open (`true`) or closed (`false`) incidents.
+    open: Option<bool>,
+}
+
 /// Provides an API for interacting with the status server.
 /// 
 /// Currently implemented:
 /// - [x] current state of all elements
 /// - [ ] current state of specific element
 /// - [ ] attribute of specific element
+/// - [x] history of all/specific elements (`/history?element=...&since=...&until=...`)
+/// - [x] uptime of all/specific elements (`/uptime?element=...&since=...&until=...`)
+/// - [x] incidents (`/incidents?element=...&open=...`, `/incidents/{id}`)
+/// - [x] acknowledging & annotating incidents (`POST /incidents/{id}/acknowledge`, `POST /incidents/{id}/notes`)
+/// - [x] delivery queue & dead letters of notification providers (`/deliveries`, `POST /deliveries/{id}/retry`)
+/// - [x] reloading the config (`/reload`, `POST /reload`)
+/// - [x] dependencies between the components (`/dependencies`)
+/// - [x] lag of the notification providers (`/bus`)
 pub struct Api {
     state: ComponentHandle,
     config: Config,
@@ -56,8 +105,54 @@ fn should_handle_path(mut path: &str, mut prefix: &str) -> bool {
     matches!(
         path,
         "" | "/" |
-        "/current"
-    )
+        "/current" |
+        "/history" |
+        "/uptime" |
+        "/incidents" |
+        "/deliveries" |
+        "/reload" |
+        "/dependencies" |
+        "/bus"
+    ) || path.starts_with("/incidents/") || path.starts_with("/deliveries/")
+}
+
+macro_rules! json {
+    ($code:expr, $val:expr) => {
+        match ::serde_json::to_string(&$val) {
+            Ok(v) => ($code, v),
+            Err(e) => {
+                error!("couldn't JSON-serialize response: {e}");
+                match ::serde_json::to_string(&ApiResponse::<(), ()>::ServerError(ServerError {
+                    id: "json.serialize".to_string(),
+                    message: e.to_string(),
+                })) {
+                    Ok(v) => (500, v),
+                    Err(e) => {
+                        error!("couldn't JSON serialize JSON serialization error?!?!? ({e})");
+                        (200, "{}".to_string())
+                    }
+                }
+            }
+        }
+    };
+}
+macro_rules! ok {
+    ($val:expr) => {
+        json!(200, ::api_types::ApiResponse::<_, ()>::Ok($val))
+    };
+}
+macro_rules! err {
+    ($code:literal, $val:expr) => {
+        json!($code, ::api_types::ApiResponse::<(), _>::ClientError($val))
+    };
+}
+macro_rules! exception {
+    ($id:literal, $msg:expr) => {
+        json!(500, ::api_types::ApiResponse::<(), ()>::ServerError(::api_types::ServerError {
+            id: $id.to_string(),
+            message: $msg.to_string()
+        }))
+    };
 }
 
 impl server::Component for Api {
@@ -66,7 +161,8 @@ impl server::Component for Api {
     type ConfigError = Never;
 
     fn init(server: ComponentHandle, config: Self::Config) -> Result<Self, Self::ConfigError> {
-        trace!("loaded API with config {config:?}");
+        // NOTE: not logging the config, as it contains the token.
+        trace!("loaded API at {}", config.path);
         Ok(Self {
             state: server,
             config,
@@ -79,45 +175,6 @@ impl server::Component for Api {
     }
 
     fn try_handle(&self, request: axum::extract::Request) -> Result<server::RequestHandle, axum::extract::Request> {
-        macro_rules! json {
-            ($code:expr, $val:expr) => {
-                match ::serde_json::to_string(&$val) {
-                    Ok(v) => ($code, v),
-                    Err(e) => {
-                        error!("couldn't JSON-serialize response: {e}");
-                        match ::serde_json::to_string(&ApiResponse::<(), ()>::ServerError(ServerError {
-                            id: "json.serialize".to_string(),
-                            message: e.to_string(),
-                        })) {
-                            Ok(v) => (500, v),
-                            Err(e) => {
-                                error!("couldn't JSON serialize JSON serialization error?!?!? ({e})");
-                                (200, "{}".to_string())
-                            }
-                        }
-                    }
-                }
-            };
-        }
-        macro_rules! ok {
-            ($val:expr) => {
-                json!(200, ::api_types::ApiResponse::<_, ()>::Ok($val))
-            };
-        }
-        #[expect(unused, reason="unused for now, might use it in the future for authenticated routes/etc.")]
-        macro_rules! err {
-            ($code:literal, $val:expr) => {
-                json!($code, ::api_types::ApiResponse::<(), _>::ClientError($val))
-            };
-        }
-        macro_rules! exception {
-            ($id:literal, $msg:expr) => {
-                json!(500, ::api_types::ApiResponse::<(), ()>::ServerError(::api_types::ServerError {
-                    id: $id.to_string(),
-                    message: $msg.to_string()
-                }))
-            };
-        }
         if !should_handle_path(request.uri().path(), &self.config.path) {
             return Err(request)
         }
@@ -125,22 +182,38 @@ impl server::Component for Api {
         let state = self.state.clone();
         let attribute_filter = self.config.attribute_filter.clone();
         let element_filter = self.config.element_filter.clone();
+        let token = self.config.token.clone();
         Ok(Box::pin(async move {
-            let path = &request.uri().path()[path_prefix_len..];
-            let _ = request;
+            let (parts, body) = request.into_parts();
+            let path = &parts.uri.path()[path_prefix_len..];
+            let time_frame_query = axum::extract::Query::<TimeFrameQuery>::try_from_uri(&parts.uri);
             let (code, json) = match path {
                 "/" => ok!("Welcome to the API!"),
                 "/current" => {
-                    ok!(api_types::States::from(state.get_states()
-                        .into_iter()
+                    ok!(api_types::States::from(state.states()
+                        .iter()
                         .filter(|(id, _)| element_filter.allows(id))
-                        .map(|(id, mut state)| {
+                        .map(|(id, state)| {
+                            let mut state = state.clone();
                             state.attributes.retain(|id, _| attribute_filter.allows(id));
-                            (id, state)
+                            (id.clone(), state)
                         })
                         .collect::<HashMap<_, _>>()
                     ))
                 },
+                "/history" => match time_frame_query {
+                    Ok(axum::extract::Query(query)) => ok!(history(&state, &query, &element_filter, &attribute_filter)),
+                    Err(e) => err!(400, format!("invalid query: {e}")),
+                },
+                "/uptime" => match time_frame_query {
+                    Ok(axum::extract::Query(query)) => ok!(uptime(&state, &query, &element_filter)),
+                    Err(e) => err!(400, format!("invalid query: {e}")),
+                },
+                _ if path.starts_with("/incidents") => incidents(&state, &parts, body, &path["/incidents".len()..], &element_filter, token.as_deref()).await,
+                _ if path.starts_with("/deliveries") => deliveries(&state, &parts, &path["/deliveries".len()..], token.as_deref()),
+                "/reload" => reload(&state, &parts, token.as_deref()),
+                "/dependencies" => dependencies(&state, &parts, token.as_deref()),
+                "/bus" => bus(&state, &parts, token.as_deref()),
                 // TODO: add routes for requesting selected elements/stati/etc.
                 _ => {
                     error!("route set to handle but no handle registered!");
@@ -150,11 +223,171 @@ impl server::Component for Api {
             axum::response::Response::builder()
                 .header("Content-Type", "application/json")
                 .header("Access-Control-Allow-Origin", "*")
-                .header("Access-Control-Allow-Methods", "GET")
+                .header("Access-Control-Allow-Methods", "GET, POST")
                 .header("Access-Control-Allow-Headers", "*")
                 .status(code)
                 .body(Body::new(json))
                 .expect("some argument failed to parse?")
         }))
     }
-}
\ No newline at end of file
+}
+fn history(state: &ComponentHandle, query: &TimeFrameQuery, element_filter: &SingleFilter<String>, attribute_filter: &SingleFilter<AttributeIdMatcher>) -> api_types::History {
+    api_types::History::from(state.get_history(query.element.as_deref(), query.since, query.until)
+        .into_iter()
+        .filter(|entry| element_filter.allows(&entry.notification.element_id))
+        .filter(|entry| match &entry.notification.reason {
+            NotificationReason::AttributeCreated(id, _) |
+            NotificationReason::AttributeChanged(id, _, _) |
+            NotificationReason::DeleteAttribute(id, _) => attribute_filter.allows(id),
+            _ => true,
+        })
+        .collect::<Vec<_>>()
+    )
+}
+fn uptime(state: &ComponentHandle, query: &TimeFrameQuery, element_filter: &SingleFilter<String>) -> api_types::UptimeReport {
+    let now = chrono::Utc::now();
+    let windows = if query.since.is_none() && query.until.is_none() {
+        Uptime::ROLLING_WINDOWS.iter()
+            .map(|(name, length)| ((*name).to_string(), now - *length, now))
+            .collect::<Vec<_>>()
+    } else {
+        let until = query.until.unwrap_or(now);
+        vec![("custom".to_string(), query.since.unwrap_or(until - chrono::Duration::days(30)), until)]
+    };
+    api_types::UptimeReport(state.states()
+        .ids()
+        .filter(|id| query.element.as_ref().is_none_or(|element| element == *id))
+        .filter(|id| element_filter.allows(id))
+        .map(|id| {
+            let report = windows.iter()
+                .map(|(name, since, until)| (name.clone(), state.get_uptime(id, *since, *until).into()))
+                .collect();
+            (id.clone(), report)
+        })
+        .collect()
+    )
+}
+/// Handles the `/incidents` routes.
+///
+/// `route` is the remaining path after `/incidents`.
+async fn incidents(state: &ComponentHandle, parts: &Parts, body: Body, route: &str, element_filter: &SingleFilter<String>, token: Option<&str>) -> (u16, String) {
+    let segments = route.split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+    let Some(id) = segments.first() else {
+        return match axum::extract::Query::<IncidentQuery>::try_from_uri(&parts.uri) {
+            Ok(axum::extract::Query(query)) => ok!(api_types::Incidents(state.get_incidents()
+                .into_iter()
+                .filter(|incident| element_filter.allows(&incident.element_id))
+                .filter(|incident| query.element.as_ref().is_none_or(|element| *element == incident.element_id))
+                .filter(|incident| query.open.is_none_or(|open| open == incident.is_open()))
+                .map(Into::into)
+                .collect()
+            )),
+            Err(e) => err!(400, format!("invalid query: {e}")),
+        };
+    };
+    let Some(incident) = id.parse::<u64>().ok()
+        .and_then(|id| state.get_incident(id))
+        .filter(|incident| element_filter.allows(&incident.element_id)) else {
+        return err!(404, format!("unknown incident `{id}`"));
+    };
+    match (&parts.method, segments.get(1).copied(), segments.len()) {
+        (&Method::GET, None, _) => ok!(api_types::Incident::from(incident)),
+        (&Method::POST, Some(action @ ("acknowledge" | "notes")), 2) => {
+            if !is_authorized(parts, token) {
+                return err!(401, "missing or invalid token".to_string());
+            }
+            let body = match axum::body::to_bytes(body, MAX_BODY_SIZE).await {
+                Ok(v) => v,
+                Err(e) => return err!(400, format!("couldn't read body: {e}")),
+            };
+            let updated = if action == "acknowledge" {
+                match serde_json::from_slice::<api_types::AcknowledgeRequest>(&body) {
+                    Ok(request) => state.acknowledge_incident(incident.id, &request.by),
+                    Err(e) => return err!(400, format!("invalid body: {e}")),
+                }
+            } else {
+                match serde_json::from_slice::<api_types::NoteRequest>(&body) {
+                    Ok(request) => state.add_incident_note(incident.id, &request.author, &request.text),
+                    Err(e) => return err!(400, format!("invalid body: {e}")),
+                }
+            };
+            match updated {
+                Some(incident) => ok!(api_types::Incident::from(incident)),
+                None => err!(404, format!("unknown incident `{id}`")),
+            }
+        },
+        _ => err!(404, format!("unknown route `{} /incidents{route}`", parts.method)),
+    }
+}
+/// Handles the `/deliveries` routes.
+///
+/// `route` is the remaining path after `/deliveries`.
+fn deliveries(state: &ComponentHandle, parts: &Parts, route: &str, token: Option<&str>) -> (u16, String) {
+    let segments = route.split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+    // NOTE: deliveries contain things like E-Mail addresses, so even reading them requires the token.
+    if !is_authorized(parts, token) {
+        return err!(401, "missing or invalid token".to_string());
+    }
+    match (&parts.method, segments.as_slice()) {
+        (&Method::GET, []) => {
+            let (pending, dead) = state.get_deliveries();
+            ok!(api_types::Deliveries {
+                pending: pending.into_iter().map(Into::into).collect(),
+                dead: dead.into_iter().map(Into::into).collect(),
+            })
+        },
+        (&Method::POST, [id, "retry"]) => {
+            match id.parse::<u64>().ok().and_then(|id| state.retry_delivery(id)) {
+                Some(delivery) => ok!(api_types::Delivery::from(delivery)),
+                None => err!(404, format!("unknown dead letter `{id}`")),
+            }
+        },
+        _ => err!(404, format!("unknown route `{} /deliveries{route}`", parts.method)),
+    }
+}
+/// Handles the `/reload` route.
+fn reload(state: &ComponentHandle, parts: &Parts, token: Option<&str>) -> (u16, String) {
+    if !is_authorized(parts, token) {
+        return err!(401, "missing or invalid token".to_string());
+    }
+    match parts.method {
+        Method::GET => match state.last_reload() {
+            Some(report) => ok!(api_types::ReloadReport::from(report)),
+            None => err!(404, "the config wasn't reloaded yet".to_string()),
+        },
+        Method::POST => ok!(api_types::ReloadReport::from(state.reload_config())),
+        _ => err!(404, format!("unknown route `{} /reload`", parts.method)),
+    }
+}
+/// Handles the `/dependencies` route.
+fn dependencies(state: &ComponentHandle, parts: &Parts, token: Option<&str>) -> (u16, String) {
+    if !is_authorized(parts, token) {
+        return err!(401, "missing or invalid token".to_string());
+    }
+    match parts.method {
+        Method::GET => ok!(api_types::DependencyGraph::from(state.dependency_graph())),
+        _ => err!(404, format!("unknown route `{} /dependencies`", parts.method)),
+    }
+}
+/// Handles the `/bus` route.
+fn bus(state: &ComponentHandle, parts: &Parts, token: Option<&str>) -> (u16, String) {
+    if !is_authorized(parts, token) {
+        return err!(401, "missing or invalid token".to_string());
+    }
+    match parts.method {
+        Method::GET => ok!(api_types::NotificationBus::from(state.notification_bus())),
+        _ => err!(404, format!("unknown route `{} /bus`", parts.method)),
+    }
+}
+/// Whether the request carries the configured bearer token.
+fn is_authorized(parts: &Parts, token: Option<&str>) -> bool {
+    let Some(token) = token else { return false; };
+    parts.headers.get(axum::http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|value| value == token)
+}
@@ -11,11 +11,15 @@ use crate::filters::Filter;
 
 fn default_path() -> String { "/api/ws".to_string() }
 
-#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+#[schemars(rename="WebsocketsConfig")]
+/// Configuration of the [`Websockets`].
 pub struct Config {
     #[serde(default="default_path")]
+    /// The path where the websockets are reachable (mind the leading `/`).
     path: String,
     #[serde(default)]
+    /// Filters the notifications sent via the websockets.
     filter: Filter,
 }
 impl Default for Config {
@@ -91,11 +95,24 @@ impl server::Component for Websockets {
     }
 }
 impl server::NotificationProvider for Websockets {
+    const RECEIVES_SUPPRESSED: bool = true;
     fn notify(&self, notification: Notification) {
+        self.notify_batch(vec![notification]);
+    }
+    fn notify_batch(&self, notifications: Vec<Notification>) {
         use axum::extract::ws::{Message, Utf8Bytes};
-        if !self.config.filter.allows(&notification) { return;}
+        use api_types::websocket::{Batch, Message as WsMessage};
+        let mut messages = notifications.into_iter()
+            .filter(|notification| self.config.filter.allows(notification))
+            .map(WsMessage::from)
+            .collect::<Vec<_>>();
+        let serialized = match messages.len() {
+            0 => return,
+            1 => serde_json::to_string(&messages.pop().expect("just checked the length")),
+            _ => serde_json::to_string(&Batch(messages)),
+        };
         let sockets = self.sockets.clone();
-        let message: Utf8Bytes = match serde_json::to_string(&api_types::websocket::Message::from(notification)) {
+        let message: Utf8Bytes = match serialized {
             Ok(v) => v,
             Err(e) => {
                 error!("couldn't serialize notification: {e}");
@@ -118,4 +135,4 @@ impl server::NotificationProvider for Websockets {
             }
         });
     }
-}
\ No newline at end of file
+}
@@ -2,14 +2,21 @@ use std::collections::HashMap;
 use axum::extract::Request;
 use tokio::time::MissedTickBehavior;
 use utils::Never;
-use server::{AttributeValue, Component, ComponentHandle, RequestHandle};
+use server::{AttributeValue, Component, ComponentHandle, Confirmation, LifecycleFuture, RequestHandle};
 
 
 const LAST_SEEN_ID: &str = "miner.last_seen";
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[derive(serde::Serialize, serde::Deserialize, schemars::JsonSchema, Clone, Debug, PartialEq)]
+#[schemars(rename="DataminerConfig")]
+/// Configuration of a single dataminer.
 pub struct Config {
+    /// How long to wait for a ping before the miner is marked as offline.
     timeout: chrono::Duration,
+    #[serde(default, alias="confirm")]
+    #[schemars(extend("x-aliases" = ["confirm"]))]
+    /// How often (or how long) a change of the online status has to be observed before it is applied.
+    confirmation: Confirmation,
 }
 
 /// [`Component`] for keeping track of dataminers.
@@ -20,6 +27,8 @@ pub struct DataminerStatus {
     config: HashMap<String, Config>,
     timeout_handles: HashMap<String, tokio::task::JoinHandle<()>>,
     server: ComponentHandle,
+    /// Whether the miners are checked for timeouts (see [`Component::start`]).
+    started: bool,
 }
 impl Component for DataminerStatus {
     const ID: &'static str = "miner";
@@ -27,14 +36,11 @@ impl Component for DataminerStatus {
     type ConfigError = Never;
 
     fn init(server: ComponentHandle, config: Self::Config) -> Result<Self, Self::ConfigError> {
-        let timeout_handles = config.iter()
-            .map(|(a, b)| (a.clone(), b.clone()))
-            .map(|(a, b)| (a.clone(), spawn_timeout_task(a, b, server.clone())))
-            .collect();
         Ok(Self {
             config,
-            timeout_handles,
+            timeout_handles: HashMap::new(),
             server,
+            started: false,
         })
     }
 
@@ -47,6 +53,7 @@ impl Component for DataminerStatus {
                 old.abort();
             }
             self.config.remove(&id);
+            self.server.queue_element_removal(&id);
         }
         for (id, new_config) in config.into_iter()
             .filter(|(id, new_conf)| self.config.get(id) != Some(new_conf))
@@ -54,23 +61,34 @@ impl Component for DataminerStatus {
         {
             let handle = self.server.clone();
             self.config.insert(id.clone(), new_config.clone());
+            if !self.started { continue; }
             if let Some(old) = self.timeout_handles.insert(id.clone(), spawn_timeout_task(id, new_config, handle)) {
                 old.abort();
             }
         }
         Ok(())
     }
+    fn start(&mut self) -> LifecycleFuture {
+        self.started = true;
+        self.timeout_handles = self.config.iter()
+            .map(|(id, config)| (id.clone(), spawn_timeout_task(id.clone(), config.clone(), self.server.clone())))
+            .collect();
+        Box::pin(async {})
+    }
+    fn shutdown(&mut self) -> LifecycleFuture {
+        self.started = false;
+        crate::stop_tasks(self.timeout_handles.drain().map(|(_, handle)| handle))
+    }
     fn try_handle(&self, request: Request) -> Result<RequestHandle, Request> {
         if !matches!(request.uri().path(), "/miner/ping") { return Err(request) }
         let Some(args) = request.uri().query() else { return Err(request) };
         if !args.starts_with("id=") || args.contains('&') { return Err(request) }
         let id = args["id=".len()..].to_string();
+        let confirmation = self.config.get(&id).map(|config| config.confirmation).unwrap_or_default();
         let server = self.server.clone();
         Ok(Box::pin(async move {
             server.change_attribute(&id, LAST_SEEN_ID, AttributeValue::Date(chrono::Utc::now()));
-            if !matches!(server.get_online_state(&id), Some(true)) {
-                server.change_online_state(&id, true);
-            }
+            server.change_health_confirmed(&id, true.into(), confirmation);
             axum::response::Response::builder()
                 .status(200)
                 .body(axum::body::Body::empty()).unwrap()
@@ -94,9 +112,10 @@ fn spawn_timeout_task(id: String, config: Config, handle: ComponentHandle) -> to
             let now = chrono::Utc::now();
             let now_std = std::time::Instant::now();
             let is_online = last_seen.is_some_and(|timestamp| (timestamp - now) > config.timeout);
-            if handle.get_online_state(&id) != Some(is_online) {
-                debug!("miner {id} changed to {}", if is_online { "online" } else { "offline" });
-                handle.change_online_state(&id, is_online);
+            let changed = handle.get_online_state(&id) != Some(is_online);
+            handle.change_health_confirmed(&id, is_online.into(), config.confirmation);
+            if changed {
+                debug!("miner {id} seems to be {}", if is_online { "online" } else { "offline" });
                 if let Some(last_ping) = last_seen {
                     let diff = now - last_ping;
                     let diff = diff.to_std().expect("last ping somehow after now?");
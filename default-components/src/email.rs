@@ -1,20 +1,33 @@
 use lettre::transport::smtp::authentication::Credentials;
 use utils::Never;
-use server::{Component, ComponentHandle, Notification, NotificationProvider, NotificationReason};
+use server::{Component, ComponentHandle, DeliveryFuture, Digest, LifecycleFuture, HealthStatus, Notification, NotificationProvider, NotificationReason, ReliableNotificationProvider};
 use crate::filters::Filter;
 
 fn default_name() -> String { "No Reply".to_string() }
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, Eq, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, schemars::JsonSchema, Clone, PartialEq, Eq, Debug)]
+#[schemars(rename="EmailConfig")]
+/// Configuration of the [`EmailNotificationProvider`].
 pub struct Config {
+    /// The address the E-Mails are sent from. Also used as the username for the E-Mail server.
     address: String,
+    /// The password of the E-Mail account.
     password: String,
+    /// The (SMTP) server the E-Mails are sent from.
     server: String,
     #[serde(default="default_name")]
+    /// The name of the mailbox the E-Mails are sent from.
     name: String,
     #[serde(alias="subscribed", alias="notify", alias="alert")]
+    #[schemars(extend("x-aliases" = ["subscribed", "notify", "alert"]))]
+    /// The addresses notifications are sent to.
     subscribers: Vec<Subscriber>,
+    /// Filters the notifications sent to any subscriber.
     filter: Filter,
+    #[serde(default, alias="digest-window", alias="digest_window")]
+    #[schemars(extend("x-aliases" = ["digest-window", "digest_window"]))]
+    /// Collects notifications over this window and sends a single summary per subscriber instead.
+    digest: Option<chrono::Duration>,
 }
 impl Default for Config {
     fn default() -> Self {
@@ -26,20 +39,31 @@ impl Default for Config {
             name: default_name(),
             subscribers: Vec::new(),
             filter: Filter::default(),
+            digest: None,
         }
     }
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, Eq, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, schemars::JsonSchema, Clone, PartialEq, Eq, Debug)]
+/// A single subscriber of the [`EmailNotificationProvider`].
 pub enum Subscriber {
     #[serde(untagged)]
+    /// An address that receives all notifications.
     Default(String),
     #[serde(untagged)]
+    /// An address with its own filter and digest window.
     Custom {
         #[serde(alias="address", alias="mail", alias="to")]
+        #[schemars(extend("x-aliases" = ["address", "mail", "to"]))]
+        /// The address notifications are sent to.
         email: String,
         #[serde(default)]
+        /// Filters the notifications sent to the subscriber.
         filter: Filter,
+        #[serde(default, alias="digest-window", alias="digest_window")]
+        #[schemars(extend("x-aliases" = ["digest-window", "digest_window"]))]
+        /// Overrides the digest window of the config.
+        digest: Option<chrono::Duration>,
     },
 }
 impl Subscriber {
@@ -49,6 +73,13 @@ impl Subscriber {
             Subscriber::Custom { email, .. } => email,
         }
     }
+    /// The digest window of the subscriber (falling back to the global one).
+    fn digest(&self, global: Option<chrono::Duration>) -> Option<chrono::Duration> {
+        match self {
+            Subscriber::Custom { digest: Some(digest), .. } => Some(*digest),
+            _ => global,
+        }
+    }
     fn allows(&self, notification: &Notification) -> bool {
         match self {
             Subscriber::Custom { filter, .. } => filter.allows(notification),
@@ -56,11 +87,23 @@ impl Subscriber {
         }
     }
 }
-#[derive(Clone, Debug)]
+/// A single E-Mail to be sent to a subscriber.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct Delivery {
+    to: String,
+    subject: String,
+    body: String,
+}
+#[derive(Clone)]
 /// [`NotificationProvider`] to send notifications via E-Mail.
+///
+/// E-Mails are sent via the delivery queue of the server, so failed E-Mails are retried.
+/// Subscribers with a digest window get a single summary per window instead.
 pub struct EmailNotificationProvider {
     config: Config,
     credentials: Credentials,
+    server: ComponentHandle,
+    digest: Digest,
 }
 
 impl Component for EmailNotificationProvider {
@@ -68,10 +111,12 @@ impl Component for EmailNotificationProvider {
     type Config = Config;
     type ConfigError = Never;
 
-    fn init(_: ComponentHandle, config: Self::Config) -> Result<Self, Self::ConfigError> {
+    fn init(server: ComponentHandle, config: Self::Config) -> Result<Self, Self::ConfigError> {
         Ok(Self {
             credentials: Credentials::new(config.address.clone(), config.password.clone()),
             config,
+            server,
+            digest: Digest::new(),
         })
     }
 
@@ -80,69 +125,158 @@ impl Component for EmailNotificationProvider {
         self.credentials = Credentials::new(self.config.address.clone(), self.config.password.clone());
         Ok(())
     }
+
+    /// Sends out the digests that are still collecting, so that they are delivered before the
+    /// server exits.
+    fn shutdown(&mut self) -> LifecycleFuture {
+        self.digest.flush();
+        Box::pin(async {})
+    }
 }
 impl NotificationProvider for EmailNotificationProvider {
     fn notify(&self, notification: Notification) {
         if !self.config.filter.allows(&notification) { return; }
-        let (subject, body) = match &notification.reason {
-            NotificationReason::OnlineStatusChanged(true) => (
+        let (subject, body) = compose(&notification);
+        for target in &self.config.subscribers {
+            if !target.allows(&notification) { continue; }
+            if let Some(window) = target.digest(self.config.digest) {
+                let server = self.server.clone();
+                let to = target.get_email().clone();
+                self.digest.collect(target.get_email(), notification.clone(), window, move |notifications| {
+                    server.queue_delivery::<Self>(&compose_digest(to, notifications));
+                });
+                continue;
+            }
+            self.server.queue_delivery::<Self>(&Delivery {
+                to: target.get_email().clone(),
+                subject: subject.clone(),
+                body: body.clone(),
+            });
+        }
+    }
+    /// Sends the escalation to the target address (or all subscribers if there is no target).
+    fn escalate(&self, notification: Notification, target: Option<&str>) {
+        let (subject, body) = compose(&notification);
+        let recipients = match target {
+            Some(address) => vec![address.to_string()],
+            None => self.config.subscribers.iter().map(|subscriber| subscriber.get_email().clone()).collect(),
+        };
+        for to in recipients {
+            self.server.queue_delivery::<Self>(&Delivery {
+                to,
+                subject: subject.clone(),
+                body: body.clone(),
+            });
+        }
+    }
+}
+/// Creates the subject and body of the E-Mail for the notification.
+fn compose(notification: &Notification) -> (String, String) {
+    let (subject, body) = match &notification.reason {
+        NotificationReason::HealthChanged(change) => match change.new.status {
+            HealthStatus::Up if change.old.is_online() => (
+                format!("{} recovered", notification.element_id),
+                format!(r"<h1> <code>{}</code> just recovered</h1>Everything is fine again", notification.element_id)
+            ),
+            HealthStatus::Up => (
                 format!("{} went online", notification.element_id),
                 format!(r"<h1> <code>{}</code> just went online</h1>Everything is fine", notification.element_id)
             ),
-            NotificationReason::OnlineStatusChanged(false) => (
+            HealthStatus::Degraded => (
+                format!("{} is degraded", notification.element_id),
+                format!(r"<h1><code>{}</code> is degraded</h1> {}", notification.element_id,
+                    change.new.reason.as_deref().unwrap_or("It is online, but something isn't quite right."))
+            ),
+            HealthStatus::Down => (
                 format!("{} went offline", notification.element_id),
                 format!(r"<h1><code>{}</code> just went offline!</h1> Go check up on it!", notification.element_id)
             ),
-            NotificationReason::AttributeCreated(attr, val) => (
-                format!("{} just got the attribute {}", notification.element_id, attr),
-                format!("The new value of <code>{}</code> for {} is: {}", attr, notification.element_id, val)
+            HealthStatus::Unknown => (
+                format!("{} has an unknown status", notification.element_id),
+                format!(r"<h1>The status of <code>{}</code> is unknown</h1> It might be worth checking up on it.", notification.element_id)
             ),
-            NotificationReason::AttributeChanged(id, old, new) => (
-                format!("{id} of {} just changed value", notification.element_id),
-                format!("{id} of {} just changed from {old} to {new}", notification.element_id)
-            ),
-            NotificationReason::DeleteAttribute(id, val) => (
-                format!("{id} of {} just got deleted", notification.element_id),
-                format!("{id} of {} just got deleted ({val})", notification.element_id)
-            ),
-            NotificationReason::NewElement(true) => (
-                format!("{} just got created (online)", notification.element_id),
-                format!("just got word that {} exists and is online.", notification.element_id)
-            ),
-            NotificationReason::NewElement(false) => (
-                format!("{} just got created (offline)", notification.element_id),
-                format!("just got word that {} exists and is offline.", notification.element_id)
-            ),
-        };
+        },
+        NotificationReason::AttributeCreated(attr, val) => (
+            format!("{} just got the attribute {}", notification.element_id, attr),
+            format!("The new value of <code>{}</code> for {} is: {}", attr, notification.element_id, val)
+        ),
+        NotificationReason::AttributeChanged(id, old, new) => (
+            format!("{id} of {} just changed value", notification.element_id),
+            format!("{id} of {} just changed from {old} to {new}", notification.element_id)
+        ),
+        NotificationReason::DeleteAttribute(id, val) => (
+            format!("{id} of {} just got deleted", notification.element_id),
+            format!("{id} of {} just got deleted ({val})", notification.element_id)
+        ),
+        NotificationReason::NewElement(health) => (
+            format!("{} just got created ({})", notification.element_id, health.status),
+            format!("just got word that {} exists and is {health}.", notification.element_id)
+        ),
+        NotificationReason::FlappingStarted(health) => (
+            format!("{} is flapping", notification.element_id),
+            format!(r"<h1><code>{}</code> keeps going online and offline!</h1> Notifications about its status are held back until it calms down. It is currently {health}.", notification.element_id)
+        ),
+        NotificationReason::FlappingStopped(health) => (
+            format!("{} stopped flapping", notification.element_id),
+            format!(r"<h1><code>{}</code> calmed down</h1> It is currently {health}.", notification.element_id)
+        ),
+        NotificationReason::DeleteElement(_) => (
+            format!("{} just got removed", notification.element_id),
+            format!("{} just got removed and won't be monitored anymore.", notification.element_id)
+        ),
+        NotificationReason::Escalation(escalation) => (
+            format!("[escalation] {} is still offline", notification.element_id),
+            format!(r"<h1><code>{}</code> is offline since {}!</h1> Nobody acknowledged the incident (#{}) yet, so it was escalated to you (step {} of {}).",
+                notification.element_id, escalation.down_since.format("%d.%m.%Y %H:%M:%S"), escalation.incident, escalation.step + 1, escalation.policy)
+        ),
+    };
+    let subject = if notification.maintenance { format!("[maintenance] {subject}") } else { subject };
+    (subject, body)
+}
+/// Creates a single E-Mail summarizing the notifications (grouped by element).
+fn compose_digest(to: String, notifications: Vec<Notification>) -> Delivery {
+    let count = notifications.len();
+    let grouped = Digest::group_by_element(notifications);
+    let subject = format!("{count} notifications for {} elements", grouped.len());
+    let mut body = format!("<h1>{count} notifications</h1>");
+    for (element_id, notifications) in grouped {
+        body.extend(["<h2><code>", &element_id, "</code></h2><ul>"]);
+        for notification in &notifications {
+            body.extend(["<li>", &compose(notification).0, "</li>"]);
+        }
+        body.push_str("</ul>");
+    }
+    Delivery {
+        to,
+        subject,
+        body,
+    }
+}
+impl ReliableNotificationProvider for EmailNotificationProvider {
+    type Delivery = Delivery;
+
+    fn deliver(&self, delivery: Self::Delivery) -> DeliveryFuture {
         let cloned = self.clone();
-        tokio::task::spawn(async move {
-            if let Err(e) = cloned.send_message(subject, &body, &notification) {
-                error!("error sending E-Mail: {e}");
-            }
-        });
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || cloned.send_message(delivery).map_err(|e| e.to_string()))
+                .await
+                .map_err(|e| e.to_string())?
+        })
     }
 }
 impl EmailNotificationProvider {
-    fn send_message(self,
-                    subject: String,
-                    body: &str,
-                    notification: &Notification) -> Result<(), Box<dyn std::error::Error>> {
+    fn send_message(self, delivery: Delivery) -> Result<(), Box<dyn std::error::Error>> {
         use lettre::Transport;
-        trace!("sending email: {:?}", body);
+        trace!("sending email to {}: {:?}", delivery.to, delivery.body);
         let mailer = lettre::transport::smtp::SmtpTransport::relay(&self.config.server)?
             .credentials(self.credentials)
             .build();
-        let builder_preset = lettre::Message::builder()
+        mailer.send(&lettre::Message::builder()
             .from(format!("{} <{}>", self.config.name, self.config.address).parse()?)
-            .subject(subject)
-            .header(lettre::message::header::ContentType::TEXT_HTML);
-        for target in self.config.subscribers {
-            if !target.allows(notification) { continue; }
-            trace!("sending email to {}", target.get_email());
-            mailer.send(&builder_preset.clone()
-                .to(target.get_email().parse()?)
-                .body(body.to_string())?)?;
-        }
+            .to(delivery.to.parse()?)
+            .subject(delivery.subject)
+            .header(lettre::message::header::ContentType::TEXT_HTML)
+            .body(delivery.body)?)?;
         Ok(())
     }
 }
@@ -1,16 +1,17 @@
 //! utilities to enable easier filtering of messages for [`server::StatusProvider`].
 
-use server::{Notification, NotificationReason};
+use server::{HealthStatus, Notification, NotificationReason};
 use std::hash::Hash;
 
 const fn always() -> bool { true }
 
-#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq, Default)]
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, schemars::JsonSchema, PartialEq, Eq, Default)]
 /// A fully configurable filter.
 ///
 /// Allows filtering for different components, entities & messages, down to the id of a changed attribute.
 pub struct Filter {
     #[serde(alias="components", alias="component-id", alias="component_id", alias="source", alias="source-id", alias="source_id")]
+    #[schemars(extend("x-aliases" = ["components", "component-id", "component_id", "source", "source-id", "source_id"]))]
     #[serde(default)]
     /// [`SingleFilter`] filtering the id of the component that caused the [`Notification`].
     pub component: SingleFilter<String>,
@@ -18,6 +19,7 @@ pub struct Filter {
     #[serde(default)]
     #[serde(alias="entities", alias="entity-id", alias="entity_id",
         alias="element", alias="elements", alias="element-id", alias="element_id")]
+    #[schemars(extend("x-aliases" = ["entities", "entity-id", "entity_id", "element", "elements", "element-id", "element_id"]))]
     /// [`SingleFilter`] filtering the id of the target entity of the [`Notification`].
     pub entity: SingleFilter<String>,
 
@@ -25,6 +27,7 @@ pub struct Filter {
         alias="state", alias="states",
         alias="status", alias="statuses", alias="stati",
         alias="change", alias="changes")]
+    #[schemars(extend("x-aliases" = ["state-change", "state", "states", "status", "statuses", "stati", "change", "changes"]))]
     /// [`SingleFilter`] filtering the state changes.
     pub state_changes: SingleFilter<StateChange>,
 }
@@ -38,17 +41,22 @@ impl Filter {
     }
 }
 
-#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, schemars::JsonSchema, PartialEq, Eq)]
+#[schemars(rename="SingleFilter_of_{ItemType}")]
 /// Filter filtering a single type of item.
 pub struct SingleFilter<ItemType> {
     #[serde(alias="allow", alias="allowed", alias="enable", alias="enabled", alias="whitelisted",
         alias="accept", alias="accepts", alias="accepted")]
+    #[schemars(extend("x-aliases" = ["allow", "allowed", "enable", "enabled", "whitelisted", "accept", "accepts", "accepted"]))]
     #[serde(default="Vec::new")]
+    #[schemars(default="Vec::<ItemType>::new")]
     /// Whitelist of things to specifically allow.
     pub whitelist: Vec<ItemType>,
     #[serde(alias="deny", alias="denied", alias="denies", alias="disable", alias="disabled", alias="blacklisted",
         alias="disallow", alias="disallowed", alias="disallows")]
+    #[schemars(extend("x-aliases" = ["deny", "denied", "denies", "disable", "disabled", "blacklisted", "disallow", "disallowed", "disallows"]))]
     #[serde(default="Vec::new")]
+    #[schemars(default="Vec::<ItemType>::new")]
     /// Blacklist of things to block.
     pub blacklist: Vec<ItemType>,
 
@@ -57,9 +65,10 @@ pub struct SingleFilter<ItemType> {
     /// Changes the behavior of the filter.
     #[serde(default)]
     #[serde(alias="default", alias="mode")]
+    #[schemars(extend("x-aliases" = ["default", "mode"]))]
     pub priority: FilterPriority,
 }
-#[derive(Clone, Copy, Debug, Default, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema, PartialEq, Eq)]
 /// What of the filter to prioritize.
 pub enum FilterPriority {
     /// Prioritizes the whitelist.
@@ -67,6 +76,7 @@ pub enum FilterPriority {
     /// This makes it so that the [`SingleFilter`] accepts values by default and only rejects values
     /// if they are in the blacklist.
     #[serde(alias="allow", alias="accept", alias="explicit-blacklist", alias="explicit_blacklist")]
+    #[schemars(extend("x-aliases" = ["allow", "accept", "explicit-blacklist", "explicit_blacklist"]))]
     #[default]
     Whitelist,
     /// Prioritizes the blacklist
@@ -74,6 +84,7 @@ pub enum FilterPriority {
     /// This makes it so that the [`SingleFilter`] accepts values by default and only accepts values
     /// if they are in the whitelist.
     #[serde(alias="disallow", alias="deny", alias="explicit-whitelist", alias="explicit_whitelist")]
+    #[schemars(extend("x-aliases" = ["disallow", "deny", "explicit-whitelist", "explicit_whitelist"]))]
     Blacklist
 }
 impl<T> Default for SingleFilter<T> {
@@ -85,39 +96,60 @@ impl<T> Default for SingleFilter<T> {
         }
     }
 }
-#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, schemars::JsonSchema, PartialEq, Eq, Hash)]
 #[serde(rename_all="snake_case")]
 /// Identifies a change in state for filtering.
 pub enum StateChange {
     #[serde(alias="create-entity", alias="create")]
+    #[schemars(extend("x-aliases" = ["create-entity", "create"]))]
     /// Matches the creation of an entity (no matter the new state. Use [`Self::OnlineStateChange`] for that.)
     CreateEntity,
 
+    #[serde(alias="delete-entity", alias="delete", alias="remove")]
+    #[schemars(extend("x-aliases" = ["delete-entity", "delete", "remove"]))]
+    /// Matches the removal of an entity.
+    DeleteEntity,
+
+    #[serde(alias="flap", alias="flaps")]
+    #[schemars(extend("x-aliases" = ["flap", "flaps"]))]
+    /// Matches an entity starting or stopping to flap (going online and offline repeatedly).
+    Flapping,
+
     #[serde(alias="attribute")]
+    #[schemars(extend("x-aliases" = ["attribute"]))]
     /// Matches changes to the attributes of an element. See [`AttributeChange`] for more infos.
     AttributeChange(AttributeChange),
 
     #[serde(alias="online", alias="online-state", alias="online_state")]
+    #[schemars(extend("x-aliases" = ["online", "online-state", "online_state"]))]
     /// matches changes to the online state.
     OnlineStateChange(OnlineStateChange)
 }
 
-#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, schemars::JsonSchema, PartialEq, Eq, Hash)]
 #[serde(rename_all="snake_case")]
-/// changes to the online state.
+/// changes to the online state (and health).
 pub enum OnlineStateChange {
     /// matches any online state change
     Any,
 
-    /// when the server went online
+    /// when the server went online (no matter whether it is up or degraded)
     #[serde(alias="up")]
+    #[schemars(extend("x-aliases" = ["up"]))]
     Online,
 
     /// when the server went offline
     #[serde(alias="down")]
+    #[schemars(extend("x-aliases" = ["down"]))]
     Offline,
+
+    /// when the server became degraded
+    Degraded,
+
+    /// when the health of the server became unknown
+    Unknown,
 }
-#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, schemars::JsonSchema, PartialEq, Eq, Hash)]
 /// Matches when attributes of an element change.
 pub struct AttributeChange {
     #[serde(flatten)]
@@ -127,7 +159,7 @@ pub struct AttributeChange {
     /// The actual element being matched.
     event: AttributeEvent,
 }
-#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq, Hash, Default)]
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, schemars::JsonSchema, PartialEq, Eq, Hash, Default)]
 /// Matches an AttributeId.
 pub struct AttributeIdMatcher {
     /// The id of the attribute.
@@ -137,7 +169,7 @@ pub struct AttributeIdMatcher {
     /// whether to match the id exactly (no children)
     exact: bool,
 }
-#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq, Hash, Default)]
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, schemars::JsonSchema, PartialEq, Eq, Hash, Default)]
 #[serde(rename_all="snake_case")]
 /// Events that can happen to an attribute.
 pub enum AttributeEvent {
@@ -184,23 +216,32 @@ impl<T: Eq> Filtering<T> for T {
         self == value
     }
 }
-impl Filtering<bool> for OnlineStateChange {
-    fn matches(&self, value: &bool) -> bool {
-        matches!((self, value),
-            (Self::Any, _) |
-            (Self::Online, true) |
-            (Self::Offline, false)
-        )
+impl Filtering<NotificationReason> for OnlineStateChange {
+    fn matches(&self, reason: &NotificationReason) -> bool {
+        let (old, new) = match reason {
+            NotificationReason::HealthChanged(change) => (Some(&change.old), &change.new),
+            NotificationReason::NewElement(health) => (None, health),
+            _ => return false,
+        };
+        // only match online/offline if the element actually went online/offline (and not e.g. from
+        // up to degraded).
+        let online_changed = old.is_none_or(|old| old.online() != new.online());
+        match self {
+            Self::Any => true,
+            Self::Online => online_changed && new.online() == Some(true),
+            Self::Offline => online_changed && new.online() == Some(false),
+            Self::Degraded => new.status == HealthStatus::Degraded,
+            Self::Unknown => new.status == HealthStatus::Unknown,
+        }
     }
 }
 impl Filtering<NotificationReason> for StateChange {
     fn matches(&self, reason: &NotificationReason) -> bool {
         match self {
-            Self::OnlineStateChange(filter)=> matches!(reason,
-                NotificationReason::OnlineStatusChanged(status) |
-                NotificationReason::NewElement(status)
-                if filter.matches(status)),
+            Self::OnlineStateChange(filter)=> filter.matches(reason),
             Self::CreateEntity => matches!(reason, NotificationReason::NewElement(_)),
+            Self::DeleteEntity => matches!(reason, NotificationReason::DeleteElement(_)),
+            Self::Flapping => matches!(reason, NotificationReason::FlappingStarted(_) | NotificationReason::FlappingStopped(_)),
             Self::AttributeChange(change) => match reason {
                 NotificationReason::AttributeCreated(id, _) |
                 NotificationReason::AttributeChanged(id, _, _) |
@@ -226,7 +267,7 @@ impl Filtering<String> for AttributeIdMatcher {
 }
 impl Filtering<NotificationReason> for AttributeEvent {
     fn matches(&self, value: &NotificationReason) -> bool {
-        !matches!(value, NotificationReason::NewElement(_) | NotificationReason::OnlineStatusChanged(_)) &&
+        matches!(value, NotificationReason::AttributeCreated(..) | NotificationReason::AttributeChanged(..) | NotificationReason::DeleteAttribute(..)) &&
             matches!((self, value),
                 (Self::Any, _) |
                 (Self::Create, NotificationReason::AttributeCreated(_, _)) |
@@ -12,8 +12,13 @@ impl Component for Frontend {
     type ConfigError = Never;
 
     fn init(handle: ComponentHandle, (): Self::Config) -> Result<Self, Self::ConfigError> {
-        handle.add_component_dependency::<Api>();
-        handle.add_notification_provider_dependency::<Websockets>();
+        // the frontend still serves its files without them, it just won't show anything.
+        if let Err(e) = handle.add_component_dependency::<Api>() {
+            error!("frontend: {e}");
+        }
+        if let Err(e) = handle.add_notification_provider_dependency::<Websockets>() {
+            error!("frontend: {e}");
+        }
         Ok(Self)
     }
 
@@ -9,6 +9,8 @@
 //! - [`MinecraftStatus`]: Component for keeping track of the status of minecraft servers.
 //! - [`EmailNotificationProvider`]: [`server::NotificationProvider`] for sending E-Mail notifications.
 //! - [`NtfyNotificationProvider`]: [`server::NotificationProvider`] for sending Push-Notifications via [NTFY](https://ntfy.sh/)
+//! - [`UptimeAttributes`]: Component for writing the uptime of elements back as attributes.
+//! - [`registry`]: all of the above that are enabled via features.
  
 
 #![cfg_attr(not(debug_assertions), deny(missing_docs))]
@@ -44,4 +46,54 @@ featured_use!(if "frontend": frontend::Frontend);
 featured_use!(if "dataminer-status": dataminer::DataminerStatus);
 featured_use!(if "minecraft-status": minecraft::MinecraftStatus);
 featured_use!(if "email-notifications": email::EmailNotificationProvider);
-featured_use!(if "ntfy-notifications": ntfy::NtfyNotificationProvider);
\ No newline at end of file
+featured_use!(if "ntfy-notifications": ntfy::NtfyNotificationProvider);
+featured_use!(if "uptime-attributes": uptime::UptimeAttributes);
+
+#[cfg(any(feature = "website-status", feature = "dataminer-status", feature = "minecraft-status", feature = "uptime-attributes"))]
+/// Aborts the tasks, returning a future that finishes once all of them stopped (for
+/// [`server::Component::shutdown`]).
+fn stop_tasks(handles: impl IntoIterator<Item=tokio::task::JoinHandle<()>>) -> server::LifecycleFuture {
+    let handles = handles.into_iter()
+        .inspect(tokio::task::JoinHandle::abort)
+        .collect::<Vec<_>>();
+    Box::pin(async move {
+        for handle in handles {
+            // the task was aborted, so there is no result (or panic) of interest.
+            let _ = handle.await;
+        }
+    })
+}
+
+/// Returns a [`ComponentRegistry`](server::ComponentRegistry) containing all default components
+/// that are enabled via features.
+#[must_use]
+pub fn registry() -> server::ComponentRegistry {
+    #[allow(unused_mut, reason="is only mutated when any component is selected.")]
+    let mut registry = server::ComponentRegistry::new();
+    macro_rules! component {
+        (if $feature:literal: $component:ident) => {
+            #[cfg(feature = $feature)]
+            registry.component::<$component>();
+        };
+        (if $feature:literal: notify $component:ident) => {
+            #[cfg(feature = $feature)]
+            registry.notification_provider::<$component>();
+        };
+        (if $feature:literal: notify reliable $component:ident) => {
+            #[cfg(feature = $feature)]
+            registry.reliable_notification_provider::<$component>();
+        };
+    }
+    component!(if "api": Api);
+    component!(if "websockets": notify Websockets);
+    component!(if "frontend": Frontend);
+
+    component!(if "ntfy-notifications": notify reliable NtfyNotificationProvider);
+    component!(if "email-notifications": notify reliable EmailNotificationProvider);
+
+    component!(if "dataminer-status": DataminerStatus);
+    component!(if "minecraft-status": MinecraftStatus);
+    component!(if "website-status": WebsiteStatuse);
+    component!(if "uptime-attributes": UptimeAttributes);
+    registry
+}
@@ -1,19 +1,30 @@
 use std::collections::HashMap;
 use tokio::time::MissedTickBehavior;
 use utils::Never;
-use server::{AttributeValue, Component, ComponentHandle};
+use server::{AttributeValue, Component, ComponentHandle, Confirmation, Health, HealthStatus, LifecycleFuture};
 
-#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+#[schemars(rename="MinecraftConfig")]
+/// Configuration of the [`MinecraftStatus`].
 pub struct Config {
+    /// The java servers to keep track of (by element id).
     pub java: HashMap<String, JavaConfig>,
 }
-#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema, PartialEq, Eq)]
+/// A single java server.
 pub struct JavaConfig {
+    /// The address of the server.
     pub url: String,
     #[serde(default="java_default_port")]
+    /// The port of the server.
     pub port: u16,
     #[serde(default="default_timeout")]
+    /// How often the server is pinged.
     pub interval: chrono::Duration,
+    #[serde(default, alias="confirm")]
+    #[schemars(extend("x-aliases" = ["confirm"]))]
+    /// How often (or how long) a change of the online status has to be observed before it is applied.
+    pub confirmation: Confirmation,
 }
 const fn java_default_port() -> u16 {
     25565
@@ -23,6 +34,8 @@ const fn default_timeout() -> chrono::Duration {
 }
 /// [`Component`] to allow keeping track of minecraft servers.
 ///
+/// Servers that are full are marked as [degraded](HealthStatus::Degraded).
+///
 /// # Attributes
 /// Sets the following attributes (if provided by the server):
 /// - `minecraft.version`: The version of the minecraft server
@@ -37,6 +50,8 @@ pub struct MinecraftStatus {
     config: Config,
     task_handles: HashMap<String, tokio::task::JoinHandle<()>>,
     state: ComponentHandle,
+    /// Whether the servers are pinged (see [`Component::start`]).
+    started: bool,
 }
 impl Component for MinecraftStatus {
     const ID: &'static str = "minecraft";
@@ -44,14 +59,11 @@ impl Component for MinecraftStatus {
     type ConfigError = Never;
 
     fn init(server: ComponentHandle, config: Self::Config) -> Result<Self, Self::ConfigError> {
-        let handles = config.java.iter()
-            .map(|(k, v)| (k.clone(), v.clone()))
-            .map(|(id, conf)| (id.clone(), start_ping(id, conf, server.clone())))
-            .collect();
         Ok(Self {
             config,
-            task_handles: handles,
+            task_handles: HashMap::new(),
             state: server,
+            started: false,
         })
     }
 
@@ -65,18 +77,32 @@ impl Component for MinecraftStatus {
                 old.abort();
             }
             self.config.java.remove(&id);
+            self.state.queue_element_removal(&id);
         }
         for (id, new_config) in config.java.into_iter()
             .filter(|(id, new_conf)| self.config.java.get(id) != Some(new_conf))
             .collect::<Vec<_>>()
         {
             self.config.java.insert(id.clone(), new_config.clone());
+            if !self.started { continue; }
             if let Some(old) = self.task_handles.insert(id.clone(), start_ping(id, new_config, self.state.clone())) {
                 old.abort();
             }
         }
         Ok(())
     }
+
+    fn start(&mut self) -> LifecycleFuture {
+        self.started = true;
+        self.task_handles = self.config.java.iter()
+            .map(|(id, conf)| (id.clone(), start_ping(id.clone(), conf.clone(), self.state.clone())))
+            .collect();
+        Box::pin(async {})
+    }
+    fn shutdown(&mut self) -> LifecycleFuture {
+        self.started = false;
+        crate::stop_tasks(self.task_handles.drain().map(|(_, handle)| handle))
+    }
 }
 fn start_ping(id: String, conf: JavaConfig, state: ComponentHandle) -> tokio::task::JoinHandle<()> {
     let mut ticker = tokio::time::interval(conf.interval.to_std().expect("unable to convert to std time"));
@@ -87,61 +113,59 @@ fn start_ping(id: String, conf: JavaConfig, state: ComponentHandle) -> tokio::ta
             ticker.tick().await;
 
             let response = send_ping(&conf.url, conf.port);
-            let is_ok = response.is_ok();
-            if state.get_online_state(&id) != Some(is_ok) {
-                state.change_online_state(&id, is_ok);
-            }
-
-            let Ok(response) = response else { continue; };
-            macro_rules! set_if_unchanged {
-                ($attr_id:literal, $val:expr) => {{
-                    let new_val = $val;
-                    if !matches!(state.get_attribute(&id, $attr_id), Some(v) if v == new_val) {
-                        state.change_attribute(&id, $attr_id, new_val);
+            state.update_element(&id, |update| {
+                update.confirm_with(conf.confirmation);
+                let Ok(response) = response else {
+                    update.set_online(false);
+                    return;
+                };
+                let full = response.players.as_ref()
+                    .is_some_and(|players| players.max > 0 && players.online >= players.max);
+                update.set_health(if full {
+                    Health::with_reason(HealthStatus::Degraded, "server is full")
+                } else {
+                    Health::from(true)
+                });
+                update.set_attribute("minecraft.last_seen", AttributeValue::Date(chrono::Utc::now()))
+                    .set_attribute("minecraft.version", AttributeValue::String(response.version.name))
+                    .set_attribute("minecraft.protocol.version", AttributeValue::Number(response.version.protocol.into()));
+                if let Some(players) = response.players {
+                    update.set_attribute("minecraft.players.max", AttributeValue::Number(players.max as i128))
+                        .set_attribute("minecraft.players.online", AttributeValue::Number(players.online as i128));
+                    if let Some(sample) = players.sample {
+                        update.set_attribute("minecraft.players.sample", AttributeValue::List(
+                            sample.into_iter()
+                            .map(|player| AttributeValue::String(player.name))
+                            .collect::<Vec<_>>()
+                        ));
+                    } else {
+                        update.delete_attribute("minecraft.players.sample", true);
                     }
-                }};
-            }
-            set_if_unchanged!("minecraft.last_seen", AttributeValue::Date(chrono::Utc::now()));
-            set_if_unchanged!("minecraft.version", AttributeValue::String(response.version.name));
-            set_if_unchanged!("minecraft.protocol.version", AttributeValue::Number(response.version.protocol.into()));
-            if let Some(players) = response.players {
-                set_if_unchanged!("minecraft.players.max", AttributeValue::Number(players.max as i128));
-                set_if_unchanged!("minecraft.players.online", AttributeValue::Number(players.online as i128));
-                if let Some(sample) = players.sample {
-                    set_if_unchanged!("minecraft.players.sample", AttributeValue::List(
-                        sample.into_iter()
-                        .map(|player| AttributeValue::String(player.name))
-                        .collect::<Vec<_>>()
-                    ));
                 } else {
-                    state.delete_attribute(&id, "minecraft.players.sample", true);
+                    update.delete_attribute("minecraft.players", false);
                 }
-            } else {
-                state.delete_attribute(&id, "state.players.max", true);
-                state.delete_attribute(&id, "state.players.online", true);
-                state.delete_attribute(&id, "state.players.sample", true);
-            }
-            if let Some(description) = response.description {
-                set_if_unchanged!("minecraft.description", AttributeValue::String(description));
-            } else {
-                state.delete_attribute(&id, "minecraft.description", true);
-            }
-            if let Some(favicon) = response.favicon {
-                set_if_unchanged!("minecraft.favicon", AttributeValue::String(favicon));
-            } else {
-                state.delete_attribute(&id, "minecraft.favicon", true);
-            }
-            if let Some(secure_chat) = response.enforcesSecureChat {
-                set_if_unchanged!("minecraft.enforces_secure_chat", AttributeValue::Boolean(secure_chat));
-                if secure_chat {
-                    set_if_unchanged!("minecraft.retarded", AttributeValue::Unit);
+                if let Some(description) = response.description {
+                    update.set_attribute("minecraft.description", AttributeValue::String(description));
                 } else {
-                    state.delete_attribute(&id, "minecraft.retarded", true);
+                    update.delete_attribute("minecraft.description", true);
                 }
-            } else {
-                state.delete_attribute(&id, "minecraft.enforces_secure_chat", true);
-                state.delete_attribute(&id, "minecraft.retarded", true);
-            }
+                if let Some(favicon) = response.favicon {
+                    update.set_attribute("minecraft.favicon", AttributeValue::String(favicon));
+                } else {
+                    update.delete_attribute("minecraft.favicon", true);
+                }
+                if let Some(secure_chat) = response.enforcesSecureChat {
+                    update.set_attribute("minecraft.enforces_secure_chat", AttributeValue::Boolean(secure_chat));
+                    if secure_chat {
+                        update.set_attribute("minecraft.retarded", AttributeValue::Unit);
+                    } else {
+                        update.delete_attribute("minecraft.retarded", true);
+                    }
+                } else {
+                    update.delete_attribute("minecraft.enforces_secure_chat", true)
+                        .delete_attribute("minecraft.retarded", true);
+                }
+            });
         }
     })
 }
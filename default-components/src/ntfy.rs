@@ -1,14 +1,19 @@
 use std::collections::HashMap;
 use utils::Never;
-use server::{ComponentHandle, Notification, NotificationReason};
+use server::{ComponentHandle, DeliveryFuture, Health, HealthChange, HealthStatus, Notification, NotificationReason};
 use crate::filters::Filter;
 
 fn default_message() -> String {
     "{element_id} {reason_long}".to_string()
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Default, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, schemars::JsonSchema, Default, Debug)]
+#[schemars(rename="NtfyConfig")]
+/// A single target of the [`NtfyNotificationProvider`].
+///
+/// The fields without a description are those of [ntfy's JSON messages](https://docs.ntfy.sh/publish/#publish-as-json).
 pub struct Config {
+    /// The url of the ntfy server.
     base: String,
     topic: String,
     title: Option<String>,
@@ -26,10 +31,20 @@ pub struct Config {
     email: Option<String>,
     call: Option<String>,
     #[serde(default)]
+    /// Filters the notifications sent to the target.
     filter: Filter,
+    /// Token for authenticated topics.
     auth_token: Option<String>,
+    #[serde(alias="id")]
+    #[schemars(extend("x-aliases" = ["id"]))]
+    /// The name of the target, used as the `target` of escalation steps (the topic can be used as well).
+    name: Option<String>,
+    #[serde(default, alias="escalation-only", alias="escalations-only")]
+    #[schemars(extend("x-aliases" = ["escalation-only", "escalations-only"]))]
+    /// Only send escalations to the target, no regular notifications.
+    escalation_only: bool,
 }
-#[derive(serde::Serialize, Clone, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
 struct NotificationBody {
     topic: String,
     #[serde(skip_serializing_if="Option::is_none")]
@@ -37,6 +52,7 @@ struct NotificationBody {
     #[serde(skip_serializing_if="Option::is_none")]
     title: Option<String>,
     #[serde(skip_serializing_if="Vec::is_empty")]
+    #[serde(default)]
     tags: Vec<String>,
     #[serde(skip_serializing_if="Option::is_none")]
     priority: Option<u8>,
@@ -76,18 +92,31 @@ impl From<&Config> for NotificationBody {
         }
     }
 }
+/// A single notification to be sent to an NTFY server.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct Delivery {
+    base: String,
+    #[serde(default)]
+    /// The index of the target in the config, used to look up its token (which isn't persisted).
+    target: Option<usize>,
+    body: NotificationBody,
+}
 /// [`NotificationProvider`] to send notifications via [NTFY](https://ntfy.sh).
+///
+/// Notifications are sent via the delivery queue of the server, so failed notifications are retried.
 pub struct NtfyNotificationProvider {
     config: Vec<Config>,
+    server: ComponentHandle,
 }
 impl server::Component for NtfyNotificationProvider {
     const ID: &'static str = "ntfy";
     type Config = Vec<Config>;
     type ConfigError = Never;
 
-    fn init(_: ComponentHandle, config: Self::Config) -> Result<Self, Self::ConfigError> {
+    fn init(server: ComponentHandle, config: Self::Config) -> Result<Self, Self::ConfigError> {
         Ok(Self {
             config,
+            server,
         })
     }
 
@@ -99,82 +128,163 @@ impl server::Component for NtfyNotificationProvider {
 
 impl server::NotificationProvider for NtfyNotificationProvider {
     fn notify(&self, notification: Notification) {
-        let format_values = HashMap::from([
-            ("component_id".to_string(), notification.component_id.clone()),
-            ("element_id".to_string(), notification.element_id.clone()),
-            ("reason_short".to_string(), match &notification.reason {
-                NotificationReason::OnlineStatusChanged(true) => "went online".to_string(),
-                NotificationReason::OnlineStatusChanged(false) => "went offline".to_string(),
-                NotificationReason::AttributeCreated(id, _) => format!("got attribute {id}"),
-                NotificationReason::AttributeChanged(id, _, _) => format!("attribute {id} changed"),
-                NotificationReason::DeleteAttribute(id, _) => format!("attribute {id} got deleted"),
-                NotificationReason::NewElement(true) => "created (online)".to_string(),
-                NotificationReason::NewElement(false) => "created (offline)".to_string(),
-            }),
-            ("reason_long".to_string(), match &notification.reason {
-                NotificationReason::OnlineStatusChanged(true) => "went online".to_string(),
-                NotificationReason::OnlineStatusChanged(false) => "went offline".to_string(),
-                NotificationReason::AttributeCreated(id, val) => format!("attribute {id} got created ({val})"),
-                NotificationReason::AttributeChanged(id, old, new) => format!("attribute {id} got changed ({old} => {new})"),
-                NotificationReason::DeleteAttribute(id, old) => format!("attribute {id} got deleted ({old})"),
-                NotificationReason::NewElement(true) => "got created and went online".to_string(),
-                NotificationReason::NewElement(false) => "got created and went offline".to_string(),
-            }),
-            ("attr_new_value".to_string(), match &notification.reason {
-                NotificationReason::AttributeCreated(_, val) |
-                NotificationReason::AttributeChanged(_, _, val)=> val.to_string(),
-                _ => String::new()
-            }),
-            ("attr_old_value".to_string(), match &notification.reason {
-                NotificationReason::AttributeChanged(_, val, _) |
-                NotificationReason::DeleteAttribute(_, val) => val.to_string(),
-                _ => String::new(),
-            }),
-            ("attr_id".to_string(), match &notification.reason {
-                NotificationReason::AttributeCreated(id, _) |
-                NotificationReason::AttributeChanged(id, _, _) |
-                NotificationReason::DeleteAttribute(id, _) => id.clone(),
-                _ => String::new(),
-            }),
-            ("status_new".to_string(), match &notification.reason {
-                NotificationReason::NewElement(true) |
-                NotificationReason::OnlineStatusChanged(true) => "online".to_string(),
-                NotificationReason::NewElement(false) |
-                NotificationReason::OnlineStatusChanged(false) => "offline".to_string(),
-                _ => String::new(),
-            }),
-            ("status_old".to_string(), match &notification.reason {
-                NotificationReason::NewElement(true) |
-                NotificationReason::OnlineStatusChanged(true) => "offline".to_string(),
-                NotificationReason::NewElement(false) |
-                NotificationReason::OnlineStatusChanged(false) => "online".to_string(),
-                _ => String::new(),
-            }),
-        ]);
+        let format_values = format_values(&notification);
         debug!("sending ntfy notification with format values: {:?}", format_values);
-        let client = reqwest::Client::new();
-        for config in &self.config {
-            use strfmt::Format;
-            if !config.filter.allows(&notification) {
+        for (target, config) in self.config.iter().enumerate() {
+            if config.escalation_only || !config.filter.allows(&notification) {
                 trace!("message filtered out through config");
                 continue;
             }
-            debug!("sending ntfy notification to {}", config.base);
-            let title = config.title.as_ref().map(|t| {
-                t.format(&format_values).unwrap_or_else(|_| t.clone())
-            });
-            let message = config.message.format(&format_values).unwrap_or_else(|_| config.message.clone());
+            self.send(target, config, &format_values);
+        }
+    }
+    /// Sends the escalation to the targets whose name (or topic) matches the target (or all targets
+    /// if there is none).
+    fn escalate(&self, notification: Notification, target: Option<&str>) {
+        let format_values = format_values(&notification);
+        let targets = self.config.iter()
+            .enumerate()
+            .filter(|(_, config)| target.is_none_or(|target| config.name.as_deref() == Some(target) || config.topic == target))
+            .collect::<Vec<_>>();
+        if targets.is_empty() {
+            warn!("no ntfy target matches the escalation target {target:?}");
+        }
+        for (target, config) in targets {
+            self.send(target, config, &format_values);
+        }
+    }
+}
+impl NtfyNotificationProvider {
+    /// Queues the notification for the target (with the given index).
+    fn send(&self, target: usize, config: &Config, format_values: &HashMap<String, String>) {
+        use strfmt::Format;
+        debug!("sending ntfy notification to {}", config.base);
+        let title = config.title.as_ref().map(|t| {
+            t.format(format_values).unwrap_or_else(|_| t.clone())
+        });
+        let message = config.message.format(format_values).unwrap_or_else(|_| config.message.clone());
+
+        let mut body = NotificationBody::from(config);
+        body.message = Some(message);
+        body.title = title;
+        trace!("finished ntfy notification: {:?}", body);
+        self.server.queue_delivery::<Self>(&Delivery {
+            base: config.base.clone(),
+            target: Some(target),
+            body,
+        });
+    }
+}
+/// The arguments available in the title & message.
+fn format_values(notification: &Notification) -> HashMap<String, String> {
+    HashMap::from([
+        ("component_id".to_string(), notification.component_id.clone()),
+        ("element_id".to_string(), notification.element_id.clone()),
+        ("reason_short".to_string(), match &notification.reason {
+            NotificationReason::HealthChanged(change) => health_change(Some(&change.old), &change.new),
+            NotificationReason::AttributeCreated(id, _) => format!("got attribute {id}"),
+            NotificationReason::AttributeChanged(id, _, _) => format!("attribute {id} changed"),
+            NotificationReason::DeleteAttribute(id, _) => format!("attribute {id} got deleted"),
+            NotificationReason::NewElement(health) => format!("created ({})", status_name(health)),
+            NotificationReason::FlappingStarted(_) => "is flapping".to_string(),
+            NotificationReason::FlappingStopped(_) => "stopped flapping".to_string(),
+            NotificationReason::DeleteElement(_) => "got removed".to_string(),
+            NotificationReason::Escalation(_) => "is still offline".to_string(),
+        }),
+        ("reason_long".to_string(), match &notification.reason {
+            NotificationReason::HealthChanged(change) => with_reason(health_change(Some(&change.old), &change.new), &change.new),
+            NotificationReason::AttributeCreated(id, val) => format!("attribute {id} got created ({val})"),
+            NotificationReason::AttributeChanged(id, old, new) => format!("attribute {id} got changed ({old} => {new})"),
+            NotificationReason::DeleteAttribute(id, old) => format!("attribute {id} got deleted ({old})"),
+            NotificationReason::NewElement(health) => with_reason(format!("got created and {}", health_change(None, health)), health),
+            NotificationReason::FlappingStarted(health) => with_reason(format!("keeps going online and offline (currently {})", status_name(health)), health),
+            NotificationReason::FlappingStopped(health) => with_reason(format!("stopped flapping (currently {})", status_name(health)), health),
+            NotificationReason::DeleteElement(_) => "got removed".to_string(),
+            NotificationReason::Escalation(escalation) => format!("is offline since {} and nobody acknowledged incident #{} (escalation step {} of {})",
+                escalation.down_since.format("%d.%m.%Y %H:%M:%S"), escalation.incident, escalation.step + 1, escalation.policy),
+        }),
+        ("attr_new_value".to_string(), match &notification.reason {
+            NotificationReason::AttributeCreated(_, val) |
+            NotificationReason::AttributeChanged(_, _, val)=> val.to_string(),
+            _ => String::new()
+        }),
+        ("attr_old_value".to_string(), match &notification.reason {
+            NotificationReason::AttributeChanged(_, val, _) |
+            NotificationReason::DeleteAttribute(_, val) => val.to_string(),
+            _ => String::new(),
+        }),
+        ("attr_id".to_string(), match &notification.reason {
+            NotificationReason::AttributeCreated(id, _) |
+            NotificationReason::AttributeChanged(id, _, _) |
+            NotificationReason::DeleteAttribute(id, _) => id.clone(),
+            _ => String::new(),
+        }),
+        ("status_new".to_string(), match &notification.reason {
+            NotificationReason::NewElement(health) => status_name(health).to_string(),
+            NotificationReason::HealthChanged(change) => status_name(&change.new).to_string(),
+            _ => String::new(),
+        }),
+        ("status_old".to_string(), match &notification.reason {
+            NotificationReason::NewElement(_) => status_name(&Health::default()).to_string(),
+            NotificationReason::HealthChanged(change) => status_name(&change.old).to_string(),
+            _ => String::new(),
+        }),
+        ("status_reason".to_string(), match &notification.reason {
+            NotificationReason::NewElement(health) |
+            NotificationReason::HealthChanged(HealthChange { new: health, .. }) => health.reason.clone().unwrap_or_default(),
+            _ => String::new(),
+        }),
+        ("maintenance".to_string(), if notification.maintenance { "in maintenance".to_string() } else { String::new() }),
+    ])
+}
+/// The name of the status used in the format values.
+const fn status_name(health: &Health) -> &'static str {
+    match health.status {
+        HealthStatus::Up => "online",
+        HealthStatus::Degraded => "degraded",
+        HealthStatus::Down => "offline",
+        HealthStatus::Unknown => "unknown",
+    }
+}
+/// Short description of the health change (e.g. "went online").
+fn health_change(old: Option<&Health>, new: &Health) -> String {
+    match new.status {
+        HealthStatus::Up if old.is_some_and(Health::is_online) => "recovered".to_string(),
+        HealthStatus::Up => "went online".to_string(),
+        HealthStatus::Degraded => "is degraded".to_string(),
+        HealthStatus::Down => "went offline".to_string(),
+        HealthStatus::Unknown => "has an unknown status".to_string(),
+    }
+}
+/// Appends the reason of the health (if any) to the text.
+fn with_reason(text: String, health: &Health) -> String {
+    match &health.reason {
+        Some(reason) => format!("{text} ({reason})"),
+        None => text,
+    }
+}
+impl server::ReliableNotificationProvider for NtfyNotificationProvider {
+    type Delivery = Delivery;
 
-            let mut body = NotificationBody::from(config);
-            body.message = Some(message.clone());
-            body.title = title;
-            trace!("finished ntfy notification: {:?}", body);
-            let mut request = client.post(&config.base)
-                .json(&body);
-            if let Some(token) = &config.auth_token {
+    fn deliver(&self, delivery: Self::Delivery) -> DeliveryFuture {
+        // NOTE: the token isn't persisted with the delivery, so it is taken from the current config.
+        //       The target may have changed since the delivery was queued, so it has to match.
+        let matches = |config: &&Config| config.base == delivery.base && config.topic == delivery.body.topic;
+        let token = delivery.target.and_then(|target| self.config.get(target))
+            .filter(matches)
+            .or_else(|| self.config.iter().find(matches))
+            .and_then(|config| config.auth_token.clone());
+        Box::pin(async move {
+            let mut request = reqwest::Client::new()
+                .post(&delivery.base)
+                .json(&delivery.body);
+            if let Some(token) = token {
                 request = request.bearer_auth(token);
             }
-            tokio::spawn(request.send());
-        }
+            request.send().await
+                .and_then(reqwest::Response::error_for_status)
+                .map_err(|e| e.to_string())?;
+            Ok(())
+        })
     }
 }
\ No newline at end of file
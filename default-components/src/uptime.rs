@@ -0,0 +1,116 @@
+use tokio::time::MissedTickBehavior;
+use utils::Never;
+use server::{AttributeValue, Component, ComponentHandle, LifecycleFuture, Uptime};
+use crate::filters::SingleFilter;
+
+const fn five_minutes() -> chrono::Duration { chrono::Duration::minutes(5) }
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+#[schemars(rename="UptimeConfig")]
+/// Configuration of the [`UptimeAttributes`].
+pub struct Config {
+    #[serde(default)]
+    /// Whether the attributes are written at all (the uptime is always available via the api).
+    enabled: bool,
+    #[serde(default="five_minutes")]
+    /// How often the attributes are updated.
+    interval: chrono::Duration,
+    #[serde(default)]
+    #[serde(alias="element-filter", alias="element_filter", alias="elements", alias="filter")]
+    #[schemars(extend("x-aliases" = ["element-filter", "element_filter", "elements", "filter"]))]
+    /// Filters the elements based on their ids.
+    element_filter: SingleFilter<String>,
+}
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: five_minutes(),
+            element_filter: SingleFilter::default(),
+        }
+    }
+}
+
+/// [`Component`] that periodically writes the uptime of the elements back as attributes, if
+/// enabled in its config.
+///
+/// The attributes are [computed](ComponentHandle::set_computed_attribute), so changing them neither
+/// notifies anyone nor is recorded in the history.
+///
+/// # Attributes
+/// Sets the following attributes (as long as the online status of the element is known):
+/// - `uptime.24h`: The uptime over the last 24 hours.
+/// - `uptime.7d`: The uptime over the last 7 days.
+/// - `uptime.30d`: The uptime over the last 30 days.
+pub struct UptimeAttributes {
+    config: Config,
+    /// The task updating the attributes (once started, see [`Component::start`], and if enabled).
+    task_handle: Option<tokio::task::JoinHandle<()>>,
+    started: bool,
+    state: ComponentHandle,
+}
+impl Component for UptimeAttributes {
+    const ID: &'static str = "uptime";
+    type Config = Config;
+    type ConfigError = Never;
+
+    fn init(server: ComponentHandle, config: Self::Config) -> Result<Self, Self::ConfigError> {
+        Ok(Self {
+            task_handle: None,
+            started: false,
+            config,
+            state: server,
+        })
+    }
+
+    fn reconfigure(&mut self, config: Self::Config) -> Result<(), Self::ConfigError> {
+        if self.config == config { return Ok(()); }
+        if let Some(old) = self.task_handle.take() {
+            old.abort();
+        }
+        if self.started && config.enabled {
+            self.task_handle = Some(spawn_update_task(config.clone(), self.state.clone()));
+        }
+        self.config = config;
+        Ok(())
+    }
+
+    fn start(&mut self) -> LifecycleFuture {
+        self.started = true;
+        if self.config.enabled {
+            self.task_handle = Some(spawn_update_task(self.config.clone(), self.state.clone()));
+        }
+        Box::pin(async {})
+    }
+    fn shutdown(&mut self) -> LifecycleFuture {
+        crate::stop_tasks(self.task_handle.take())
+    }
+}
+fn spawn_update_task(config: Config, state: ComponentHandle) -> tokio::task::JoinHandle<()> {
+    let mut ticker = tokio::time::interval(config.interval.to_std().expect("couldn't convert interval to std interval"));
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    tokio::spawn(async move {
+        loop {
+            ticker.tick().await;
+            let now = chrono::Utc::now();
+            let states = state.states();
+            for id in states.ids()
+                .filter(|id| config.element_filter.allows(id)) {
+                for (window, length) in Uptime::ROLLING_WINDOWS {
+                    let attr_id = format!("uptime.{window}");
+                    let new_val = state.get_uptime(id, now - length, now).uptime.map(AttributeValue::Percentage);
+                    if states.get(id).and_then(|element| element.attributes.get(&attr_id)) != new_val.as_ref() {
+                        state.set_computed_attribute(id, &attr_id, new_val);
+                    }
+                }
+            }
+        }
+    })
+}
+impl Drop for UptimeAttributes {
+    fn drop(&mut self) {
+        if let Some(handle) = &self.task_handle {
+            handle.abort();
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use std::collections::{HashMap};
 use tokio::time::MissedTickBehavior;
-use server::{AttributeValue, ComponentHandle};
+use server::{AttributeValue, ComponentHandle, Confirmation, Health, HealthStatus, LifecycleFuture};
 use utils::Never;
 use crate::filters::SingleFilter;
 
@@ -8,20 +8,35 @@ const LAST_SEEN_ID: &str = "website.last_seen";
 
 const fn hourly() -> chrono::Duration { chrono::Duration::hours(1) }
 
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+#[derive(serde::Serialize, serde::Deserialize, schemars::JsonSchema, Clone, Debug, PartialEq, Eq)]
+#[schemars(rename="WebsiteConfig")]
+/// Configuration of a single website.
 pub struct Config {
+    /// The url to request.
     url: String,
     #[serde(default="hourly")]
+    /// How often the url is requested.
     interval: chrono::Duration,
     #[serde(default)]
+    /// Filters the returned status codes. 200-299 are accepted unless they are blacklisted.
     status: SingleFilter<u16>,
+    #[serde(default, alias="slow", alias="slow-after", alias="degraded-after")]
+    #[schemars(extend("x-aliases" = ["slow", "slow-after", "degraded-after"]))]
+    /// Marks the website as degraded if responding takes longer than this.
+    slow_after: Option<chrono::Duration>,
+    #[serde(default, alias="confirm")]
+    #[schemars(extend("x-aliases" = ["confirm"]))]
+    /// How often (or how long) a change of the online status has to be observed before it is applied.
+    confirmation: Confirmation,
 }
 impl Default for Config {
     fn default() -> Self {
         Self {
             url: "https://example.com".to_string(),
             interval: hourly(),
-            status: SingleFilter::default()
+            status: SingleFilter::default(),
+            slow_after: None,
+            confirmation: Confirmation::default(),
         }
     }
 }
@@ -30,6 +45,8 @@ pub struct WebsiteStatuse {
     config: HashMap<String, Config>,
     task_handles: HashMap<String, tokio::task::JoinHandle<()>>,
     state: ComponentHandle,
+    /// Whether the websites are requested (see [`server::Component::start`]).
+    started: bool,
 }
 impl server::Component for WebsiteStatuse {
     const ID: &'static str = "website";
@@ -38,14 +55,10 @@ impl server::Component for WebsiteStatuse {
 
     fn init(server: ComponentHandle, config: Self::Config) -> Result<Self, Self::ConfigError> {
         Ok(Self {
-            task_handles: config.iter()
-                .map(|(a, b)| (a.clone(), b.clone()))
-                .map(|(id, config)| {
-                    let handle = server.clone();
-                    (id.clone(), spawn_listen_task(id, config, handle))
-                }).collect(),
             config,
+            task_handles: HashMap::new(),
             state: server,
+            started: false,
         })
     }
 
@@ -58,6 +71,7 @@ impl server::Component for WebsiteStatuse {
                 old.abort();
             }
             self.config.remove(&id);
+            self.state.queue_element_removal(&id);
         }
         for (id, new_config) in config.into_iter()
             .filter(|(id, new_conf)| self.config.get(id) != Some(new_conf))
@@ -65,12 +79,25 @@ impl server::Component for WebsiteStatuse {
         {
             let handle = self.state.clone();
             self.config.insert(id.clone(), new_config.clone());
+            if !self.started { continue; }
             if let Some(old) = self.task_handles.insert(id.clone(), spawn_listen_task(id, new_config, handle)) {
                 old.abort();
             }
         }
         Ok(())
     }
+
+    fn start(&mut self) -> LifecycleFuture {
+        self.started = true;
+        self.task_handles = self.config.iter()
+            .map(|(id, config)| (id.clone(), spawn_listen_task(id.clone(), config.clone(), self.state.clone())))
+            .collect();
+        Box::pin(async {})
+    }
+    fn shutdown(&mut self) -> LifecycleFuture {
+        self.started = false;
+        crate::stop_tasks(self.task_handles.drain().map(|(_, handle)| handle))
+    }
 }
 fn spawn_listen_task(id: String, config: Config, state: ComponentHandle) -> tokio::task::JoinHandle<()> {
     let mut ticker = tokio::time::interval(config.interval.to_std().expect("couldn't convert interval to std interval"));
@@ -79,19 +106,30 @@ fn spawn_listen_task(id: String, config: Config, state: ComponentHandle) -> toki
         let client = reqwest::Client::new();
         loop {
             ticker.tick().await;
+            let started = std::time::Instant::now();
             let new_status = matches!(request_website(&client, &config).await, Ok(true));
-            let old_state = state.get_online_state(&id);
-            trace!("old state: {old_state:?}; new_state: {new_status}");
-            if Some(new_status) != old_state {
-                info!("webserver {} has changed", id);
-                state.change_online_state(&id, new_status);
-            }
-            if new_status {
-                trace!("successfully requested {}", config.url);
-                state.change_attribute(&id, LAST_SEEN_ID, AttributeValue::Date(chrono::Utc::now()));
-            } else {
-                trace!("failed to request {}", config.url);
-            }
+            let slow = config.slow_after
+                .and_then(|slow_after| slow_after.to_std().ok())
+                .is_some_and(|slow_after| started.elapsed() > slow_after);
+            let new_health = match (new_status, slow) {
+                (false, _) => Health::from(false),
+                (true, true) => Health::with_reason(HealthStatus::Degraded, "slow response"),
+                (true, false) => Health::from(true),
+            };
+            state.update_element(&id, |update| {
+                let old_health = update.state().map(|state| &state.health);
+                trace!("old health: {old_health:?}; new health: {new_health}");
+                if Some(&new_health) != old_health {
+                    info!("webserver {} has changed", id);
+                }
+                update.set_health(new_health).confirm_with(config.confirmation);
+                if new_status {
+                    trace!("successfully requested {}", config.url);
+                    update.set_attribute(LAST_SEEN_ID, AttributeValue::Date(chrono::Utc::now()));
+                } else {
+                    trace!("failed to request {}", config.url);
+                }
+            });
         }
     })
 }
@@ -1,5 +1,8 @@
 use yew::Html;
-use api_types::AttributeValue;
+use api_types::{AttributeValue, HealthStatus};
+
+/// The attribute the server sets on elements that are under maintenance.
+const MAINTENANCE_ATTRIBUTE: &str = "maintenance";
 
 #[derive(Debug, Properties, PartialEq)]
 pub struct Props {
@@ -9,11 +12,38 @@ pub struct Props {
 
 #[function_component(ElementDisplay)]
 pub fn element_display(props: &Props) -> Html {
+    let maintenance = props.element.attributes.get(MAINTENANCE_ATTRIBUTE).map(|value| match value {
+        AttributeValue::String(description) => description.clone(),
+        _ => "under maintenance".to_string(),
+    });
+    let health = &props.element.health;
+    let status = match health.status {
+        HealthStatus::Up => "online",
+        HealthStatus::Degraded => "degraded",
+        HealthStatus::Down => "offline",
+        HealthStatus::Unknown => "unknown",
+    };
+    let status_title = match &health.reason {
+        Some(reason) => format!("{status}: {reason}"),
+        None => status.to_string(),
+    };
     html!{
-        <div class={if props.element.online {"element element-online"} else { "element element-offline" }}>
-            <h2><b class={if props.element.online { "status-online status" } else { "status-offline status" }}>{"⬤"}</b>{"   "}{&props.id}</h2>
+        <div class={classes!("element", format!("element-{status}"), maintenance.is_some().then_some("element-maintenance"))}>
+            <h2>
+                <b class={classes!("status", format!("status-{status}"))} title={status_title}>{"⬤"}</b>{"   "}{&props.id}
+                if let Some(reason) = &health.reason {
+                    <span class="health-reason">{reason}</span>
+                }
+                if props.element.flapping {
+                    <span class="flapping-badge" title="keeps going online and offline">{"flapping"}</span>
+                }
+                if let Some(description) = maintenance {
+                    <span class="maintenance-badge" title={description}>{"maintenance"}</span>
+                }
+            </h2>
             <div class="attributes">{
                 props.element.attributes.iter()
+                    .filter(|(id, _)| *id != MAINTENANCE_ATTRIBUTE)
                     .map(|(a, b)| (a.clone(), b.clone()))
                     .map(|(id, val)| html!(<AttributeDisplay id={id} value={val}/>))
                     .collect::<Html>()
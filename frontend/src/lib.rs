@@ -63,6 +63,7 @@ struct App {
 enum AppMessage {
     LoadedInitial(AppState),
     ReceivedMessage(api_types::websocket::Message),
+    ReceivedBatch(Vec<api_types::websocket::Message>),
 }
 impl yew::Component for App {
     type Message = AppMessage;
@@ -100,20 +101,32 @@ impl yew::Component for App {
                         let message = match message {
                             Message::Text(msg) => {
                                 trace!("received message: {msg}");
-                                serde_json::from_str::<api_types::websocket::Message>(&msg)
-                                    .expect("received an invalid message from the server!")
+                                // batches are sent as arrays of messages.
+                                if msg.trim_start().starts_with('[') {
+                                    serde_json::from_str::<api_types::websocket::Batch>(&msg)
+                                        .map(|batch| AppMessage::ReceivedBatch(batch.0))
+                                } else {
+                                    serde_json::from_str::<api_types::websocket::Message>(&msg)
+                                        .map(AppMessage::ReceivedMessage)
+                                }.expect("received an invalid message from the server!")
                             },
                             Message::Bytes(_) => {
                                 error!("received bytes message...");
                                 continue;
                             }
                         };
-                        link.send_message(AppMessage::ReceivedMessage(message));
+                        link.send_message(message);
                     }
                 });
             },
             AppMessage::ReceivedMessage(msg) => self.state.as_mut().expect("received websocket message before state was set")
-                .handle(msg)
+                .handle(msg),
+            AppMessage::ReceivedBatch(messages) => {
+                let state = self.state.as_mut().expect("received websocket message before state was set");
+                for msg in messages {
+                    state.handle(msg);
+                }
+            }
         }
         true
     }
@@ -1,17 +1,19 @@
-use api_types::AttributeValue;
+use api_types::{AttributeValue, Health};
 use std::collections::HashMap;
 use api_types::websocket::{AttributeChange, Message, MessageReason, OnlineStatusChange};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Element {
-    pub online: bool,
+    pub health: Health,
     pub attributes: HashMap<String, AttributeValue>,
+    pub flapping: bool,
 }
 impl From<api_types::State> for Element {
     fn from(value: api_types::State) -> Self {
         Self {
-            online: value.online,
+            health: value.health,
             attributes: value.attributes,
+            flapping: value.flapping,
         }
     }
 }
@@ -34,8 +36,9 @@ impl AppState {
             Some(e) => e,
             None => {
                 self.0.insert(message.element_id.clone(), Element {
-                    online: false,
+                    health: Health::default(),
                     attributes: HashMap::new(),
+                    flapping: false,
                 });
                 self.0.get_mut(&message.element_id).unwrap()
             }
@@ -48,9 +51,11 @@ impl Element {
         match msg {
             MessageReason::OnlineStatus(change) => match change {
                 OnlineStatusChange::Delete => {}
-                OnlineStatusChange::Create(state) |
-                OnlineStatusChange::Change(state) => self.online = state,
+                OnlineStatusChange::Create(health) |
+                OnlineStatusChange::Change(health) => self.health = health,
             }
+            MessageReason::Flapping(flapping) => self.flapping = flapping,
+            MessageReason::Escalation(_) => {},
             MessageReason::Attribute(change) => {
                 match change.change {
                     AttributeChange::Create(val) |
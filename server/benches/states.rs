@@ -0,0 +1,96 @@
+//! Compares copying all element states ([`ComponentHandle::get_states`]) with taking a snapshot of
+//! them ([`ComponentHandle::states`]), both on an idle server and while another thread keeps
+//! changing an element.
+//!
+//! Run with `cargo bench -p server --bench states`.
+use std::convert::Infallible;
+use std::hint::black_box;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use server::{AttributeValue, Component, ComponentHandle, Health, HealthStatus, Server};
+
+const ELEMENT_COUNTS: [usize; 4] = [10, 100, 1_000, 10_000];
+
+/// A component that does nothing but hand out its handle.
+struct Bench(ComponentHandle);
+impl Component for Bench {
+    const ID: &'static str = "bench";
+    type Config = ();
+    type ConfigError = Infallible;
+    fn init(server: ComponentHandle, (): Self::Config) -> Result<Self, Self::ConfigError> {
+        Ok(Self(server))
+    }
+    fn reconfigure(&mut self, (): Self::Config) -> Result<(), Self::ConfigError> {
+        Ok(())
+    }
+}
+
+/// Creates a server (that doesn't touch the disk) with `elements` elements, each with a few
+/// attributes, and returns the handle of the bench component.
+fn populated(runtime: &tokio::runtime::Runtime, elements: usize) -> ComponentHandle {
+    let config_path = std::env::temp_dir().join(format!("status-server-bench-{}.toml", std::process::id()));
+    std::fs::write(&config_path, "[storage]\nenabled = false\n\n[storage.history]\nenabled = false\n")
+        .expect("couldn't write the bench config");
+    let _guard = runtime.enter();
+    let server = Server::new(config_path.clone());
+    let _ = std::fs::remove_file(config_path);
+    server.add_component::<Bench>();
+    let handle = server.component_map::<Bench, _, _>(|bench| bench.expect("bench component is loaded").0.clone());
+    for i in 0..elements {
+        let id = format!("element-{i}");
+        handle.change_health(&id, Health::with_reason(HealthStatus::Up, "reachable"));
+        handle.change_attribute(&id, "players", AttributeValue::Count(i));
+        handle.change_attribute(&id, "uptime.day", AttributeValue::Percentage(99.9));
+        handle.change_attribute(&id, "version", AttributeValue::String(format!("1.{i}")));
+    }
+    handle
+}
+
+fn read_states(c: &mut Criterion, contended: bool) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .expect("couldn't create the runtime");
+    let mut group = c.benchmark_group(if contended { "read_states/contended" } else { "read_states/idle" });
+    for elements in ELEMENT_COUNTS {
+        let handle = populated(&runtime, elements);
+        let stop = Arc::new(AtomicBool::new(false));
+        let writer = contended.then(|| {
+            let handle = handle.clone();
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                let mut count = 0;
+                while !stop.load(Ordering::Relaxed) {
+                    count += 1;
+                    handle.change_attribute("element-0", "players", AttributeValue::Count(count));
+                }
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("copy", elements), &handle, |b, handle| {
+            b.iter(|| black_box(handle.get_states()));
+        });
+        group.bench_with_input(BenchmarkId::new("snapshot", elements), &handle, |b, handle| {
+            b.iter(|| black_box(handle.states()));
+        });
+        group.bench_with_input(BenchmarkId::new("snapshot_iter", elements), &handle, |b, handle| {
+            b.iter(|| black_box(handle.states().iter().filter(|(_, state)| state.online()).count()));
+        });
+        stop.store(true, Ordering::Relaxed);
+        if let Some(writer) = writer {
+            writer.join().expect("writer panicked");
+        }
+    }
+    group.finish();
+}
+
+fn idle(c: &mut Criterion) {
+    read_states(c, false);
+}
+
+fn contended(c: &mut Criterion) {
+    read_states(c, true);
+}
+
+criterion_group!(benches, idle, contended);
+criterion_main!(benches);
@@ -0,0 +1,322 @@
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::Instant;
+use parking_lot::Mutex;
+use tokio::sync::Notify;
+use crate::dependency::Node;
+use crate::notification::{Notification, NotificationReason};
+
+/// Something published on the [`NotificationBus`] for a single provider.
+pub(crate) enum Event {
+    /// Notifications that belong together (see
+    /// [`NotificationProvider::notify_batch`](crate::NotificationProvider::notify_batch)).
+    Notify(Vec<Notification>),
+    /// An escalation to the target (see
+    /// [`NotificationProvider::escalate`](crate::NotificationProvider::escalate)).
+    Escalate(Notification, Option<String>),
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+/// How well a notification provider keeps up with the notifications published on the bus (see
+/// [`ServerHandle::notification_bus`](crate::Server::notification_bus)).
+///
+/// Counts are in batches of notifications (and escalations).
+pub struct SubscriberMetrics {
+    /// How many are waiting to be handed to the provider.
+    pub queued: usize,
+    /// The most that were waiting at once.
+    pub max_queued: usize,
+    /// How many were handed to the provider.
+    pub delivered: u64,
+    /// How often the queue was collapsed because the provider fell too far behind (see
+    /// [`Config::bus`](crate::Config::bus)).
+    pub collapsed: u64,
+    /// How many notifications (not batches) were dropped when collapsing the queue.
+    pub dropped: u64,
+    /// How long the last one handed to the provider waited for it.
+    pub last_lag: chrono::Duration,
+    /// The longest any of them waited to be handed to the provider.
+    pub max_lag: chrono::Duration,
+}
+
+/// The bounded queue of a single notification provider, which is consumed by its own task.
+pub(crate) struct Subscriber {
+    node: Node,
+    receives_suppressed: bool,
+    queue: Mutex<Queue>,
+    wake: Notify,
+}
+#[derive(Default)]
+struct Queue {
+    /// The events waiting to be handed to the provider and when they were published.
+    events: VecDeque<(Instant, Event)>,
+    /// Whether an event is currently handed to the provider.
+    dispatching: bool,
+    /// Whether the provider was removed.
+    closed: bool,
+    metrics: SubscriberMetrics,
+}
+impl Subscriber {
+    /// The provider instance the events are for.
+    pub(crate) const fn node(&self) -> &Node {
+        &self.node
+    }
+    /// Queues the event, collapsing the queue once it holds more than `capacity` events (see
+    /// [`Queue::collapse`]).
+    fn push(&self, event: Event, capacity: usize) {
+        let mut queue = self.queue.lock();
+        if queue.closed { return; }
+        queue.events.push_back((Instant::now(), event));
+        if queue.events.len() > capacity.max(1) {
+            let dropped = queue.collapse();
+            queue.metrics.collapsed += 1;
+            queue.metrics.dropped += dropped;
+            warn!("notification provider {} fell behind; collapsed its queue, dropping {dropped} notifications",
+                self.node.instance);
+        }
+        queue.metrics.queued = queue.events.len();
+        queue.metrics.max_queued = queue.metrics.max_queued.max(queue.metrics.queued);
+        drop(queue);
+        self.wake.notify_one();
+    }
+    /// Waits for the next event, returning `None` once the provider was removed.
+    ///
+    /// [`Self::done`] has to be called once the event was handed to the provider.
+    pub(crate) async fn next(&self) -> Option<Event> {
+        loop {
+            {
+                let mut queue = self.queue.lock();
+                if queue.closed { return None; }
+                if let Some((published, event)) = queue.events.pop_front() {
+                    let lag = chrono::Duration::from_std(published.elapsed()).unwrap_or(chrono::Duration::MAX);
+                    queue.dispatching = true;
+                    queue.metrics.queued = queue.events.len();
+                    queue.metrics.last_lag = lag;
+                    queue.metrics.max_lag = queue.metrics.max_lag.max(lag);
+                    return Some(event);
+                }
+            }
+            self.wake.notified().await;
+        }
+    }
+    /// Marks the event returned by [`Self::next`] as handed to the provider.
+    pub(crate) fn done(&self) {
+        let mut queue = self.queue.lock();
+        queue.dispatching = false;
+        queue.metrics.delivered += 1;
+    }
+    fn close(&self) {
+        let mut queue = self.queue.lock();
+        queue.closed = true;
+        queue.events.clear();
+        queue.metrics.queued = 0;
+        drop(queue);
+        self.wake.notify_one();
+    }
+    fn is_idle(&self) -> bool {
+        let queue = self.queue.lock();
+        queue.closed || (queue.events.is_empty() && !queue.dispatching)
+    }
+}
+impl Queue {
+    /// Merges all queued batches into a single one (published when the oldest one was), returning
+    /// how many notifications were dropped.
+    ///
+    /// Changes of attributes are dropped, as well as all but the latest health change of each
+    /// element, so that no element misses the health it ended up with. Escalations are kept as they
+    /// are (after the merged batch).
+    fn collapse(&mut self) -> u64 {
+        let Some((published, _)) = self.events.front() else { return 0; };
+        let published = *published;
+        let mut notifications = Vec::new();
+        let mut escalations = VecDeque::new();
+        for (published, event) in self.events.drain(..) {
+            match event {
+                Event::Notify(batch) => notifications.extend(batch),
+                escalation @ Event::Escalate(..) => escalations.push_back((published, escalation)),
+            }
+        }
+        let count = notifications.len();
+        let mut latest_health = HashSet::new();
+        let mut kept = notifications.into_iter().rev()
+            .filter(|notification| match &notification.reason {
+                NotificationReason::AttributeCreated(..)
+                | NotificationReason::AttributeChanged(..)
+                | NotificationReason::DeleteAttribute(..) => false,
+                NotificationReason::HealthChanged(_) => latest_health.insert(notification.element_id.clone()),
+                _ => true,
+            })
+            .collect::<Vec<_>>();
+        kept.reverse();
+        let dropped = count - kept.len();
+        if !kept.is_empty() {
+            self.events.push_back((published, Event::Notify(kept)));
+        }
+        self.events.append(&mut escalations);
+        dropped as u64
+    }
+}
+
+#[derive(Default)]
+/// Hands published notifications to the notification providers without waiting for them.
+///
+/// Every provider has its own bounded queue (see [`Subscriber`]), so a slow provider neither
+/// blocks whoever publishes notifications nor the other providers.
+pub(crate) struct NotificationBus {
+    subscribers: Vec<Arc<Subscriber>>,
+    /// Subscribers that don't have a task consuming their queue yet.
+    unattended: Vec<Arc<Subscriber>>,
+}
+impl NotificationBus {
+    /// Adds a queue for the provider instance (unless it already has one).
+    pub(crate) fn subscribe(&mut self, node: Node, receives_suppressed: bool) {
+        if self.subscribers.iter().any(|subscriber| subscriber.node == node) { return; }
+        let subscriber = Arc::new(Subscriber { node, receives_suppressed, queue: Mutex::default(), wake: Notify::new() });
+        self.subscribers.push(subscriber.clone());
+        self.unattended.push(subscriber);
+    }
+    /// Removes the queue of the provider instance, stopping the task consuming it.
+    pub(crate) fn unsubscribe(&mut self, node: &Node) {
+        for subscriber in self.subscribers.iter().filter(|subscriber| subscriber.node == *node) {
+            subscriber.close();
+        }
+        self.subscribers.retain(|subscriber| subscriber.node != *node);
+        self.unattended.retain(|subscriber| subscriber.node != *node);
+    }
+    /// Returns the subscribers that need a task consuming their queue.
+    pub(crate) fn take_unattended(&mut self) -> Vec<Arc<Subscriber>> {
+        std::mem::take(&mut self.unattended)
+    }
+    /// Queues the notifications (and whether each is suppressed) for every provider.
+    pub(crate) fn publish(&self, notifications: &[(Notification, bool)], capacity: usize) {
+        for subscriber in &self.subscribers {
+            let batch = notifications.iter()
+                .filter(|(_, suppressed)| !suppressed || subscriber.receives_suppressed)
+                .map(|(notification, _)| notification.clone())
+                .collect::<Vec<_>>();
+            if batch.is_empty() { continue; }
+            subscriber.push(Event::Notify(batch), capacity);
+        }
+    }
+    /// Queues the escalation for the provider instance, returning whether it is subscribed.
+    pub(crate) fn escalate(&self, instance: &str, notification: Notification, target: Option<&str>, capacity: usize) -> bool {
+        let Some(subscriber) = self.subscribers.iter().find(|subscriber| subscriber.node.instance == instance) else {
+            return false;
+        };
+        subscriber.push(Event::Escalate(notification, target.map(str::to_string)), capacity);
+        true
+    }
+    /// Whether every provider was handed everything published for it.
+    pub(crate) fn is_idle(&self) -> bool {
+        self.subscribers.iter().all(|subscriber| subscriber.is_idle())
+    }
+    /// The metrics of every provider (by instance).
+    pub(crate) fn metrics(&self) -> BTreeMap<String, SubscriberMetrics> {
+        self.subscribers.iter()
+            .map(|subscriber| (subscriber.node.instance.clone(), subscriber.queue.lock().metrics.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::any::TypeId;
+    use super::*;
+    use crate::notification::HealthChange;
+    use crate::state::AttributeValue;
+
+    fn bus(instances: &[(&str, bool)]) -> (NotificationBus, Vec<Arc<Subscriber>>) {
+        let mut bus = NotificationBus::default();
+        for (instance, receives_suppressed) in instances {
+            bus.subscribe(Node::new(instance, TypeId::of::<()>()), *receives_suppressed);
+        }
+        let subscribers = bus.take_unattended();
+        (bus, subscribers)
+    }
+    fn attribute(element_id: &str, value: usize) -> Notification {
+        Notification::new("test".to_string(), element_id.to_string(), NotificationReason::AttributeCreated("players".to_string(), AttributeValue::Count(value)))
+    }
+    fn health(element_id: &str, online: bool) -> Notification {
+        Notification::new("test".to_string(), element_id.to_string(), NotificationReason::HealthChanged(HealthChange {
+            old: (!online).into(),
+            new: online.into(),
+        }))
+    }
+    /// Takes all events queued for the subscriber, describing each batch by the notifications in it.
+    fn drain(subscriber: &Subscriber) -> Vec<Vec<String>> {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().expect("couldn't create the runtime");
+        let mut events = Vec::new();
+        while subscriber.queue.lock().metrics.queued > 0 {
+            let event = runtime.block_on(subscriber.next()).expect("subscriber isn't closed");
+            subscriber.done();
+            events.push(match event {
+                Event::Notify(batch) => batch.iter().map(describe).collect(),
+                Event::Escalate(notification, target) => vec![format!("escalate {} to {target:?}", notification.element_id)],
+            });
+        }
+        events
+    }
+    fn describe(notification: &Notification) -> String {
+        match &notification.reason {
+            NotificationReason::HealthChanged(change) => format!("{} {}", notification.element_id, change.new),
+            NotificationReason::AttributeCreated(_, value) => format!("{} {value}", notification.element_id),
+            other => format!("{} {other:?}", notification.element_id),
+        }
+    }
+
+    #[test]
+    fn batches_are_handed_over_in_order() {
+        let (bus, subscribers) = bus(&[("a", false)]);
+        bus.publish(&[(attribute("x", 1), false), (attribute("y", 1), false)], 10);
+        bus.publish(&[(attribute("x", 2), false)], 10);
+        assert!(bus.escalate("a", health("x", false), Some("admin"), 10));
+        assert!(!bus.escalate("b", health("x", false), None, 10));
+        assert!(!bus.is_idle());
+        assert_eq!(drain(&subscribers[0]), [
+            vec!["x 1".to_string(), "y 1".to_string()],
+            vec!["x 2".to_string()],
+            vec!["escalate x to Some(\"admin\")".to_string()],
+        ]);
+        assert!(bus.is_idle());
+        assert_eq!(bus.metrics()["a"].delivered, 3);
+    }
+
+    #[test]
+    fn providers_have_their_own_queues() {
+        let (bus, subscribers) = bus(&[("a", false), ("b", true)]);
+        bus.publish(&[(attribute("x", 1), false), (attribute("y", 1), true)], 10);
+        bus.publish(&[(attribute("z", 1), true)], 10);
+        assert_eq!(drain(&subscribers[0]), [vec!["x 1".to_string()]]);
+        // b still has everything, including the suppressed notifications.
+        assert_eq!(bus.metrics()["b"].queued, 2);
+        assert_eq!(drain(&subscribers[1]), [vec!["x 1".to_string(), "y 1".to_string()], vec!["z 1".to_string()]]);
+    }
+
+    #[test]
+    fn overflow_keeps_latest_health_of_each_element() {
+        let (bus, subscribers) = bus(&[("a", false), ("b", false)]);
+        bus.publish(&[(health("x", false), false), (attribute("x", 1), false)], 3);
+        bus.publish(&[(health("y", false), false)], 3);
+        assert!(bus.escalate("a", health("x", false), None, 3));
+        bus.publish(&[(health("x", true), false), (attribute("x", 2), false)], 3);
+        let metrics = &bus.metrics()["a"];
+        assert_eq!((metrics.collapsed, metrics.dropped, metrics.queued), (1, 3, 2));
+        assert_eq!(drain(&subscribers[0]), [
+            vec!["y down".to_string(), "x up".to_string()],
+            vec!["escalate x to None".to_string()],
+        ]);
+        // the queue of b was never full.
+        assert_eq!(bus.metrics()["b"].collapsed, 0);
+        assert_eq!(drain(&subscribers[1]).len(), 3);
+    }
+
+    #[test]
+    fn unsubscribing_closes_the_queue() {
+        let (mut bus, subscribers) = bus(&[("a", false)]);
+        bus.publish(&[(attribute("x", 1), false)], 10);
+        bus.unsubscribe(&Node::new("a", TypeId::of::<()>()));
+        let runtime = tokio::runtime::Builder::new_current_thread().build().expect("couldn't create the runtime");
+        assert!(runtime.block_on(subscribers[0].next()).is_none());
+        assert!(bus.is_idle());
+    }
+}
@@ -4,6 +4,9 @@ use crate::ComponentHandle;
 
 /// A pinned future that handles the given request.
 pub type RequestHandle = Pin<Box<dyn Future<Output=axum::response::Response> + Send >>;
+/// A pinned future finishing a lifecycle hook of a component (see [`Component::start`] &
+/// [`Component::shutdown`]).
+pub type LifecycleFuture = Pin<Box<dyn Future<Output=()> + Send>>;
 
 #[async_trait::async_trait]
 /// A single Component in a server.
@@ -15,14 +18,42 @@ pub trait Component: Sized + Send + Sync + 'static {
     
     /// The type of the config.
     /// 
-    /// The original toml config will be further parsed down into this.
-    type Config: serde::Serialize + for<'de> serde::Deserialize<'de> + Default;
+    /// The original toml config will be further parsed down into this. Its [`JsonSchema`](schemars::JsonSchema)
+    /// is part of the [schema of the whole config](crate::ComponentRegistry::config_schema). As
+    /// schemars ignores `#[serde(alias)]`, aliases have to be repeated via
+    /// `#[schemars(extend("x-aliases" = [...]))]` for the schema to accept them.
+    type Config: serde::Serialize + for<'de> serde::Deserialize<'de> + Default + schemars::JsonSchema;
     /// Errors that can occur while configuring the server.
     type ConfigError: core::error::Error;
     /// Initialize the [`Component`] with the given server & config.
     fn init(server: ComponentHandle, config: Self::Config) -> Result<Self, Self::ConfigError>;
     /// trigger a reconfiguration of the component.
     fn reconfigure(&mut self, config: Self::Config) -> Result<(), Self::ConfigError>;
+    /// Starts the component once the server runs (see [`ServerHandle::start`](crate::Server::start)),
+    /// e.g. spawning the tasks it needs. Components added to a running server are started right
+    /// away.
+    ///
+    /// Components are started after their dependencies. The returned future is spawned.
+    ///
+    /// # Note
+    /// The server is locked while this is called (but not while the returned future runs), so
+    /// anything interacting with the server has to happen in the future.
+    fn start(&mut self) -> LifecycleFuture {
+        Box::pin(async {})
+    }
+    /// Stops the component when it is removed or the server shuts down (see
+    /// [`ServerHandle::shutdown`](crate::Server::shutdown)), e.g. stopping its tasks. Only called
+    /// if the component was started.
+    ///
+    /// When the server shuts down, components are stopped before their dependencies and the
+    /// returned future is awaited (up to the configured timeout) before the final snapshot is
+    /// written, so it can finish in-flight work.
+    ///
+    /// # Note
+    /// The server is locked while this is called (but not while the returned future runs).
+    fn shutdown(&mut self) -> LifecycleFuture {
+        Box::pin(async {})
+    }
     #[expect(clippy::result_large_err, reason="The error here isn't actually an error, but just the request if we fail to parse it.")]
     /// Try to handle the request sent to the server.
     /// 
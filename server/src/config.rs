@@ -1,22 +1,337 @@
 use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use crate::maintenance::MaintenanceWindow;
+use crate::flapping::FlappingConfig;
+use crate::escalation::EscalationPolicy;
 
-#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+mod load;
+
+const fn always() -> bool { true }
+
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
 /// Configuration for the server.
 pub struct Config {
     #[serde(flatten)]
+    #[schemars(skip)]
     /// Map of all the configs for the different [`crate::Component`]s.
     pub configs: HashMap<String, toml::Value>,
 
+    #[serde(alias="instance")]
+    #[schemars(extend("x-aliases" = ["instance"]))]
+    #[serde(default)]
+    /// The names of the instances of the [`crate::Component`]s (by their id).
+    ///
+    /// Components without an entry have a single instance named after their id.
+    pub instances: HashMap<String, Vec<String>>,
+
     #[serde(alias="ignore", alias="disabled", alias="disable")]
+    #[schemars(extend("x-aliases" = ["ignore", "disabled", "disable"]))]
     #[serde(default)]
     /// The things that the server ignores completely.
-    pub ignored: Ignored
+    pub ignored: Ignored,
+
+    #[serde(alias="persistence")]
+    #[schemars(extend("x-aliases" = ["persistence"]))]
+    #[serde(default)]
+    /// Where and how the server persists data across restarts.
+    pub storage: Storage,
+
+    #[serde(alias="maintenance-windows", alias="maintenance_windows")]
+    #[schemars(extend("x-aliases" = ["maintenance-windows", "maintenance_windows"]))]
+    #[serde(default)]
+    /// Time frames in which elements are under maintenance.
+    pub maintenance: Vec<MaintenanceWindow>,
+
+    #[serde(alias="flap-detection", alias="flap_detection", alias="flaps")]
+    #[schemars(extend("x-aliases" = ["flap-detection", "flap_detection", "flaps"]))]
+    #[serde(default)]
+    /// How elements that keep going online and offline are detected.
+    pub flapping: FlappingConfig,
+
+    #[serde(alias="escalations", alias="escalation-policies", alias="escalation_policies")]
+    #[schemars(extend("x-aliases" = ["escalations", "escalation-policies", "escalation_policies"]))]
+    #[serde(default)]
+    /// How open incidents that nobody acknowledges are escalated.
+    pub escalation: Vec<EscalationPolicy>,
+
+    #[serde(alias="watch", alias="auto-reload", alias="auto_reload")]
+    #[schemars(extend("x-aliases" = ["watch", "auto-reload", "auto_reload"]))]
+    #[serde(default)]
+    /// Whether and how the config is reloaded when the config file changes.
+    pub reload: ReloadConfig,
+
+    #[serde(alias="deliveries", alias="retry")]
+    #[schemars(extend("x-aliases" = ["deliveries", "retry"]))]
+    #[serde(default)]
+    /// How notifications of reliable providers are retried.
+    pub delivery: DeliveryConfig,
+
+    #[serde(alias="graceful-shutdown", alias="graceful_shutdown")]
+    #[schemars(extend("x-aliases" = ["graceful-shutdown", "graceful_shutdown"]))]
+    #[serde(default)]
+    /// How the server shuts down (e.g. on `SIGTERM`).
+    pub shutdown: ShutdownConfig,
+
+    #[serde(alias="notification-bus", alias="notification_bus")]
+    #[schemars(extend("x-aliases" = ["notification-bus", "notification_bus"]))]
+    #[serde(default)]
+    /// How notifications are handed to the notification providers.
+    pub bus: BusConfig,
+}
+impl Config {
+    /// Loads the config from the file, returning it together with all files (and directories) it
+    /// was read from.
+    ///
+    /// Files can include other files (or all `*.toml` files of a directory, ordered by name) via
+    /// `include = ["path", ...]` (relative to the including file). Tables of included files are
+    /// merged and arrays are concatenated (in the order they are included); setting any other value
+    /// more than once is an error.
+    ///
+    /// Afterward, references in all strings are resolved (so that secrets don't have to be written
+    /// into the config files):
+    /// - `${NAME}` is replaced with the value of the environment variable `NAME`
+    /// - `${file:path}` is replaced with the content of the file (without a trailing newline).
+    ///   Relative paths are resolved against `$CREDENTIALS_DIRECTORY` (if set).
+    /// - `$${` is a literal `${`
+    pub fn load(path: &Path) -> Result<(Self, Vec<PathBuf>), String> {
+        use serde::Deserialize;
+        let (table, files) = Self::read(path)?;
+        let config = Self::deserialize(toml::Value::Table(table))
+            .map_err(|e| e.to_string())?;
+        Ok((config, files))
+    }
+    /// Reads the config like [`Self::load`] (including files and resolving references), but returns
+    /// the merged table instead of parsing it.
+    pub fn read(path: &Path) -> Result<(toml::Table, Vec<PathBuf>), String> {
+        let (mut table, files) = load::read_files(path)?;
+        for (key, value) in &mut table {
+            load::resolve_references(value, key)?;
+        }
+        Ok((table, files))
+    }
+    /// The names of all instances of the [`crate::Component`] with the given id.
+    ///
+    /// Named instances are called `<id>.<name>` and configured in the `<name>` table of the
+    /// component's config.
+    #[must_use]
+    pub fn instances_of(&self, id: &str) -> Vec<String> {
+        match self.instances.get(id) {
+            Some(names) => names.iter().map(|name| format!("{id}.{name}")).collect(),
+            None => vec![id.to_string()],
+        }
+    }
+    /// The config of the instance with the given name (see [`Self::instances_of`]).
+    #[must_use]
+    pub fn config_of(&self, instance: &str) -> Option<&toml::Value> {
+        match instance.split_once('.') {
+            Some((id, name)) => self.configs.get(id)?.get(name),
+            None => self.configs.get(instance),
+        }
+    }
+    /// Whether the instance is ignored, either by its own name or by the id of its component.
+    #[must_use]
+    pub fn is_ignored(&self, instance: &str) -> bool {
+        self.ignored.components.contains(instance)
+            || instance.split_once('.').is_some_and(|(id, _)| self.ignored.components.contains(id))
+    }
 }
-#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
 /// Ignored values 
 pub struct Ignored {
     #[serde(default)]
-    /// ignores all components with the given id.
+    /// ignores all components (or single instances) with the given id.
     pub components: HashSet<String>,
 
-}
\ No newline at end of file
+}
+fn default_state_file() -> PathBuf { PathBuf::from("states.json") }
+const fn default_snapshot_interval() -> chrono::Duration { chrono::Duration::seconds(30) }
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
+/// Configuration for persisting data to disk.
+pub struct Storage {
+    #[serde(default="always")]
+    /// Whether the element states, incidents and deliveries are persisted at all.
+    pub enabled: bool,
+
+    #[serde(alias="states", alias="state-file", alias="states-file", alias="states_file")]
+    #[schemars(extend("x-aliases" = ["states", "state-file", "states-file", "states_file"]))]
+    #[serde(default="default_state_file")]
+    /// The file the element states are written to and restored from on startup.
+    pub state_file: PathBuf,
+
+    #[serde(alias="snapshot-interval", alias="interval")]
+    #[schemars(extend("x-aliases" = ["snapshot-interval", "interval"]))]
+    #[serde(default="default_snapshot_interval")]
+    /// How often the element states are written to disk (if they changed in the meantime).
+    pub snapshot_interval: chrono::Duration,
+
+    #[serde(alias="incidents", alias="incidents-file", alias="incident-file", alias="incident_file")]
+    #[schemars(extend("x-aliases" = ["incidents", "incidents-file", "incident-file", "incident_file"]))]
+    #[serde(default="default_incidents_file")]
+    /// The file the incidents are written to and restored from on startup.
+    pub incidents_file: PathBuf,
+
+    #[serde(alias="incident-retention", alias="incidents-retention", alias="incidents_retention")]
+    #[schemars(extend("x-aliases" = ["incident-retention", "incidents-retention", "incidents_retention"]))]
+    #[serde(default="default_incident_retention")]
+    /// How long closed incidents are kept (after they were closed).
+    pub incident_retention: chrono::Duration,
+
+    #[serde(alias="deliveries", alias="deliveries-file", alias="delivery-file", alias="delivery_file")]
+    #[schemars(extend("x-aliases" = ["deliveries", "deliveries-file", "delivery-file", "delivery_file"]))]
+    #[serde(default="default_deliveries_file")]
+    /// The file the delivery queue & dead letters are written to and restored from on startup.
+    pub deliveries_file: PathBuf,
+
+    #[serde(default)]
+    /// Configuration for recording the history of all notifications.
+    pub history: HistoryConfig,
+}
+impl Default for Storage {
+    fn default() -> Self {
+        Self {
+            enabled: always(),
+            state_file: default_state_file(),
+            snapshot_interval: default_snapshot_interval(),
+            incidents_file: default_incidents_file(),
+            incident_retention: default_incident_retention(),
+            deliveries_file: default_deliveries_file(),
+            history: HistoryConfig::default(),
+        }
+    }
+}
+fn default_incidents_file() -> PathBuf { PathBuf::from("incidents.json") }
+const fn default_incident_retention() -> chrono::Duration { chrono::Duration::days(90) }
+fn default_deliveries_file() -> PathBuf { PathBuf::from("deliveries.json") }
+fn default_history_file() -> PathBuf { PathBuf::from("history.jsonl") }
+const fn default_retention() -> chrono::Duration { chrono::Duration::days(30) }
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
+/// Configuration for the history of notifications.
+pub struct HistoryConfig {
+    #[serde(default="always")]
+    /// Whether notifications are recorded at all.
+    pub enabled: bool,
+
+    #[serde(alias="path")]
+    #[schemars(extend("x-aliases" = ["path"]))]
+    #[serde(default="default_history_file")]
+    /// The file the history is written to and restored from on startup.
+    pub file: PathBuf,
+
+    #[serde(alias="keep", alias="max-age", alias="max_age")]
+    #[schemars(extend("x-aliases" = ["keep", "max-age", "max_age"]))]
+    #[serde(default="default_retention")]
+    /// How long recorded notifications are kept.
+    pub retention: chrono::Duration,
+}
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: always(),
+            file: default_history_file(),
+            retention: default_retention(),
+        }
+    }
+}
+const fn default_max_attempts() -> u32 { 8 }
+const fn default_initial_backoff() -> chrono::Duration { chrono::Duration::seconds(10) }
+const fn default_max_backoff() -> chrono::Duration { chrono::Duration::hours(1) }
+const fn default_dead_letter_limit() -> usize { 1000 }
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
+/// Configuration for retrying deliveries of reliable notification providers.
+pub struct DeliveryConfig {
+    #[serde(alias="max-attempts", alias="attempts")]
+    #[schemars(extend("x-aliases" = ["max-attempts", "attempts"]))]
+    #[serde(default="default_max_attempts")]
+    /// How often sending a delivery is attempted before it is moved to the dead letters.
+    pub max_attempts: u32,
+
+    #[serde(alias="initial-backoff", alias="backoff")]
+    #[schemars(extend("x-aliases" = ["initial-backoff", "backoff"]))]
+    #[serde(default="default_initial_backoff")]
+    /// How long to wait before the first retry. Doubles with every failed attempt.
+    pub initial_backoff: chrono::Duration,
+
+    #[serde(alias="max-backoff")]
+    #[schemars(extend("x-aliases" = ["max-backoff"]))]
+    #[serde(default="default_max_backoff")]
+    /// The maximum time to wait between two attempts.
+    pub max_backoff: chrono::Duration,
+
+    #[serde(alias="dead-letter-limit", alias="max-dead-letters", alias="max_dead_letters")]
+    #[schemars(extend("x-aliases" = ["dead-letter-limit", "max-dead-letters", "max_dead_letters"]))]
+    #[serde(default="default_dead_letter_limit")]
+    /// How many dead letters are kept (the oldest are dropped first).
+    pub dead_letter_limit: usize,
+}
+impl Default for DeliveryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_max_attempts(),
+            initial_backoff: default_initial_backoff(),
+            max_backoff: default_max_backoff(),
+            dead_letter_limit: default_dead_letter_limit(),
+        }
+    }
+}
+const fn default_bus_capacity() -> usize { 1024 }
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
+/// Configuration for the notification bus.
+pub struct BusConfig {
+    #[serde(alias="buffer", alias="size")]
+    #[schemars(extend("x-aliases" = ["buffer", "size"]))]
+    #[serde(default="default_bus_capacity")]
+    /// How many batches of notifications are buffered for each notification provider. Once a
+    /// provider falls further behind, its queued batches are collapsed into one, dropping attribute
+    /// changes and all but the latest health change of each element.
+    pub capacity: usize,
+}
+impl Default for BusConfig {
+    fn default() -> Self {
+        Self {
+            capacity: default_bus_capacity(),
+        }
+    }
+}
+const fn default_shutdown_timeout() -> chrono::Duration { chrono::Duration::seconds(30) }
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
+/// Configuration for shutting the server down.
+pub struct ShutdownConfig {
+    #[serde(alias="grace-period", alias="grace_period", alias="grace")]
+    #[schemars(extend("x-aliases" = ["grace-period", "grace_period", "grace"]))]
+    #[serde(default="default_shutdown_timeout")]
+    /// How long open connections are given to close and (separately) how long components and
+    /// pending deliveries are given to finish before the server exits anyway.
+    pub timeout: chrono::Duration,
+}
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self {
+            timeout: default_shutdown_timeout(),
+        }
+    }
+}
+const fn default_debounce() -> chrono::Duration { chrono::Duration::seconds(2) }
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
+/// Configuration for reloading the config automatically.
+pub struct ReloadConfig {
+    #[serde(alias="enabled", alias="auto")]
+    #[schemars(extend("x-aliases" = ["enabled", "auto"]))]
+    #[serde(default="always")]
+    /// Whether the config file is watched for changes.
+    pub watch: bool,
+
+    #[serde(alias="delay", alias="settle")]
+    #[schemars(extend("x-aliases" = ["delay", "settle"]))]
+    #[serde(default="default_debounce")]
+    /// How long the config file has to stay unchanged after a change before it is reloaded (so
+    /// that multiple writes only trigger a single reload).
+    pub debounce: chrono::Duration,
+}
+impl Default for ReloadConfig {
+    fn default() -> Self {
+        Self {
+            watch: always(),
+            debounce: default_debounce(),
+        }
+    }
+}
@@ -0,0 +1,286 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Reads the config file and all files it includes (see [`Config::load`](super::Config::load)),
+/// returning the merged table and all files (and directories) that were read.
+pub(super) fn read_files(path: &Path) -> Result<(toml::Table, Vec<PathBuf>), String> {
+    let mut files = Vec::new();
+    let table = read_file(path, &mut files, &mut HashSet::new())?;
+    Ok((table, files))
+}
+/// Reads a single config file and the files it includes.
+///
+/// `including` are the files that are currently being read (to detect include cycles).
+fn read_file(path: &Path, files: &mut Vec<PathBuf>, including: &mut HashSet<PathBuf>) -> Result<toml::Table, String> {
+    let name = path.to_string_lossy();
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("couldn't read config file `{name}`: {e}"))?;
+    // NOTE: not logging the content, as it may contain secrets.
+    trace!("read config file `{name}`");
+    files.push(path.to_path_buf());
+    let canonical = path.canonicalize()
+        .map_err(|e| format!("couldn't resolve config file `{name}`: {e}"))?;
+    if !including.insert(canonical.clone()) {
+        return Err(format!("`{name}` includes itself"));
+    }
+    let mut table = toml::from_str::<toml::Table>(&content)
+        .map_err(|e| format!("invalid config file: `{name}`: {e}"))?;
+    let directory = path.parent().unwrap_or(Path::new("."));
+    for include in take_includes(&mut table).map_err(|e| format!("invalid include in `{name}`: {e}"))? {
+        for file in expand(&directory.join(include), files)? {
+            let included = read_file(&file, files, including)?;
+            merge(&mut table, included, "")
+                .map_err(|e| format!("couldn't include `{}` in `{name}`: {e}", file.to_string_lossy()))?;
+        }
+    }
+    including.remove(&canonical);
+    Ok(table)
+}
+/// Removes the include directive from the table, returning the included paths.
+fn take_includes(table: &mut toml::Table) -> Result<Vec<String>, String> {
+    let Some(includes) = table.remove("include").or_else(|| table.remove("includes")) else {
+        return Ok(Vec::new());
+    };
+    match includes {
+        toml::Value::String(path) => Ok(vec![path]),
+        toml::Value::Array(paths) => paths.into_iter()
+            .map(|path| match path {
+                toml::Value::String(path) => Ok(path),
+                other => Err(format!("expected a path, found `{other}`")),
+            })
+            .collect(),
+        other => Err(format!("expected a path or a list of paths, found `{other}`")),
+    }
+}
+/// Returns the files of the included path (all `*.toml` files ordered by name for directories).
+fn expand(path: &Path, files: &mut Vec<PathBuf>) -> Result<Vec<PathBuf>, String> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let name = path.to_string_lossy();
+    // watching the directory itself as well, so that added & removed files are noticed.
+    files.push(path.to_path_buf());
+    let mut included = std::fs::read_dir(path)
+        .map_err(|e| format!("couldn't read config directory `{name}`: {e}"))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("couldn't read config directory `{name}`: {e}"))?;
+    included.retain(|file| file.is_file() && file.extension().is_some_and(|extension| extension == "toml"));
+    included.sort();
+    Ok(included)
+}
+/// Merges the included table into the table.
+///
+/// `path` is the key of the table, used for error messages.
+fn merge(table: &mut toml::Table, included: toml::Table, path: &str) -> Result<(), String> {
+    for (key, value) in included {
+        let key_path = if path.is_empty() { key.clone() } else { format!("{path}.{key}") };
+        match (table.get_mut(&key), value) {
+            (None, value) => { table.insert(key, value); },
+            (Some(toml::Value::Table(existing)), toml::Value::Table(value)) => merge(existing, value, &key_path)?,
+            (Some(toml::Value::Array(existing)), toml::Value::Array(value)) => existing.extend(value),
+            (Some(_), _) => return Err(format!("`{key_path}` is set more than once")),
+        }
+    }
+    Ok(())
+}
+
+/// Resolves the references in all strings of the value (see [`Config::load`](super::Config::load)).
+///
+/// `path` is the key of the value, used for error messages.
+pub(super) fn resolve_references(value: &mut toml::Value, path: &str) -> Result<(), String> {
+    match value {
+        toml::Value::String(string) if string.contains("${") => {
+            *string = substitute(string)
+                .map_err(|e| format!("couldn't resolve `{path}`: {e}"))?;
+        },
+        toml::Value::Array(values) => {
+            for (index, value) in values.iter_mut().enumerate() {
+                resolve_references(value, &format!("{path}[{index}]"))?;
+            }
+        },
+        toml::Value::Table(table) => {
+            for (key, value) in table {
+                resolve_references(value, &format!("{path}.{key}"))?;
+            }
+        },
+        _ => {},
+    }
+    Ok(())
+}
+fn substitute(string: &str) -> Result<String, String> {
+    let mut result = String::with_capacity(string.len());
+    let mut rest = string;
+    while let Some(start) = rest.find("${") {
+        if let Some(before) = rest[..start].strip_suffix('$') {
+            result.extend([before, "${"]);
+            rest = &rest[start + 2..];
+            continue;
+        }
+        result.push_str(&rest[..start]);
+        let Some(length) = rest[start..].find('}') else {
+            return Err("missing `}`".to_string());
+        };
+        result.push_str(&resolve_reference(&rest[start + 2..start + length])?);
+        rest = &rest[start + length + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+fn resolve_reference(reference: &str) -> Result<String, String> {
+    let Some(path) = reference.strip_prefix("file:") else {
+        return std::env::var(reference)
+            .map_err(|e| format!("environment variable `{reference}`: {e}"));
+    };
+    let path = match std::env::var_os("CREDENTIALS_DIRECTORY") {
+        Some(directory) => PathBuf::from(directory).join(path),
+        None => PathBuf::from(path),
+    };
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("couldn't read `{}`: {e}", path.to_string_lossy()))?;
+    // secret files usually end with a newline that isn't part of the secret.
+    let secret = content.strip_suffix('\n')
+        .map_or(content.as_str(), |content| content.strip_suffix('\r').unwrap_or(content));
+    Ok(secret.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn substitutes_environment_variables() {
+        // cargo sets this for the test binary as well
+        assert_eq!(substitute("name: ${CARGO_PKG_NAME}!").unwrap(), format!("name: {}!", env!("CARGO_PKG_NAME")));
+        assert!(substitute("${STATUS_SERVER_TEST_UNSET_VARIABLE}").is_err());
+    }
+
+    #[test]
+    fn escaped_references_are_kept() {
+        assert_eq!(substitute("$${CARGO_PKG_NAME}").unwrap(), "${CARGO_PKG_NAME}");
+        assert_eq!(substitute("a$${b} ${CARGO_PKG_NAME}").unwrap(), format!("a${{b}} {}", env!("CARGO_PKG_NAME")));
+        assert_eq!(substitute("no references").unwrap(), "no references");
+    }
+
+    #[test]
+    fn missing_closing_brace_is_rejected() {
+        assert_eq!(substitute("${CARGO_PKG_NAME").unwrap_err(), "missing `}`");
+        assert_eq!(substitute("${CARGO_PKG_NAME} ${").unwrap_err(), "missing `}`");
+    }
+
+    #[test]
+    fn file_references_drop_trailing_newline() {
+        let directory = TempDir::new("substitute");
+        let lf = directory.write("lf", "secret\n");
+        let crlf = directory.write("crlf", "secret\r\n");
+        let multiline = directory.write("multiline", "first\nsecond\n\n");
+        assert_eq!(substitute(&format!("${{file:{}}}", lf.display())).unwrap(), "secret");
+        assert_eq!(substitute(&format!("${{file:{}}}", crlf.display())).unwrap(), "secret");
+        assert_eq!(substitute(&format!("${{file:{}}}", multiline.display())).unwrap(), "first\nsecond\n");
+        assert!(substitute(&format!("${{file:{}}}", directory.path().join("missing").display())).is_err());
+    }
+
+    #[test]
+    fn resolves_references_in_nested_values() {
+        let mut value = toml::Value::Table(toml::toml! {
+            plain = "$${kept}"
+            [section]
+            list = ["${CARGO_PKG_NAME}", 1]
+        });
+        resolve_references(&mut value, "").unwrap();
+        assert_eq!(value["plain"].as_str(), Some("${kept}"));
+        assert_eq!(value["section"]["list"][0].as_str(), Some(env!("CARGO_PKG_NAME")));
+
+        let mut value = toml::Value::Table(toml::toml! {
+            [section]
+            list = ["${"]
+        });
+        assert_eq!(resolve_references(&mut value, "").unwrap_err(), "couldn't resolve `.section.list[0]`: missing `}`");
+    }
+
+    #[test]
+    fn merges_tables_and_concatenates_arrays() {
+        let mut table = toml::toml! {
+            list = [1]
+            [section]
+            a = 1
+        };
+        merge(&mut table, toml::toml! {
+            list = [2, 3]
+            [section]
+            b = 2
+            [other]
+            c = 3
+        }, "").unwrap();
+        assert_eq!(table, toml::toml! {
+            list = [1, 2, 3]
+            [section]
+            a = 1
+            b = 2
+            [other]
+            c = 3
+        });
+    }
+
+    #[test]
+    fn duplicate_scalars_are_rejected() {
+        let mut table = toml::toml! {
+            [section]
+            a = 1
+        };
+        assert_eq!(merge(&mut table, toml::toml! {
+            [section]
+            a = 1
+        }, "").unwrap_err(), "`section.a` is set more than once");
+        let mut table = toml::toml! {
+            section = 1
+        };
+        assert_eq!(merge(&mut table, toml::toml! {
+            [section]
+            a = 1
+        }, "").unwrap_err(), "`section` is set more than once");
+    }
+
+    #[test]
+    fn directories_are_included_in_name_order() {
+        let directory = TempDir::new("include-directory");
+        let config = directory.write("config.toml", "include = \"conf.d\"\nlist = [\"main\"]\n");
+        directory.write("conf.d/b.toml", "list = [\"b\"]\n");
+        directory.write("conf.d/a.toml", "list = [\"a\"]\n");
+        directory.write("conf.d/c.txt", "not = \"included\"\n");
+        let (table, files) = read_files(&config).unwrap();
+        assert_eq!(table, toml::toml! {
+            list = ["main", "a", "b"]
+        });
+        assert_eq!(files, [
+            config,
+            directory.path().join("conf.d"),
+            directory.path().join("conf.d/a.toml"),
+            directory.path().join("conf.d/b.toml"),
+        ]);
+    }
+
+    #[test]
+    fn include_cycles_are_rejected() {
+        let directory = TempDir::new("include-cycle");
+        let config = directory.write("config.toml", "include = [\"a.toml\"]\n");
+        directory.write("a.toml", "include = \"b.toml\"\n");
+        directory.write("b.toml", "include = \"config.toml\"\n");
+        let error = read_files(&config).unwrap_err();
+        assert!(error.ends_with("includes itself"), "unexpected error: {error}");
+    }
+
+    #[test]
+    fn files_may_be_included_more_than_once_without_a_cycle() {
+        let directory = TempDir::new("include-twice");
+        let config = directory.write("config.toml", "include = [\"a.toml\", \"b.toml\"]\n");
+        directory.write("a.toml", "include = \"shared.toml\"\n");
+        directory.write("b.toml", "include = \"shared.toml\"\n");
+        directory.write("shared.toml", "list = [1]\n");
+        let (table, _) = read_files(&config).unwrap();
+        assert_eq!(table, toml::toml! {
+            list = [1, 1]
+        });
+    }
+}
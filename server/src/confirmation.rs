@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+
+const fn one() -> u32 { 1 }
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
+/// How often (or how long) a change of the online status has to be observed before it is applied.
+///
+/// Keeps transient blips (e.g. a single failed request) from marking an element as offline. Changes
+/// that don't change whether the element is online (e.g. from up to degraded) are always applied
+/// immediately.
+pub struct Confirmation {
+    #[serde(alias="down-after", alias="down_after", alias="failure")]
+    #[schemars(extend("x-aliases" = ["down-after", "down_after", "failure"]))]
+    #[serde(default="one")]
+    /// How many consecutive failures are needed before the element is marked as offline.
+    pub failures: u32,
+
+    #[serde(alias="up-after", alias="up_after", alias="success")]
+    #[schemars(extend("x-aliases" = ["up-after", "up_after", "success"]))]
+    #[serde(default="one")]
+    /// How many consecutive successes are needed before the element is marked as online again.
+    pub successes: u32,
+
+    #[serde(alias="grace-period", alias="grace_period", alias="down-grace")]
+    #[schemars(extend("x-aliases" = ["grace-period", "grace_period", "down-grace"]))]
+    #[serde(default)]
+    /// How long the element has to be failing before it is marked as offline (in addition to
+    /// [`Self::failures`]).
+    pub grace: Option<chrono::Duration>,
+}
+impl Default for Confirmation {
+    fn default() -> Self {
+        Self {
+            failures: one(),
+            successes: one(),
+            grace: None,
+        }
+    }
+}
+impl Confirmation {
+    /// Whether a change to `online` that was observed `observations` times in a row since `since`
+    /// is confirmed.
+    fn confirms(&self, online: bool, observations: u32, since: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        if online {
+            observations >= self.successes
+        } else {
+            observations >= self.failures && self.grace.is_none_or(|grace| now - since >= grace)
+        }
+    }
+}
+
+/// A change of the online status that hasn't been confirmed yet.
+#[derive(Clone, Debug)]
+struct Pending {
+    online: bool,
+    observations: u32,
+    since: DateTime<Utc>,
+}
+#[derive(Clone, Debug, Default)]
+/// Keeps track of the unconfirmed online status changes of all elements.
+pub(crate) struct Confirmations {
+    pending: HashMap<String, Pending>,
+}
+impl Confirmations {
+    /// Records that the element was observed to be `online` (which differs from its current online
+    /// status), returning whether the change is confirmed.
+    pub(crate) fn observe(&mut self, element_id: &str, online: bool, confirmation: &Confirmation, now: DateTime<Utc>) -> bool {
+        let pending = self.pending.entry(element_id.to_string())
+            .or_insert_with(|| Pending { online, observations: 0, since: now });
+        if pending.online != online {
+            *pending = Pending { online, observations: 0, since: now };
+        }
+        pending.observations += 1;
+        let confirmed = confirmation.confirms(online, pending.observations, pending.since, now);
+        if confirmed {
+            self.pending.remove(element_id);
+        }
+        confirmed
+    }
+    /// Forgets the unconfirmed change of the element (e.g. because it was observed in its current
+    /// state again).
+    pub(crate) fn reset(&mut self, element_id: &str) {
+        self.pending.remove(element_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(minutes: i64) -> DateTime<Utc> {
+        DateTime::<Utc>::UNIX_EPOCH + chrono::Duration::minutes(minutes)
+    }
+
+    #[test]
+    fn default_confirms_immediately() {
+        let mut confirmations = Confirmations::default();
+        assert!(confirmations.observe("a", false, &Confirmation::default(), at(0)));
+        assert!(confirmations.observe("a", true, &Confirmation::default(), at(0)));
+    }
+
+    #[test]
+    fn needs_consecutive_observations() {
+        let confirmation = Confirmation { failures: 3, successes: 2, grace: None };
+        let mut confirmations = Confirmations::default();
+        assert!(!confirmations.observe("a", false, &confirmation, at(0)));
+        assert!(!confirmations.observe("a", false, &confirmation, at(1)));
+        assert!(confirmations.observe("a", false, &confirmation, at(2)));
+        // a confirmed change starts counting from scratch
+        assert!(!confirmations.observe("a", true, &confirmation, at(3)));
+        assert!(confirmations.observe("a", true, &confirmation, at(4)));
+    }
+
+    #[test]
+    fn opposite_observation_restarts_count() {
+        let confirmation = Confirmation { failures: 2, successes: 2, grace: None };
+        let mut confirmations = Confirmations::default();
+        assert!(!confirmations.observe("a", false, &confirmation, at(0)));
+        assert!(!confirmations.observe("a", true, &confirmation, at(1)));
+        assert!(!confirmations.observe("a", false, &confirmation, at(2)));
+        assert!(confirmations.observe("a", false, &confirmation, at(3)));
+    }
+
+    #[test]
+    fn reset_forgets_pending_change() {
+        let confirmation = Confirmation { failures: 2, successes: 1, grace: None };
+        let mut confirmations = Confirmations::default();
+        assert!(!confirmations.observe("a", false, &confirmation, at(0)));
+        confirmations.reset("a");
+        assert!(!confirmations.observe("a", false, &confirmation, at(1)));
+        assert!(confirmations.observe("a", false, &confirmation, at(2)));
+    }
+
+    #[test]
+    fn grace_period_delays_going_offline() {
+        let confirmation = Confirmation { failures: 1, successes: 1, grace: Some(chrono::Duration::minutes(5)) };
+        let mut confirmations = Confirmations::default();
+        assert!(!confirmations.observe("a", false, &confirmation, at(0)));
+        assert!(!confirmations.observe("a", false, &confirmation, at(4)));
+        assert!(confirmations.observe("a", false, &confirmation, at(5)));
+        // the grace period only applies to going offline
+        assert!(confirmations.observe("a", true, &confirmation, at(6)));
+    }
+}
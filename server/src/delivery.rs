@@ -0,0 +1,233 @@
+use std::collections::HashSet;
+use chrono::{DateTime, Utc};
+use crate::config::DeliveryConfig;
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+/// A single message of a [`ReliableNotificationProvider`](crate::ReliableNotificationProvider)
+/// waiting to be (or that ultimately failed to be) delivered.
+pub struct Delivery {
+    /// The unique id of the delivery.
+    pub id: u64,
+    /// The instance of the provider that sends out the delivery (usually its id).
+    pub provider: String,
+    /// The serialized [`ReliableNotificationProvider::Delivery`](crate::ReliableNotificationProvider::Delivery).
+    pub payload: serde_json::Value,
+    /// When the delivery was queued.
+    pub created: DateTime<Utc>,
+    /// How often sending the delivery was attempted.
+    pub attempts: u32,
+    /// When the next attempt is due.
+    pub next_attempt: DateTime<Utc>,
+    /// Why the last attempt failed.
+    pub last_error: Option<String>,
+}
+
+/// How long to wait after the given number of failed attempts: the initial backoff, doubled with
+/// every further attempt, but at most the maximum backoff.
+fn backoff(attempts: u32, config: &DeliveryConfig) -> chrono::Duration {
+    config.initial_backoff
+        .checked_mul(1_i32 << attempts.saturating_sub(1).min(30))
+        .map_or(config.max_backoff, |backoff| backoff.min(config.max_backoff))
+}
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+/// The delivery queue and the dead letters (deliveries that ultimately failed).
+pub(crate) struct Deliveries {
+    next_id: u64,
+    pending: Vec<Delivery>,
+    dead: Vec<Delivery>,
+    /// Deliveries that are currently being sent.
+    #[serde(skip)]
+    in_flight: HashSet<u64>,
+}
+impl Deliveries {
+    pub(crate) fn queue(&mut self, provider: &str, payload: serde_json::Value) {
+        let now = Utc::now();
+        trace!("queueing delivery {} for {provider}", self.next_id);
+        self.pending.push(Delivery {
+            id: self.next_id,
+            provider: provider.to_string(),
+            payload,
+            created: now,
+            attempts: 0,
+            next_attempt: now,
+            last_error: None,
+        });
+        self.next_id += 1;
+    }
+    /// Returns all deliveries that are due and not yet being sent, marking them as in flight.
+    ///
+    /// Only deliveries of providers for which `can_send` returns `true` are taken.
+    pub(crate) fn take_due(&mut self, now: DateTime<Utc>, can_send: impl Fn(&str) -> bool) -> Vec<Delivery> {
+        let due = self.pending.iter()
+            .filter(|delivery| delivery.next_attempt <= now && !self.in_flight.contains(&delivery.id))
+            .filter(|delivery| can_send(&delivery.provider))
+            .cloned()
+            .collect::<Vec<_>>();
+        self.in_flight.extend(due.iter().map(|delivery| delivery.id));
+        due
+    }
+    /// Whether any delivery is due (and not yet being sent) or currently being sent.
+    ///
+    /// Only deliveries of providers for which `can_send` returns `true` are considered due.
+    pub(crate) fn is_busy(&self, now: DateTime<Utc>, can_send: impl Fn(&str) -> bool) -> bool {
+        !self.in_flight.is_empty() || self.pending.iter()
+            .any(|delivery| delivery.next_attempt <= now && can_send(&delivery.provider))
+    }
+    /// Removes the delivery from the queue.
+    pub(crate) fn succeeded(&mut self, id: u64) {
+        self.in_flight.remove(&id);
+        self.pending.retain(|delivery| delivery.id != id);
+    }
+    /// Schedules the next attempt with exponential backoff or moves the delivery to the dead letters
+    /// if it ran out of attempts.
+    pub(crate) fn failed(&mut self, id: u64, error: String, config: &DeliveryConfig) {
+        self.in_flight.remove(&id);
+        let Some(index) = self.pending.iter().position(|delivery| delivery.id == id) else { return; };
+        let delivery = &mut self.pending[index];
+        delivery.attempts += 1;
+        delivery.last_error = Some(error);
+        if delivery.attempts < config.max_attempts {
+            let backoff = backoff(delivery.attempts, config);
+            delivery.next_attempt = Utc::now() + backoff;
+            debug!("delivery {id} failed (attempt {}); retrying in {backoff}", delivery.attempts);
+            return;
+        }
+        let delivery = self.pending.remove(index);
+        warn!("delivery {id} for {} ultimately failed after {} attempts; moving it to the dead letters",
+            delivery.provider, delivery.attempts);
+        self.dead.push(delivery);
+        if let Some(excess) = self.dead.len().checked_sub(config.dead_letter_limit) {
+            self.dead.drain(..excess);
+        }
+    }
+    /// Moves the dead letter back into the queue, returning it (if it exists).
+    pub(crate) fn retry(&mut self, id: u64) -> Option<Delivery> {
+        let index = self.dead.iter().position(|delivery| delivery.id == id)?;
+        let mut delivery = self.dead.remove(index);
+        delivery.attempts = 0;
+        delivery.next_attempt = Utc::now();
+        self.pending.push(delivery.clone());
+        Some(delivery)
+    }
+    pub(crate) fn pending(&self) -> &[Delivery] {
+        &self.pending
+    }
+    pub(crate) fn dead(&self) -> &[Delivery] {
+        &self.dead
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(max_attempts: u32, dead_letter_limit: usize) -> DeliveryConfig {
+        DeliveryConfig {
+            max_attempts,
+            initial_backoff: chrono::Duration::seconds(10),
+            max_backoff: chrono::Duration::minutes(5),
+            dead_letter_limit,
+        }
+    }
+    fn ids(deliveries: &[Delivery]) -> Vec<u64> {
+        deliveries.iter().map(|delivery| delivery.id).collect()
+    }
+    /// Takes all deliveries (whether they are due or not) and lets them fail.
+    fn fail_all(deliveries: &mut Deliveries, config: &DeliveryConfig) {
+        for delivery in deliveries.take_due(DateTime::<Utc>::MAX_UTC, |_| true) {
+            deliveries.failed(delivery.id, "failed".to_string(), config);
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        let config = config(10, 10);
+        let backoffs = (1..=7).map(|attempts| backoff(attempts, &config).num_seconds()).collect::<Vec<_>>();
+        assert_eq!(backoffs, [10, 20, 40, 80, 160, 300, 300]);
+    }
+
+    #[test]
+    fn backoff_doesnt_overflow() {
+        let mut config = config(10, 10);
+        config.max_backoff = chrono::Duration::MAX;
+        assert_eq!(backoff(31, &config), backoff(u32::MAX, &config));
+        config.initial_backoff = chrono::Duration::MAX;
+        assert_eq!(backoff(2, &config), chrono::Duration::MAX);
+    }
+
+    #[test]
+    fn failed_deliveries_are_retried_later() {
+        let config = config(3, 10);
+        let mut deliveries = Deliveries::default();
+        deliveries.queue("a", serde_json::Value::Null);
+        let before = Utc::now();
+        fail_all(&mut deliveries, &config);
+        let delivery = &deliveries.pending()[0];
+        assert_eq!((delivery.attempts, delivery.last_error.as_deref()), (1, Some("failed")));
+        assert!(delivery.next_attempt >= before + config.initial_backoff);
+        // it isn't taken again before then.
+        assert!(deliveries.take_due(before, |_| true).is_empty());
+        assert!(!deliveries.is_busy(before, |_| true));
+    }
+
+    #[test]
+    fn deliveries_run_out_of_attempts() {
+        let config = config(3, 10);
+        let mut deliveries = Deliveries::default();
+        deliveries.queue("a", serde_json::Value::Null);
+        deliveries.queue("b", serde_json::Value::Null);
+        fail_all(&mut deliveries, &config);
+        fail_all(&mut deliveries, &config);
+        assert_eq!(ids(deliveries.pending()), [0, 1]);
+        assert!(deliveries.dead().is_empty());
+        fail_all(&mut deliveries, &config);
+        assert!(deliveries.pending().is_empty());
+        assert_eq!(ids(deliveries.dead()), [0, 1]);
+        assert!(deliveries.dead().iter().all(|delivery| delivery.attempts == 3));
+    }
+
+    #[test]
+    fn only_the_newest_dead_letters_are_kept() {
+        let config = config(1, 2);
+        let mut deliveries = Deliveries::default();
+        for _ in 0..3 {
+            deliveries.queue("a", serde_json::Value::Null);
+        }
+        fail_all(&mut deliveries, &config);
+        assert_eq!(ids(deliveries.dead()), [1, 2]);
+        deliveries.queue("a", serde_json::Value::Null);
+        fail_all(&mut deliveries, &config);
+        assert_eq!(ids(deliveries.dead()), [2, 3]);
+    }
+
+    #[test]
+    fn retrying_requeues_dead_letters() {
+        let config = config(1, 10);
+        let mut deliveries = Deliveries::default();
+        deliveries.queue("a", serde_json::json!("payload"));
+        fail_all(&mut deliveries, &config);
+        assert!(deliveries.retry(1).is_none());
+        let delivery = deliveries.retry(0).expect("is a dead letter");
+        assert_eq!((delivery.attempts, delivery.payload), (0, serde_json::json!("payload")));
+        assert!(deliveries.dead().is_empty());
+        assert_eq!(ids(&deliveries.take_due(Utc::now(), |_| true)), [0]);
+        // it gets all attempts again.
+        deliveries.failed(0, "failed again".to_string(), &config);
+        assert_eq!(deliveries.dead()[0].last_error.as_deref(), Some("failed again"));
+        assert!(deliveries.retry(0).is_some());
+    }
+
+    #[test]
+    fn only_sendable_deliveries_are_taken_once() {
+        let mut deliveries = Deliveries::default();
+        deliveries.queue("a", serde_json::Value::Null);
+        deliveries.queue("b", serde_json::Value::Null);
+        let now = Utc::now();
+        assert_eq!(ids(&deliveries.take_due(now, |provider| provider == "a")), [0]);
+        assert!(deliveries.take_due(now, |provider| provider == "a").is_empty());
+        assert!(deliveries.is_busy(now, |_| false));
+        deliveries.succeeded(0);
+        assert!(!deliveries.is_busy(now, |provider| provider == "a"));
+        assert_eq!(ids(deliveries.pending()), [1]);
+    }
+}
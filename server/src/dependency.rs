@@ -0,0 +1,229 @@
+use std::any::TypeId;
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Why a dependency of a component couldn't be added (see
+/// [`ComponentHandle::add_component_dependency`](crate::ComponentHandle::add_component_dependency)).
+pub enum DependencyError {
+    /// All instances of the dependency (by id) are ignored.
+    Ignored(String),
+    /// No instance of the dependency could be initialized.
+    Unavailable {
+        /// The id of the dependency.
+        dependency: String,
+        /// Why (the first instance of) it couldn't be initialized.
+        reason: Option<String>,
+    },
+    /// The dependency (directly or indirectly) depends on the dependant. Contains the instances
+    /// forming the cycle, starting and ending with the dependant.
+    Cycle(Vec<String>),
+}
+impl std::fmt::Display for DependencyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ignored(id) => write!(f, "dependency {id} cannot be satisfied as it is set to be ignored"),
+            Self::Unavailable { dependency, reason: None } => write!(f, "no instance of dependency {dependency} could be initialized"),
+            Self::Unavailable { dependency, reason: Some(reason) } => write!(f, "no instance of dependency {dependency} could be initialized: {reason}"),
+            Self::Cycle(cycle) => write!(f, "dependency cycle: {}", cycle.join(" -> ")),
+        }
+    }
+}
+impl std::error::Error for DependencyError {}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+/// The dependencies between the loaded components.
+pub struct DependencyGraph {
+    /// The instances each loaded instance depends on (by instance name).
+    pub dependencies: BTreeMap<String, Vec<String>>,
+    /// All loaded instances in the order they are initialized in: every instance comes after its
+    /// dependencies (and otherwise ordered by name).
+    pub init_order: Vec<String>,
+}
+impl DependencyGraph {
+    /// The order in which the loaded instances are shut down: every instance comes before its
+    /// dependencies (the reverse of [`Self::init_order`]).
+    pub fn shutdown_order(&self) -> impl Iterator<Item=&String> {
+        self.init_order.iter().rev()
+    }
+    /// The loaded instances that directly depend on the instance.
+    pub fn dependants<'a>(&'a self, instance: &'a str) -> impl Iterator<Item=&'a String> {
+        self.dependencies.iter()
+            .filter(move |(_, dependencies)| dependencies.iter().any(|dependency| dependency == instance))
+            .map(|(dependant, _)| dependant)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// A component instance in the dependency graph.
+pub(crate) struct Node {
+    pub(crate) instance: String,
+    pub(crate) type_id: TypeId,
+}
+impl Node {
+    pub(crate) fn new(instance: &str, type_id: TypeId) -> Self {
+        Self { instance: instance.to_string(), type_id }
+    }
+}
+#[derive(Debug, Default)]
+/// The dependencies declared by the components.
+///
+/// Dependencies are declared before they are initialized (so that cycles are rejected before
+/// initializing them recursively), so they might refer to instances that aren't loaded (yet).
+pub(crate) struct Dependencies {
+    /// The dependencies of each dependant.
+    edges: BTreeMap<Node, BTreeSet<Node>>,
+}
+impl Dependencies {
+    /// Declares that the dependant depends on the dependency, unless that would form a cycle.
+    pub(crate) fn add(&mut self, dependant: &Node, dependency: Node) -> Result<(), DependencyError> {
+        if let Some(mut cycle) = self.path(&dependency, dependant) {
+            cycle.insert(0, dependant.instance.clone());
+            return Err(DependencyError::Cycle(cycle));
+        }
+        self.edges.entry(dependant.clone()).or_default().insert(dependency);
+        Ok(())
+    }
+    /// The path of instances leading from `from` to `to` via dependencies (if there is one).
+    fn path(&self, from: &Node, to: &Node) -> Option<Vec<String>> {
+        if from == to {
+            return Some(vec![from.instance.clone()]);
+        }
+        // the graph is acyclic, so there is no need to keep track of visited nodes.
+        self.edges.get(from)?.iter()
+            .find_map(|dependency| self.path(dependency, to))
+            .map(|mut path| { path.insert(0, from.instance.clone()); path })
+    }
+    /// Removes the dependency (e.g. as it couldn't be initialized).
+    pub(crate) fn remove(&mut self, dependant: &Node, dependency: &Node) {
+        if let Some(dependencies) = self.edges.get_mut(dependant) {
+            dependencies.remove(dependency);
+        }
+    }
+    /// Removes all dependencies of the instance (e.g. as it was removed itself).
+    pub(crate) fn remove_dependant(&mut self, dependant: &Node) {
+        self.edges.remove(dependant);
+    }
+    /// The instances that (transitively) depend on the node, in the order they have to be removed
+    /// in (dependants first).
+    pub(crate) fn dependants(&self, node: &Node) -> Vec<Node> {
+        let mut dependants = Vec::new();
+        self.collect_dependants(node, &mut dependants);
+        dependants
+    }
+    fn collect_dependants(&self, node: &Node, dependants: &mut Vec<Node>) {
+        for (dependant, dependencies) in &self.edges {
+            if dependencies.contains(node) && !dependants.contains(dependant) {
+                self.collect_dependants(dependant, dependants);
+                dependants.push(dependant.clone());
+            }
+        }
+    }
+    /// The loaded dependencies of the node.
+    fn loaded_dependencies<'a>(&'a self, node: &Node, loaded: &'a BTreeSet<Node>) -> impl Iterator<Item=&'a Node> {
+        self.edges.get(node).into_iter().flatten()
+            .filter(|dependency| loaded.contains(dependency))
+    }
+    /// The loaded instances ordered so that every instance comes after its (loaded) dependencies.
+    pub(crate) fn init_order(&self, loaded: &BTreeSet<Node>) -> Vec<Node> {
+        let mut init_order = Vec::<Node>::new();
+        let mut remaining = loaded.iter().collect::<Vec<_>>();
+        // always picks the first (by name) instance whose dependencies are all initialized.
+        while let Some(index) = remaining.iter()
+            .position(|node| self.loaded_dependencies(node, loaded).all(|dependency| init_order.contains(dependency))) {
+            init_order.push(remaining.remove(index).clone());
+        }
+        init_order
+    }
+    /// The graph of the loaded instances (ignoring dependencies that aren't loaded).
+    pub(crate) fn graph(&self, loaded: &BTreeSet<Node>) -> DependencyGraph {
+        DependencyGraph {
+            dependencies: loaded.iter()
+                .map(|node| (node.instance.clone(), self.loaded_dependencies(node, loaded).map(|dependency| dependency.instance.clone()).collect()))
+                .collect(),
+            init_order: self.init_order(loaded).into_iter().map(|node| node.instance).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(instance: &str) -> Node {
+        Node::new(instance, TypeId::of::<()>())
+    }
+    fn nodes(instances: &[&str]) -> BTreeSet<Node> {
+        instances.iter().map(|instance| node(instance)).collect()
+    }
+    fn instances(nodes: Vec<Node>) -> Vec<String> {
+        nodes.into_iter().map(|node| node.instance).collect()
+    }
+
+    #[test]
+    fn rejects_cycles() {
+        let mut dependencies = Dependencies::default();
+        dependencies.add(&node("a"), node("b")).unwrap();
+        dependencies.add(&node("b"), node("c")).unwrap();
+        assert_eq!(dependencies.add(&node("c"), node("a")), Err(DependencyError::Cycle(
+            vec!["c".to_string(), "a".to_string(), "b".to_string(), "c".to_string()],
+        )));
+        assert_eq!(dependencies.add(&node("a"), node("a")), Err(DependencyError::Cycle(
+            vec!["a".to_string(), "a".to_string()],
+        )));
+        // the rejected dependencies weren't added
+        assert_eq!(instances(dependencies.dependants(&node("a"))), Vec::<String>::new());
+        dependencies.add(&node("a"), node("c")).unwrap();
+    }
+
+    #[test]
+    fn removed_dependencies_no_longer_form_cycles() {
+        let mut dependencies = Dependencies::default();
+        dependencies.add(&node("a"), node("b")).unwrap();
+        dependencies.remove(&node("a"), &node("b"));
+        dependencies.add(&node("b"), node("a")).unwrap();
+        dependencies.remove_dependant(&node("b"));
+        dependencies.add(&node("a"), node("b")).unwrap();
+    }
+
+    #[test]
+    fn init_order_puts_dependencies_first() {
+        let mut dependencies = Dependencies::default();
+        dependencies.add(&node("a"), node("c")).unwrap();
+        dependencies.add(&node("c"), node("d")).unwrap();
+        dependencies.add(&node("b"), node("d")).unwrap();
+        let graph = dependencies.graph(&nodes(&["a", "b", "c", "d", "e"]));
+        assert_eq!(graph.init_order, ["d", "b", "c", "a", "e"]);
+        // dependencies that aren't loaded are ignored
+        let graph = dependencies.graph(&nodes(&["a", "b", "c"]));
+        assert_eq!(graph.init_order, ["b", "c", "a"]);
+        assert_eq!(graph.dependencies["a"], ["c"]);
+        assert_eq!(graph.dependencies["c"], Vec::<String>::new());
+    }
+
+    #[test]
+    fn init_order_doesnt_depend_on_declaration_order() {
+        let mut forward = Dependencies::default();
+        forward.add(&node("x"), node("y")).unwrap();
+        forward.add(&node("x"), node("z")).unwrap();
+        forward.add(&node("w"), node("z")).unwrap();
+        let mut backward = Dependencies::default();
+        backward.add(&node("w"), node("z")).unwrap();
+        backward.add(&node("x"), node("z")).unwrap();
+        backward.add(&node("x"), node("y")).unwrap();
+        let loaded = nodes(&["w", "x", "y", "z"]);
+        assert_eq!(forward.graph(&loaded), backward.graph(&loaded));
+        assert_eq!(forward.graph(&loaded).init_order, ["y", "z", "w", "x"]);
+    }
+
+    #[test]
+    fn dependants_are_removed_first() {
+        let mut dependencies = Dependencies::default();
+        dependencies.add(&node("a"), node("b")).unwrap();
+        dependencies.add(&node("b"), node("c")).unwrap();
+        dependencies.add(&node("d"), node("c")).unwrap();
+        assert_eq!(instances(dependencies.dependants(&node("c"))), ["a", "b", "d"]);
+        let graph = dependencies.graph(&nodes(&["a", "b", "c", "d"]));
+        assert_eq!(graph.shutdown_order().collect::<Vec<_>>(), ["d", "a", "b", "c"]);
+        assert_eq!(graph.dependants("c").collect::<Vec<_>>(), ["b", "d"]);
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use crate::notification::Notification;
+
+/// Hands the collected notifications of a key over.
+type Send = Box<dyn FnOnce(Vec<Notification>) + std::marker::Send>;
+
+#[derive(Clone, Default)]
+/// Collects notifications over a time window, so that a [`NotificationProvider`](crate::NotificationProvider)
+/// can send a single summary instead of one message per notification.
+///
+/// Notifications are collected per key (e.g. the recipient). The window starts with the first
+/// notification of a key. Collected notifications have to be [flushed](Self::flush) when the
+/// provider shuts down, as they are lost otherwise.
+pub struct Digest {
+    pending: Arc<parking_lot::Mutex<Pending>>,
+}
+#[derive(Default)]
+struct Pending {
+    /// The notifications collected in the current window of each key, the id of the window and
+    /// how they are handed over.
+    windows: HashMap<String, (u64, Vec<Notification>, Send)>,
+    next_window: u64,
+    /// Whether the digest was flushed, in which case nothing is collected anymore.
+    flushed: bool,
+}
+impl std::fmt::Debug for Digest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pending = self.pending.lock();
+        f.debug_struct("Digest")
+            .field("pending", &pending.windows.iter().map(|(key, (_, notifications, _))| (key, notifications)).collect::<BTreeMap<_, _>>())
+            .field("flushed", &pending.flushed)
+            .finish()
+    }
+}
+impl Digest {
+    /// creates a new, empty digest.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Adds the notification to the digest of the key.
+    ///
+    /// If it is the first notification of the key, `send` is called with all notifications
+    /// collected for the key (ordered by arrival) once the window elapsed (or the digest is
+    /// [flushed](Self::flush)). Once the digest was flushed, `send` is called right away.
+    ///
+    /// # Note
+    /// This doesn't lock the server, so it can be used in [`NotificationProvider::notify`](crate::NotificationProvider::notify).
+    ///
+    /// # Panics
+    /// If called outside a tokio runtime.
+    pub fn collect<F>(&self, key: &str, notification: Notification, window: chrono::Duration, send: F)
+    where F: FnOnce(Vec<Notification>) + std::marker::Send + 'static {
+        let mut pending = self.pending.lock();
+        if pending.flushed {
+            drop(pending);
+            send(vec![notification]);
+            return;
+        }
+        if let Some((_, notifications, _)) = pending.windows.get_mut(key) {
+            notifications.push(notification);
+            return;
+        }
+        let id = pending.next_window;
+        pending.next_window += 1;
+        pending.windows.insert(key.to_string(), (id, vec![notification], Box::new(send)));
+        drop(pending);
+        let pending = Arc::downgrade(&self.pending);
+        let key = key.to_string();
+        let window = window.to_std().unwrap_or_default();
+        tokio::spawn(async move {
+            tokio::time::sleep(window).await;
+            let Some(pending) = pending.upgrade() else { return; };
+            let mut pending = pending.lock();
+            // the window may have been flushed (and a new one started) in the meantime.
+            if pending.windows.get(&key).is_none_or(|(window, _, _)| *window != id) { return; }
+            let (_, notifications, send) = pending.windows.remove(&key).expect("just checked that it exists");
+            drop(pending);
+            trace!("sending digest of {} notifications for {key}", notifications.len());
+            send(notifications);
+        });
+    }
+    /// Hands over the notifications collected for every key right away, e.g. when the provider
+    /// shuts down. Notifications collected afterwards are handed over right away as well.
+    pub fn flush(&self) {
+        let windows = {
+            let mut pending = self.pending.lock();
+            pending.flushed = true;
+            std::mem::take(&mut pending.windows)
+        };
+        for (key, (_, notifications, send)) in windows {
+            trace!("flushing digest of {} notifications for {key}", notifications.len());
+            send(notifications);
+        }
+    }
+    /// Groups the notifications by the element they are about (keeping their order).
+    #[must_use]
+    pub fn group_by_element(notifications: Vec<Notification>) -> BTreeMap<String, Vec<Notification>> {
+        let mut grouped = BTreeMap::<String, Vec<Notification>>::new();
+        for notification in notifications {
+            grouped.entry(notification.element_id.clone())
+                .or_default()
+                .push(notification);
+        }
+        grouped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use super::*;
+    use crate::notification::NotificationReason;
+    use crate::state::Health;
+
+    fn notification(element_id: &str) -> Notification {
+        Notification::new("test".to_string(), element_id.to_string(), NotificationReason::NewElement(Health::default()))
+    }
+    fn elements(notifications: &[Notification]) -> Vec<&str> {
+        notifications.iter().map(|notification| notification.element_id.as_str()).collect()
+    }
+    fn runtime() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_current_thread().enable_time().build().expect("couldn't create the runtime")
+    }
+    /// Collects the notification for the key, sending the digest (with its key) to `sent`.
+    fn collect(digest: &Digest, key: &str, element_id: &str, sent: &mpsc::Sender<(String, Vec<Notification>)>) {
+        let (key, sent) = (key.to_string(), sent.clone());
+        digest.collect(&key.clone(), notification(element_id), chrono::Duration::milliseconds(50), move |notifications| {
+            sent.send((key, notifications)).expect("receiver is alive");
+        });
+    }
+
+    #[test]
+    fn collects_per_key_until_the_window_elapsed() {
+        let runtime = runtime();
+        let digest = Digest::new();
+        let (sent, received) = mpsc::channel();
+        runtime.block_on(async {
+            collect(&digest, "x", "a", &sent);
+            collect(&digest, "y", "b", &sent);
+            collect(&digest, "x", "c", &sent);
+            assert!(received.try_recv().is_err());
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        });
+        let mut digests = received.try_iter().collect::<Vec<_>>();
+        digests.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let digests = digests.iter().map(|(key, notifications)| (key.as_str(), elements(notifications))).collect::<Vec<_>>();
+        assert_eq!(digests, [("x", vec!["a", "c"]), ("y", vec!["b"])]);
+        // a new window starts with the next notification.
+        runtime.block_on(async {
+            collect(&digest, "x", "d", &sent);
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        });
+        let (key, notifications) = received.try_recv().expect("digest was sent");
+        assert_eq!((key.as_str(), elements(&notifications)), ("x", vec!["d"]));
+    }
+
+    #[test]
+    fn flushing_hands_everything_over_right_away() {
+        let runtime = runtime();
+        let digest = Digest::new();
+        let (sent, received) = mpsc::channel();
+        runtime.block_on(async {
+            collect(&digest, "x", "a", &sent);
+            collect(&digest, "x", "b", &sent);
+            digest.flush();
+            let (_, notifications) = received.try_recv().expect("digest was flushed");
+            assert_eq!(elements(&notifications), ["a", "b"]);
+            collect(&digest, "x", "c", &sent);
+            let (_, notifications) = received.try_recv().expect("sent right away");
+            assert_eq!(elements(&notifications), ["c"]);
+            // the elapsed window of the flushed notifications doesn't hand them over again.
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        });
+        assert!(received.try_recv().is_err());
+    }
+
+    #[test]
+    fn groups_by_element_in_order() {
+        let grouped = Digest::group_by_element(vec![notification("b"), notification("a"), notification("b")]);
+        let grouped = grouped.iter().map(|(element, notifications)| (element.as_str(), notifications.len())).collect::<Vec<_>>();
+        assert_eq!(grouped, [("a", 1), ("b", 2)]);
+    }
+}
@@ -0,0 +1,134 @@
+use chrono::{DateTime, Utc};
+use crate::maintenance::matches_pattern;
+
+/// The id used for notifications the server sends out when escalating incidents.
+pub(crate) const COMPONENT_ID: &str = "escalation";
+
+fn everything() -> Vec<String> { vec!["*".to_string()] }
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
+/// Steps taken one after another while an incident stays open and unacknowledged.
+pub struct EscalationPolicy {
+    #[serde(alias="id")]
+    #[schemars(extend("x-aliases" = ["id"]))]
+    /// The name of the policy (included in the notifications).
+    pub name: String,
+
+    #[serde(alias="element", alias="patterns")]
+    #[schemars(extend("x-aliases" = ["element", "patterns"]))]
+    #[serde(default="everything")]
+    /// The elements the policy applies to. `*` matches any (possibly empty) sequence of characters.
+    ///
+    /// Only the first policy matching an element is used.
+    pub elements: Vec<String>,
+
+    /// The steps of the policy.
+    pub steps: Vec<EscalationStep>,
+}
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
+/// A single step of an [`EscalationPolicy`].
+pub struct EscalationStep {
+    #[serde(alias="delay", alias="wait")]
+    #[schemars(extend("x-aliases" = ["delay", "wait"]))]
+    #[serde(default)]
+    /// How long after the element went offline the step is taken.
+    pub after: chrono::Duration,
+
+    #[serde(alias="via", alias="notify")]
+    #[schemars(extend("x-aliases" = ["via", "notify"]))]
+    /// The id (or instance name) of the [`NotificationProvider`](crate::NotificationProvider) notified
+    /// in this step.
+    pub provider: String,
+
+    #[serde(alias="to")]
+    #[schemars(extend("x-aliases" = ["to"]))]
+    #[serde(default)]
+    /// The target of the provider (e.g. the name of a ntfy target or an E-Mail address).
+    ///
+    /// What the target means is up to the provider. If not set, the provider decides whom to
+    /// notify.
+    pub target: Option<String>,
+}
+impl EscalationPolicy {
+    /// Whether the policy applies to the element.
+    pub(crate) fn applies_to(&self, element_id: &str) -> bool {
+        self.elements.iter().any(|pattern| matches_pattern(pattern, element_id))
+    }
+    /// The steps (with their index) that are due at `now` for an incident that started at `start`
+    /// and already had `taken` steps taken.
+    ///
+    /// Steps are taken in order, so a step that isn't due yet holds back all following ones.
+    pub(crate) fn due_steps(&self, taken: usize, start: DateTime<Utc>, now: DateTime<Utc>) -> Vec<(usize, EscalationStep)> {
+        self.steps.iter().enumerate()
+            .skip(taken)
+            .take_while(|(_, step)| start + step.after <= now)
+            .map(|(index, step)| (index, step.clone()))
+            .collect()
+    }
+}
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+/// An incident being escalated, sent to the provider of the [`EscalationStep`].
+pub struct Escalation {
+    /// The name of the [`EscalationPolicy`].
+    pub policy: String,
+    /// The index of the step (starting at 0).
+    pub step: usize,
+    /// The id of the [`Incident`](crate::Incident).
+    pub incident: u64,
+    /// When the element went offline.
+    pub down_since: DateTime<Utc>,
+    /// The target of the step.
+    pub target: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(minutes: i64, provider: &str) -> EscalationStep {
+        EscalationStep { after: chrono::Duration::minutes(minutes), provider: provider.to_string(), target: None }
+    }
+    fn at(minutes: i64) -> DateTime<Utc> {
+        DateTime::<Utc>::UNIX_EPOCH + chrono::Duration::minutes(minutes)
+    }
+    fn indices(steps: &[(usize, EscalationStep)]) -> Vec<usize> {
+        steps.iter().map(|(index, _)| *index).collect()
+    }
+
+    #[test]
+    fn takes_due_steps_after_taken_ones() {
+        let policy = EscalationPolicy {
+            name: "default".to_string(),
+            elements: everything(),
+            steps: vec![step(0, "a"), step(10, "b"), step(30, "c")],
+        };
+        assert_eq!(indices(&policy.due_steps(0, at(0), at(0))), [0]);
+        assert_eq!(indices(&policy.due_steps(0, at(0), at(15))), [0, 1]);
+        assert_eq!(indices(&policy.due_steps(1, at(0), at(15))), [1]);
+        assert_eq!(indices(&policy.due_steps(2, at(0), at(15))), Vec::<usize>::new());
+        assert_eq!(indices(&policy.due_steps(2, at(0), at(30))), [2]);
+        assert_eq!(indices(&policy.due_steps(3, at(0), at(60))), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn step_not_due_holds_back_later_steps() {
+        let policy = EscalationPolicy {
+            name: "unordered".to_string(),
+            elements: everything(),
+            steps: vec![step(0, "a"), step(30, "b"), step(10, "c")],
+        };
+        assert_eq!(indices(&policy.due_steps(1, at(0), at(15))), Vec::<usize>::new());
+        assert_eq!(indices(&policy.due_steps(1, at(0), at(30))), [1, 2]);
+    }
+
+    #[test]
+    fn applies_to_matching_elements() {
+        let policy = EscalationPolicy {
+            name: "minecraft".to_string(),
+            elements: vec!["minecraft-*".to_string(), "website".to_string()],
+            steps: Vec::new(),
+        };
+        assert!(policy.applies_to("minecraft-survival"));
+        assert!(policy.applies_to("website"));
+        assert!(!policy.applies_to("website-2"));
+    }
+}
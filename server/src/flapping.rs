@@ -0,0 +1,123 @@
+use std::collections::{HashMap, VecDeque};
+use chrono::{DateTime, Utc};
+
+/// The id used for notifications the server sends out about flapping elements.
+pub(crate) const COMPONENT_ID: &str = "flapping";
+
+const fn default_transitions() -> usize { 5 }
+const fn default_window() -> chrono::Duration { chrono::Duration::minutes(30) }
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
+/// Configuration for detecting elements that keep going online and offline.
+pub struct FlappingConfig {
+    #[serde(alias="threshold", alias="changes")]
+    #[schemars(extend("x-aliases" = ["threshold", "changes"]))]
+    #[serde(default="default_transitions")]
+    /// How many online/offline transitions within [`Self::window`] mark an element as flapping.
+    ///
+    /// The element stops flapping once fewer than half of these transitions happened within the
+    /// window. `0` disables flap detection.
+    pub transitions: usize,
+
+    #[serde(alias="period", alias="time-frame", alias="time_frame")]
+    #[schemars(extend("x-aliases" = ["period", "time-frame", "time_frame"]))]
+    #[serde(default="default_window")]
+    /// The time frame in which the transitions are counted.
+    pub window: chrono::Duration,
+}
+impl Default for FlappingConfig {
+    fn default() -> Self {
+        Self {
+            transitions: default_transitions(),
+            window: default_window(),
+        }
+    }
+}
+impl FlappingConfig {
+    /// Whether an element with the given number of recent transitions starts flapping.
+    pub(crate) const fn starts_flapping(&self, transitions: usize) -> bool {
+        self.transitions > 0 && transitions >= self.transitions
+    }
+    /// Whether a flapping element with the given number of recent transitions stops flapping.
+    pub(crate) const fn stops_flapping(&self, transitions: usize) -> bool {
+        self.transitions == 0 || transitions < self.transitions.div_ceil(2)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+/// Keeps track of the recent online/offline transitions of all elements.
+pub(crate) struct FlapDetector {
+    transitions: HashMap<String, VecDeque<DateTime<Utc>>>,
+}
+impl FlapDetector {
+    /// Records a transition of the element, returning the number of transitions within the window.
+    pub(crate) fn record(&mut self, element_id: &str, now: DateTime<Utc>, config: &FlappingConfig) -> usize {
+        self.transitions.entry(element_id.to_string())
+            .or_default()
+            .push_back(now);
+        self.recent(element_id, now, config)
+    }
+    /// Returns the number of transitions of the element within the window, forgetting older ones.
+    pub(crate) fn recent(&mut self, element_id: &str, now: DateTime<Utc>, config: &FlappingConfig) -> usize {
+        let Some(transitions) = self.transitions.get_mut(element_id) else { return 0; };
+        while transitions.front().is_some_and(|transition| now - *transition > config.window) {
+            transitions.pop_front();
+        }
+        if transitions.is_empty() {
+            self.transitions.remove(element_id);
+            return 0;
+        }
+        transitions.len()
+    }
+    pub(crate) fn remove(&mut self, element_id: &str) {
+        self.transitions.remove(element_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(transitions: usize) -> FlappingConfig {
+        FlappingConfig { transitions, window: chrono::Duration::minutes(30) }
+    }
+
+    #[test]
+    fn starts_flapping_at_threshold() {
+        let config = config(5);
+        assert!(!config.starts_flapping(4));
+        assert!(config.starts_flapping(5));
+        assert!(config.starts_flapping(6));
+    }
+
+    #[test]
+    fn stops_flapping_below_half_of_threshold() {
+        // half of 5 rounded up is 3
+        let config = config(5);
+        assert!(!config.stops_flapping(3));
+        assert!(config.stops_flapping(2));
+        let config = self::config(4);
+        assert!(!config.stops_flapping(2));
+        assert!(config.stops_flapping(1));
+    }
+
+    #[test]
+    fn zero_transitions_disables_detection() {
+        let config = config(0);
+        assert!(!config.starts_flapping(0));
+        assert!(!config.starts_flapping(100));
+        assert!(config.stops_flapping(100));
+    }
+
+    #[test]
+    fn detector_forgets_transitions_outside_window() {
+        let config = config(5);
+        let start = DateTime::<Utc>::UNIX_EPOCH;
+        let mut detector = FlapDetector::default();
+        assert_eq!(detector.record("a", start, &config), 1);
+        assert_eq!(detector.record("a", start + chrono::Duration::minutes(20), &config), 2);
+        assert_eq!(detector.record("b", start + chrono::Duration::minutes(20), &config), 1);
+        assert_eq!(detector.recent("a", start + chrono::Duration::minutes(31), &config), 1);
+        assert_eq!(detector.recent("a", start + chrono::Duration::minutes(51), &config), 0);
+        assert_eq!(detector.recent("b", start + chrono::Duration::minutes(21), &config), 1);
+    }
+}
@@ -0,0 +1,245 @@
+use std::collections::{HashMap, VecDeque};
+use chrono::{DateTime, Utc};
+use crate::notification::{Notification, NotificationReason};
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+/// A [`Notification`] that was recorded in the history of the server.
+pub struct HistoryEntry {
+    /// When the notification was sent out.
+    pub timestamp: DateTime<Utc>,
+    /// The notification itself.
+    pub notification: Notification,
+}
+
+/// When the online status of an element changed and to what (`None` meaning unknown).
+type OnlineChange = (DateTime<Utc>, Option<bool>);
+/// The recorded [`HistoryEntry`]s, ordered by their timestamp.
+pub(crate) struct History {
+    entries: VecDeque<HistoryEntry>,
+    /// The changes to the online status of every element (see [`Self::online_changes`]), so that
+    /// they don't have to be searched for in all entries.
+    online_changes: HashMap<String, VecDeque<OnlineChange>>,
+    /// The amount of entries (at the back) that weren't written to disk yet.
+    unsaved: usize,
+    /// The amount of pruned entries that are still in the history file.
+    stale: usize,
+    /// Whether the history file needs to be rewritten completely (e.g. because writing to it failed).
+    needs_rewrite: bool,
+}
+/// Changes to the history that need to be written to disk.
+pub(crate) enum HistoryWrite {
+    /// The entries need to be appended to the existing file.
+    Append(Vec<HistoryEntry>),
+    /// The file needs to be replaced with the entries.
+    Rewrite(Vec<HistoryEntry>),
+}
+impl History {
+    pub(crate) fn new() -> Self {
+        Self {
+            entries: VecDeque::new(),
+            online_changes: HashMap::new(),
+            unsaved: 0,
+            stale: 0,
+            needs_rewrite: false,
+        }
+    }
+    /// Creates a history from the given entries.
+    pub(crate) fn from_entries(mut entries: Vec<HistoryEntry>) -> Self {
+        entries.sort_by_key(|entry| entry.timestamp);
+        let mut online_changes = HashMap::<_, VecDeque<_>>::new();
+        for entry in &entries {
+            if let Some(change) = online_change(entry) {
+                online_changes.entry(entry.notification.element_id.clone()).or_default().push_back(change);
+            }
+        }
+        Self {
+            entries: entries.into(),
+            online_changes,
+            unsaved: 0,
+            stale: 0,
+            needs_rewrite: false,
+        }
+    }
+    pub(crate) fn record(&mut self, notification: Notification) {
+        let entry = HistoryEntry {
+            timestamp: Utc::now(),
+            notification,
+        };
+        if let Some(change) = online_change(&entry) {
+            self.online_changes.entry(entry.notification.element_id.clone()).or_default().push_back(change);
+        }
+        self.entries.push_back(entry);
+        self.unsaved += 1;
+    }
+    /// Removes all entries recorded before `cutoff`.
+    ///
+    /// The pruned entries are only removed from the history file once at least as many of them are
+    /// left in it as there are entries remaining (see [`Self::take_unsaved`]), so that the file is
+    /// neither rewritten with every snapshot nor more than twice as large as needed.
+    pub(crate) fn prune(&mut self, cutoff: DateTime<Utc>) {
+        let outdated = self.entries.partition_point(|entry| entry.timestamp < cutoff);
+        if outdated == 0 { return; }
+        debug!("pruning {outdated} history entries");
+        let saved = self.entries.len() - self.unsaved;
+        self.entries.drain(..outdated);
+        self.unsaved = self.unsaved.min(self.entries.len());
+        self.stale += outdated.min(saved);
+        self.online_changes.retain(|_, changes| {
+            let outdated = changes.partition_point(|(timestamp, _)| *timestamp < cutoff);
+            changes.drain(..outdated);
+            !changes.is_empty()
+        });
+    }
+    /// Returns all entries for the given element (or all elements if `None`) that were recorded in
+    /// the given time frame.
+    pub(crate) fn query(&self, element_id: Option<&str>, since: Option<DateTime<Utc>>, until: Option<DateTime<Utc>>) -> Vec<HistoryEntry> {
+        let start = since.map_or(0, |since| self.entries.partition_point(|entry| entry.timestamp < since));
+        let end = until.map_or(self.entries.len(), |until| self.entries.partition_point(|entry| entry.timestamp <= until));
+        self.entries.range(start..end.max(start))
+            .filter(|entry| element_id.is_none_or(|id| entry.notification.element_id == id))
+            .cloned()
+            .collect()
+    }
+    /// Returns all recorded changes to the online status of the given element (ordered by time).
+    ///
+    /// `None` means that the health of the element became unknown.
+    pub(crate) fn online_changes(&self, element_id: &str) -> impl Iterator<Item=OnlineChange> + '_ {
+        self.online_changes.get(element_id).into_iter().flatten().copied()
+    }
+    /// Returns the changes that need to be written to disk (if any).
+    pub(crate) fn take_unsaved(&mut self) -> Option<HistoryWrite> {
+        if self.needs_rewrite || (self.stale > 0 && self.stale >= self.entries.len()) {
+            self.needs_rewrite = false;
+            self.unsaved = 0;
+            self.stale = 0;
+            return Some(HistoryWrite::Rewrite(self.entries.iter().cloned().collect()));
+        }
+        if self.unsaved == 0 { return None; }
+        let unsaved = self.entries.range(self.entries.len() - self.unsaved..)
+            .cloned()
+            .collect();
+        self.unsaved = 0;
+        Some(HistoryWrite::Append(unsaved))
+    }
+    /// Writing the changes returned by [`Self::take_unsaved`] failed (maybe partially), so the
+    /// file has to be rewritten completely.
+    pub(crate) fn write_failed(&mut self) {
+        self.needs_rewrite = true;
+    }
+}
+/// The online status the entry changed its element to (if it is a health change).
+fn online_change(entry: &HistoryEntry) -> Option<OnlineChange> {
+    match &entry.notification.reason {
+        NotificationReason::HealthChanged(change) => Some((entry.timestamp, change.new.online())),
+        NotificationReason::NewElement(health) => Some((entry.timestamp, health.online())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notification::HealthChange;
+    use crate::state::Health;
+
+    fn at(minutes: i64) -> DateTime<Utc> {
+        DateTime::<Utc>::UNIX_EPOCH + chrono::Duration::minutes(minutes)
+    }
+    fn notification(element_id: &str) -> Notification {
+        Notification::new("test".to_string(), element_id.to_string(), NotificationReason::NewElement(Health::default()))
+    }
+    fn entry(minutes: i64, element_id: &str) -> HistoryEntry {
+        HistoryEntry { timestamp: at(minutes), notification: notification(element_id) }
+    }
+    fn elements(entries: &[HistoryEntry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.notification.element_id.as_str()).collect()
+    }
+
+    #[test]
+    fn query_bounds_are_inclusive() {
+        let history = History::from_entries(vec![entry(2, "c"), entry(0, "a"), entry(1, "b"), entry(3, "a")]);
+        assert_eq!(elements(&history.query(None, None, None)), ["a", "b", "c", "a"]);
+        assert_eq!(elements(&history.query(None, Some(at(1)), Some(at(2)))), ["b", "c"]);
+        assert_eq!(elements(&history.query(Some("a"), Some(at(0)), None)), ["a", "a"]);
+        assert_eq!(elements(&history.query(Some("a"), None, Some(at(2)))), ["a"]);
+        assert!(history.query(None, Some(at(3)), Some(at(1))).is_empty());
+    }
+
+    #[test]
+    fn appends_only_new_entries() {
+        let mut history = History::from_entries(vec![entry(0, "a")]);
+        assert!(history.take_unsaved().is_none());
+        history.record(notification("b"));
+        history.record(notification("c"));
+        let Some(HistoryWrite::Append(entries)) = history.take_unsaved() else { panic!("expected an append") };
+        assert_eq!(elements(&entries), ["b", "c"]);
+        assert!(history.take_unsaved().is_none());
+    }
+
+    #[test]
+    fn rewrites_after_a_failed_write() {
+        let mut history = History::from_entries(vec![entry(0, "a")]);
+        history.record(notification("b"));
+        assert!(matches!(history.take_unsaved(), Some(HistoryWrite::Append(_))));
+        history.write_failed();
+        let Some(HistoryWrite::Rewrite(entries)) = history.take_unsaved() else { panic!("expected a rewrite") };
+        assert_eq!(elements(&entries), ["a", "b"]);
+        assert!(history.take_unsaved().is_none());
+    }
+
+    #[test]
+    fn pruning_only_rewrites_once_the_file_is_mostly_stale() {
+        let mut history = History::from_entries((0..4).map(|minute| entry(minute, "a")).collect());
+        history.prune(at(1));
+        assert_eq!(history.query(None, None, None).len(), 3);
+        // a single stale entry in the file is fine, new entries are still appended.
+        history.record(notification("b"));
+        assert!(matches!(history.take_unsaved(), Some(HistoryWrite::Append(entries)) if entries.len() == 1));
+        history.prune(at(2));
+        // 2 stale entries and 3 remaining ones.
+        assert!(history.take_unsaved().is_none());
+        history.prune(at(4));
+        // 4 stale entries and only "b" left.
+        let Some(HistoryWrite::Rewrite(entries)) = history.take_unsaved() else { panic!("expected a rewrite") };
+        assert_eq!(elements(&entries), ["b"]);
+        assert!(history.take_unsaved().is_none());
+    }
+
+    #[test]
+    fn pruned_unsaved_entries_arent_stale() {
+        let mut history = History::new();
+        history.record(notification("a"));
+        history.prune(Utc::now() + chrono::Duration::minutes(1));
+        // the entry never made it into the file, so there is nothing to write.
+        assert!(history.take_unsaved().is_none());
+    }
+
+    #[test]
+    fn online_changes_are_kept_per_element() {
+        let health_change = |minutes, element_id: &str, online: bool| HistoryEntry {
+            timestamp: at(minutes),
+            notification: Notification::new("test".to_string(), element_id.to_string(), NotificationReason::HealthChanged(
+                HealthChange { old: (!online).into(), new: online.into() },
+            )),
+        };
+        let mut history = History::from_entries(vec![
+            health_change(2, "a", false),
+            entry(0, "a"),
+            health_change(1, "b", false),
+            HistoryEntry {
+                timestamp: at(3),
+                notification: Notification::new("test".to_string(), "a".to_string(),
+                    NotificationReason::DeleteAttribute("x".to_string(), crate::AttributeValue::Unit)),
+            },
+            health_change(4, "a", true),
+        ]);
+        let changes = |history: &History, element_id| history.online_changes(element_id).collect::<Vec<_>>();
+        assert_eq!(changes(&history, "a"), [(at(0), None), (at(2), Some(false)), (at(4), Some(true))]);
+        assert_eq!(changes(&history, "b"), [(at(1), Some(false))]);
+        history.prune(at(2));
+        assert_eq!(changes(&history, "a"), [(at(2), Some(false)), (at(4), Some(true))]);
+        assert!(changes(&history, "b").is_empty());
+        history.record(notification("b"));
+        assert_eq!(changes(&history, "b").len(), 1);
+    }
+}
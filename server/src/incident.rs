@@ -0,0 +1,152 @@
+use chrono::{DateTime, Utc};
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+/// An outage of an element.
+///
+/// Incidents are opened when an element goes offline (or is offline when it is first seen) and
+/// closed once it comes back online.
+pub struct Incident {
+    /// The unique id of the incident.
+    pub id: u64,
+    /// The id of the element that went offline.
+    pub element_id: String,
+    /// The id of the component that reported the element as offline.
+    pub component_id: String,
+    /// When the element went offline.
+    pub start: DateTime<Utc>,
+    /// When the element came back online (`None` while the incident is still open).
+    pub end: Option<DateTime<Utc>>,
+    /// Who acknowledged the incident (if anyone).
+    pub acknowledged: Option<Acknowledgement>,
+    /// Notes added to the incident.
+    pub notes: Vec<IncidentNote>,
+    /// How many steps of the [`EscalationPolicy`](crate::EscalationPolicy) were taken.
+    #[serde(default)]
+    pub escalation_steps: usize,
+    /// When the delays of the escalation steps started counting if not at `start` (because a
+    /// maintenance window held the escalation back until then).
+    #[serde(default)]
+    pub escalation_start: Option<DateTime<Utc>>,
+}
+impl Incident {
+    /// Whether the incident is still ongoing.
+    #[must_use]
+    pub const fn is_open(&self) -> bool {
+        self.end.is_none()
+    }
+}
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+/// Marks that someone is taking care of an [`Incident`].
+pub struct Acknowledgement {
+    /// Who acknowledged the incident.
+    pub by: String,
+    /// When the incident was acknowledged.
+    pub timestamp: DateTime<Utc>,
+}
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+/// A note added to an [`Incident`].
+pub struct IncidentNote {
+    /// Who wrote the note.
+    pub author: String,
+    /// When the note was added.
+    pub timestamp: DateTime<Utc>,
+    /// The content of the note.
+    pub text: String,
+}
+
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+/// All [`Incident`]s known to the server.
+pub(crate) struct Incidents {
+    next_id: u64,
+    incidents: Vec<Incident>,
+}
+impl Incidents {
+    /// Opens a new incident for the element (unless one is already open).
+    pub(crate) fn open(&mut self, element_id: &str, component_id: &str) {
+        if self.incidents.iter().any(|incident| incident.is_open() && incident.element_id == element_id) {
+            return;
+        }
+        debug!("opening incident {} for {element_id}", self.next_id);
+        self.incidents.push(Incident {
+            id: self.next_id,
+            element_id: element_id.to_string(),
+            component_id: component_id.to_string(),
+            start: Utc::now(),
+            end: None,
+            acknowledged: None,
+            notes: Vec::new(),
+            escalation_steps: 0,
+            escalation_start: None,
+        });
+        self.next_id += 1;
+    }
+    /// Closes the open incident of the element (if any).
+    pub(crate) fn close(&mut self, element_id: &str) {
+        if let Some(incident) = self.incidents.iter_mut()
+            .find(|incident| incident.is_open() && incident.element_id == element_id) {
+            debug!("closing incident {} for {element_id}", incident.id);
+            incident.end = Some(Utc::now());
+        }
+    }
+    pub(crate) fn get(&self, id: u64) -> Option<&Incident> {
+        self.incidents.iter().find(|incident| incident.id == id)
+    }
+    pub(crate) fn get_mut(&mut self, id: u64) -> Option<&mut Incident> {
+        self.incidents.iter_mut().find(|incident| incident.id == id)
+    }
+    pub(crate) fn iter(&self) -> impl Iterator<Item=&Incident> {
+        self.incidents.iter()
+    }
+    /// Removes the incidents that were closed before the cutoff, returning how many were removed.
+    pub(crate) fn prune(&mut self, cutoff: DateTime<Utc>) -> usize {
+        let before = self.incidents.len();
+        self.incidents.retain(|incident| incident.end.is_none_or(|end| end >= cutoff));
+        before - self.incidents.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_elements(incidents: &Incidents) -> Vec<&str> {
+        incidents.iter()
+            .filter(|incident| incident.is_open())
+            .map(|incident| incident.element_id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn one_open_incident_per_element() {
+        let mut incidents = Incidents::default();
+        incidents.open("a", "test");
+        incidents.open("a", "test");
+        incidents.open("b", "test");
+        assert_eq!(open_elements(&incidents), ["a", "b"]);
+        incidents.close("a");
+        incidents.close("c");
+        assert_eq!(open_elements(&incidents), ["b"]);
+        incidents.open("a", "test");
+        assert_eq!(open_elements(&incidents), ["b", "a"]);
+        let ids = incidents.iter().map(|incident| incident.id).collect::<Vec<_>>();
+        assert_eq!(ids, [0, 1, 2]);
+        assert_eq!(incidents.get(2).map(|incident| incident.element_id.as_str()), Some("a"));
+    }
+
+    #[test]
+    fn prune_only_removes_incidents_closed_before_cutoff() {
+        let mut incidents = Incidents::default();
+        for element in ["old", "new", "open"] {
+            incidents.open(element, "test");
+        }
+        incidents.close("old");
+        incidents.close("new");
+        let now = Utc::now();
+        incidents.get_mut(0).expect("exists").end = Some(now - chrono::Duration::days(2));
+        assert_eq!(incidents.prune(now - chrono::Duration::days(1)), 1);
+        assert_eq!(incidents.iter().map(|incident| incident.element_id.as_str()).collect::<Vec<_>>(), ["new", "open"]);
+        // ids aren't reused after pruning.
+        incidents.open("old", "test");
+        assert_eq!(incidents.iter().last().map(|incident| incident.id), Some(3));
+    }
+}
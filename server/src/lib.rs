@@ -37,22 +37,81 @@ mod notification_provider;
 mod component;
 mod server;
 mod notification;
+mod history;
+mod uptime;
+mod incident;
+mod maintenance;
+mod flapping;
+mod confirmation;
+mod escalation;
+mod digest;
+mod delivery;
+mod update;
+mod registry;
+mod reload;
+mod dependency;
+mod bus;
+mod snapshot;
+mod schema;
+#[cfg(test)]
+mod test_util;
 
 pub use server::{
     ComponentHandle,
+    DryRun,
     ServerHandle as Server,
 };
 pub use config::Config;
 pub use component::{
     Component,
     RequestHandle,
+    LifecycleFuture,
 };
 pub use notification::{
     Notification,
     NotificationReason,
+    HealthChange,
 };
 pub use state::{
     State,
     AttributeValue,
+    Health,
+    HealthStatus,
+};
+pub use history::HistoryEntry;
+pub use uptime::Uptime;
+pub use incident::{
+    Incident,
+    Acknowledgement,
+    IncidentNote,
+};
+pub use maintenance::{
+    MaintenanceWindow,
+    MaintenanceMode,
+};
+pub use flapping::FlappingConfig;
+pub use confirmation::Confirmation;
+pub use escalation::{
+    EscalationPolicy,
+    EscalationStep,
+    Escalation,
+};
+pub use delivery::Delivery;
+pub use digest::Digest;
+pub use bus::SubscriberMetrics;
+pub use snapshot::StatesSnapshot;
+pub use update::ElementUpdate;
+pub use registry::ComponentRegistry;
+pub use reload::{
+    ReloadReport,
+    ReloadResult,
+};
+pub use dependency::{
+    DependencyError,
+    DependencyGraph,
+};
+pub use notification_provider::{
+    NotificationProvider,
+    ReliableNotificationProvider,
+    DeliveryFuture,
 };
-pub use notification_provider::NotificationProvider;
@@ -0,0 +1,129 @@
+use chrono::{DateTime, Utc};
+
+/// The id used for notifications the server sends out about maintenance windows.
+pub(crate) const COMPONENT_ID: &str = "maintenance";
+/// The attribute set on elements while they are in maintenance.
+pub(crate) const ATTRIBUTE_ID: &str = "maintenance";
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
+/// A (possibly recurring) time frame in which some elements are under maintenance.
+pub struct MaintenanceWindow {
+    #[serde(alias="element", alias="elements-pattern", alias="patterns")]
+    #[schemars(extend("x-aliases" = ["element", "elements-pattern", "patterns"]))]
+    /// The elements that are affected.
+    ///
+    /// `*` matches any (possibly empty) sequence of characters, e.g. `minecraft.*`.
+    pub elements: Vec<String>,
+
+    #[serde(alias="from", alias="begin")]
+    #[schemars(extend("x-aliases" = ["from", "begin"]))]
+    /// When the (first) maintenance starts.
+    pub start: DateTime<Utc>,
+
+    #[serde(alias="length")]
+    #[schemars(extend("x-aliases" = ["length"]))]
+    /// How long the maintenance lasts.
+    pub duration: chrono::Duration,
+
+    #[serde(alias="every", alias="recurring", alias="interval")]
+    #[schemars(extend("x-aliases" = ["every", "recurring", "interval"]))]
+    #[serde(default)]
+    /// Repeats the maintenance after the given time (starting from `start`).
+    pub repeat: Option<chrono::Duration>,
+
+    #[serde(alias="reason", alias="name")]
+    #[schemars(extend("x-aliases" = ["reason", "name"]))]
+    #[serde(default)]
+    /// A short description of the maintenance, shown in the frontend.
+    pub description: Option<String>,
+
+    #[serde(default)]
+    /// What happens to notifications of the affected elements.
+    pub mode: MaintenanceMode,
+}
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
+#[serde(rename_all="snake_case")]
+/// What happens to notifications of elements under maintenance.
+pub enum MaintenanceMode {
+    #[default]
+    #[serde(alias="hold", alias="hold-back", alias="hold_back")]
+    #[schemars(extend("x-aliases" = ["hold", "hold-back", "hold_back"]))]
+    /// Notifications are only sent to providers that keep clients in sync (e.g. websockets).
+    Suppress,
+    /// Notifications are sent to all providers but marked as being sent during maintenance.
+    Mark,
+}
+impl MaintenanceWindow {
+    /// Whether the window is active at the given point in time.
+    pub(crate) fn is_active(&self, now: DateTime<Utc>) -> bool {
+        if now < self.start { return false; }
+        let mut elapsed = now - self.start;
+        if let Some(repeat) = self.repeat.filter(|repeat| !repeat.is_zero()) {
+            let repeat_ms = repeat.num_milliseconds();
+            elapsed = chrono::Duration::milliseconds(elapsed.num_milliseconds() % repeat_ms);
+        }
+        elapsed < self.duration
+    }
+    /// Whether the element is affected by the window.
+    pub(crate) fn applies_to(&self, element_id: &str) -> bool {
+        self.elements.iter().any(|pattern| matches_pattern(pattern, element_id))
+    }
+    /// The value of the maintenance attribute while the window is active.
+    pub(crate) fn attribute_value(&self) -> crate::AttributeValue {
+        self.description.clone()
+            .map_or(crate::AttributeValue::Unit, crate::AttributeValue::String)
+    }
+}
+/// Matches the text against a pattern where `*` matches any (possibly empty) sequence of characters.
+pub(crate) fn matches_pattern(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else { return false; };
+    let mut parts = parts.collect::<Vec<_>>();
+    let Some(last) = parts.pop() else { return rest.is_empty(); };
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::matches_pattern;
+
+    #[test]
+    fn matches_without_wildcards() {
+        assert!(matches_pattern("website", "website"));
+        assert!(!matches_pattern("website", "website-2"));
+        assert!(!matches_pattern("website", "web"));
+        assert!(matches_pattern("", ""));
+        assert!(!matches_pattern("", "website"));
+    }
+
+    #[test]
+    fn matches_wildcards() {
+        assert!(matches_pattern("*", ""));
+        assert!(matches_pattern("*", "anything"));
+        assert!(matches_pattern("minecraft-*", "minecraft-"));
+        assert!(matches_pattern("minecraft-*", "minecraft-survival"));
+        assert!(!matches_pattern("minecraft-*", "website"));
+        assert!(matches_pattern("*-backup", "db-backup"));
+        assert!(!matches_pattern("*-backup", "db-backup-2"));
+        assert!(matches_pattern("a*b*c", "abc"));
+        assert!(matches_pattern("a*b*c", "a-b-b-c"));
+        assert!(!matches_pattern("a*b*c", "a-c-b"));
+        assert!(matches_pattern("**", "x"));
+    }
+
+    #[test]
+    fn wildcards_dont_reuse_matched_characters() {
+        assert!(!matches_pattern("a*a", "a"));
+        assert!(matches_pattern("a*a", "aa"));
+        assert!(!matches_pattern("ab*ba", "aba"));
+        assert!(matches_pattern("ab*ba", "abba"));
+        assert!(!matches_pattern("*b*b", "b"));
+    }
+}
@@ -1,9 +1,11 @@
-use crate::state::AttributeValue;
+use crate::escalation::Escalation;
+use crate::state::{AttributeValue, Health, State};
 
 #[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
 /// A Notification of a changing state for an element.
 pub struct Notification {
-    /// The id of the component that triggered this change.
+    /// The id of the component that triggered this change (or the name of its instance, see
+    /// [`Config::instances`](crate::Config::instances)).
     ///
     /// Primarily meant for filtering.
     pub component_id: String,
@@ -11,25 +13,24 @@ pub struct Notification {
     pub element_id: String,
     /// The reason this notification was sent out.
     pub reason: NotificationReason,
+    /// Whether the element was under maintenance when the notification was sent out.
+    #[serde(default)]
+    pub maintenance: bool,
 }
 impl Notification {
     /// creates a new [`Notification`]
     #[must_use]
     pub const fn new(component_id: String, element_id: String, reason: NotificationReason) -> Self {
-        Self { component_id, element_id, reason, }
+        Self { component_id, element_id, reason, maintenance: false, }
     }
 }
 
 #[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
 /// Reasons why a notification might be sent out
 pub enum NotificationReason {
-    /// The online status of the element changed.
-    ///
-    /// The inner state is the new online state.
-    ///
-    /// # Note
-    /// When receiving such a notification it is guaranteed that the previous state was `!current`.
-    OnlineStatusChanged(bool),
+    /// The health of the element changed.
+    #[serde(alias="OnlineStatusChanged")]
+    HealthChanged(HealthChange),
     /// An attribute was created.
     AttributeCreated(String, AttributeValue),
     /// An attribute was changed.
@@ -42,6 +43,60 @@ pub enum NotificationReason {
     /// # Note
     /// contains the last attribute value.
     DeleteAttribute(String, AttributeValue),
-    /// A new element was created and was designated the given online status.
-    NewElement(bool),
-}
\ No newline at end of file
+    /// A new element was created and was designated the given health.
+    NewElement(Health),
+    /// The element started going online and offline repeatedly.
+    ///
+    /// Instead of notifications about the individual health changes (which are suppressed while the
+    /// element is flapping) only this is sent out. Contains the current health.
+    FlappingStarted(Health),
+    /// The element stopped going online and offline repeatedly. Contains the current health.
+    FlappingStopped(Health),
+    /// The element is still offline and nobody acknowledged the incident, so it is escalated.
+    ///
+    /// Only sent to the provider of the [`EscalationStep`](crate::EscalationStep) via
+    /// [`NotificationProvider::escalate`](crate::NotificationProvider::escalate).
+    Escalation(Escalation),
+    /// The element was removed.
+    ///
+    /// # Note
+    /// contains the last state of the element.
+    DeleteElement(State),
+}
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(from="HealthChangeRepr")]
+/// A change of the [`Health`] of an element.
+///
+/// # Note
+/// When receiving such a notification it is guaranteed that `old != new`.
+pub struct HealthChange {
+    /// The previous health.
+    pub old: Health,
+    /// The new health.
+    pub new: Health,
+}
+impl HealthChange {
+    /// Whether the element went online or offline (instead of e.g. just becoming degraded).
+    #[must_use]
+    pub fn online_changed(&self) -> bool {
+        self.old.online() != self.new.online()
+    }
+}
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+/// Allows deserializing [`HealthChange`]s from the old `bool` based online status changes.
+enum HealthChangeRepr {
+    Online(bool),
+    Change {
+        old: Health,
+        new: Health,
+    },
+}
+impl From<HealthChangeRepr> for HealthChange {
+    fn from(value: HealthChangeRepr) -> Self {
+        match value {
+            HealthChangeRepr::Online(online) => Self { old: (!online).into(), new: online.into() },
+            HealthChangeRepr::Change { old, new } => Self { old, new },
+        }
+    }
+}
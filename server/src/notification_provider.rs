@@ -1,8 +1,62 @@
+use std::pin::Pin;
 use crate::notification::Notification;
 use crate::Component;
 
+/// A pinned future that sends out a single delivery, resolving to the reason if it failed.
+pub type DeliveryFuture = Pin<Box<dyn Future<Output=Result<(), String>> + Send>>;
+
 /// [`Component`] that can send out notifications over a specific channel.
 pub trait NotificationProvider: Component {
+    /// Whether the provider also receives notifications that are suppressed due to maintenance.
+    ///
+    /// Should be set by providers that keep clients in sync with the states (instead of alerting
+    /// someone), so that their clients don't miss any changes.
+    const RECEIVES_SUPPRESSED: bool = false;
     /// Notify via the specific channel about the [`Notification`]
+    ///
+    /// # Note
+    /// Notifications are handed to each provider from its own task (see
+    /// [`ServerHandle::notification_bus`](crate::Server::notification_bus)) on a thread meant for
+    /// blocking, without the server being locked. While this runs the provider can't be
+    /// reconfigured (or removed), so it still shouldn't block for long (e.g. queue a delivery or
+    /// spawn a task instead).
     fn notify(&self, notification: Notification);
-}
\ No newline at end of file
+    /// Notify via the specific channel about multiple [`Notification`]s that belong together (e.g.
+    /// the changes of a single [`ElementUpdate`](crate::ElementUpdate)).
+    ///
+    /// Calls [`Self::notify`] for every notification by default.
+    fn notify_batch(&self, notifications: Vec<Notification>) {
+        for notification in notifications {
+            self.notify(notification);
+        }
+    }
+    /// Sends out a notification about an incident being escalated (see
+    /// [`EscalationPolicy`](crate::EscalationPolicy)) to the given target.
+    ///
+    /// What the target means is up to the provider (e.g. a ntfy target or an E-Mail address).
+    /// Providers that don't support escalations ignore them by default.
+    fn escalate(&self, notification: Notification, target: Option<&str>) {
+        let _ = (notification, target);
+        warn!("notification provider {} doesn't support escalations", Self::ID);
+    }
+}
+/// [`NotificationProvider`] whose messages are sent out via the delivery queue of the server.
+///
+/// Instead of sending messages directly in [`NotificationProvider::notify`], the provider queues
+/// them via [`ComponentHandle::queue_delivery`](crate::ComponentHandle::queue_delivery). The server
+/// then calls [`Self::deliver`], retrying failed deliveries with exponential backoff and moving
+/// them to the dead letters once they run out of attempts. Queued deliveries are persisted across
+/// restarts.
+pub trait ReliableNotificationProvider: NotificationProvider {
+    /// A single message (e.g. an E-Mail to one recipient).
+    ///
+    /// As these are persisted, they should contain everything needed to send the message, except
+    /// for secrets, which should be taken from the config when delivering.
+    type Delivery: serde::Serialize + serde::de::DeserializeOwned;
+    /// Sends out the delivery.
+    ///
+    /// # Note
+    /// This is called while the server is locked, so the [`ComponentHandle`](crate::ComponentHandle)
+    /// must only be used inside the returned future.
+    fn deliver(&self, delivery: Self::Delivery) -> DeliveryFuture;
+}
@@ -0,0 +1,143 @@
+use crate::notification_provider::{NotificationProvider, ReliableNotificationProvider};
+use crate::server::ServerHandle;
+use crate::{Component, Config};
+use schemars::generate::{SchemaGenerator, SchemaSettings};
+use schemars::transform::{RecursiveTransform, Transform};
+use schemars::Schema;
+
+#[derive(Clone, Copy)]
+/// How to add a single registered component to a server and how to check its config.
+pub(crate) struct Registration {
+    pub(crate) id: &'static str,
+    pub(crate) add: fn(&ServerHandle),
+    check: fn(toml::Value) -> Result<serde_json::Value, toml::de::Error>,
+    check_source: fn(toml::de::ValueDeserializer<'_>) -> Result<(), toml::de::Error>,
+    schema: fn(&mut SchemaGenerator) -> Schema,
+}
+impl Registration {
+    /// Parses the config of the component, returning it (including defaults) as JSON.
+    pub(crate) fn check(&self, config: toml::Value) -> Result<serde_json::Value, toml::de::Error> {
+        (self.check)(config)
+    }
+}
+fn add_component<C: Component>(server: &ServerHandle) {
+    server.add_component::<C>();
+}
+fn add_notification_provider<P: NotificationProvider>(server: &ServerHandle) {
+    server.add_notification_provider::<P>();
+}
+fn add_reliable_notification_provider<P: ReliableNotificationProvider>(server: &ServerHandle) {
+    server.add_reliable_notification_provider::<P>();
+}
+fn check_config<C: Component>(config: toml::Value) -> Result<serde_json::Value, toml::de::Error> {
+    use serde::Deserialize;
+    let config = C::Config::deserialize(config)?;
+    Ok(serde_json::to_value(config).unwrap_or_default())
+}
+fn check_config_source<C: Component>(source: toml::de::ValueDeserializer<'_>) -> Result<(), toml::de::Error> {
+    use serde::Deserialize;
+    C::Config::deserialize(source).map(drop)
+}
+fn config_schema<C: Component>(generator: &mut SchemaGenerator) -> Schema {
+    generator.subschema_for::<C::Config>()
+}
+
+#[derive(Clone, Default)]
+/// All [`Component`]s that are compiled into the program (in the order they are added to the
+/// server).
+///
+/// Used for starting the server (see [`Server::add_components`](crate::Server::add_components)),
+/// checking the config of every component and adding components that are no longer ignored when
+/// reloading the config.
+pub struct ComponentRegistry {
+    registrations: Vec<Registration>,
+}
+impl ComponentRegistry {
+    /// creates a new, empty registry.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+    fn register<C: Component>(&mut self, add: fn(&ServerHandle)) -> &mut Self {
+        if self.contains(C::ID) {
+            warn!("component {} was registered twice! Ignoring.", C::ID);
+            return self;
+        }
+        self.registrations.push(Registration {
+            id: C::ID,
+            add,
+            check: check_config::<C>,
+            check_source: check_config_source::<C>,
+            schema: config_schema::<C>,
+        });
+        self
+    }
+    /// Registers a [`Component`] (see [`Server::add_component`](crate::Server::add_component)).
+    pub fn component<C: Component>(&mut self) -> &mut Self {
+        self.register::<C>(add_component::<C>)
+    }
+    /// Registers a [`NotificationProvider`] (see
+    /// [`Server::add_notification_provider`](crate::Server::add_notification_provider)).
+    pub fn notification_provider<P: NotificationProvider>(&mut self) -> &mut Self {
+        self.register::<P>(add_notification_provider::<P>)
+    }
+    /// Registers a [`ReliableNotificationProvider`] (see
+    /// [`Server::add_reliable_notification_provider`](crate::Server::add_reliable_notification_provider)).
+    pub fn reliable_notification_provider<P: ReliableNotificationProvider>(&mut self) -> &mut Self {
+        self.register::<P>(add_reliable_notification_provider::<P>)
+    }
+    /// Whether a component with the given id is registered.
+    #[must_use]
+    pub fn contains(&self, id: &str) -> bool {
+        self.registrations.iter().any(|registration| registration.id == id)
+    }
+    /// The ids of all registered components.
+    pub fn ids(&self) -> impl Iterator<Item=&'static str> {
+        self.registrations.iter().map(|registration| registration.id)
+    }
+    /// Parses the given config for the registered component with the given id.
+    ///
+    /// Returns the parsed config (including defaults) or `None` if no such component is registered.
+    #[must_use]
+    pub fn check_config(&self, id: &str, config: toml::Value) -> Option<Result<serde_json::Value, toml::de::Error>> {
+        let registration = self.registrations.iter().find(|registration| registration.id == id)?;
+        Some(registration.check(config))
+    }
+    /// Parses the config for the registered component with the given id from (a part of) a parsed
+    /// config file, so that errors carry the span (in the file) they occurred at.
+    ///
+    /// Returns `None` if no such component is registered.
+    #[must_use]
+    pub fn check_config_source(&self, id: &str, source: toml::de::ValueDeserializer<'_>) -> Option<Result<(), toml::de::Error>> {
+        let registration = self.registrations.iter().find(|registration| registration.id == id)?;
+        Some((registration.check_source)(source))
+    }
+    /// The JSON Schema (draft 2020-12) of the whole config, including the configs of all
+    /// registered components.
+    ///
+    /// The config of a component is either its own config or, if it has named instances (see
+    /// [`Config::instances`]), a table with the configs of its instances.
+    #[must_use]
+    pub fn config_schema(&self) -> serde_json::Value {
+        let mut generator = SchemaSettings::draft2020_12().for_deserialize().into_generator();
+        let (components, conditions) = self.registrations.iter()
+            .map(|registration| {
+                let schema = (registration.schema)(&mut generator);
+                crate::schema::component(registration.id, &schema)
+            })
+            .collect::<(Vec<_>, Vec<_>)>();
+        let mut schema = generator.into_root_schema_for::<Config>();
+        if let Some(serde_json::Value::Object(properties)) = schema.get_mut("properties") {
+            properties.extend(components);
+            properties.insert("include".to_string(), crate::schema::include());
+        }
+        if let serde_json::Value::Array(all_of) = schema.ensure_object().entry("allOf").or_insert_with(|| serde_json::Value::Array(Vec::new())) {
+            all_of.extend(conditions);
+        }
+        RecursiveTransform(crate::schema::resolve_aliases).transform(&mut schema);
+        schema.to_value()
+    }
+    pub(crate) fn registrations(&self) -> impl Iterator<Item=&Registration> {
+        self.registrations.iter()
+    }
+}
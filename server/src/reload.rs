@@ -0,0 +1,67 @@
+use std::collections::BTreeMap;
+use chrono::{DateTime, Utc};
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+/// What reloading the config did to a single component (instance).
+pub enum ReloadResult {
+    /// The component was reconfigured with its new config.
+    Applied,
+    /// The config of the component didn't change, so it was left alone.
+    Unchanged,
+    /// The component was added (e.g. because it is no longer ignored).
+    Added,
+    /// The component was removed (e.g. because it is ignored now).
+    Removed,
+    /// The new config of the component is invalid or the component couldn't be (re)configured.
+    Failed(String),
+}
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+/// The outcome of reloading the config.
+///
+/// If the new config (or the config of any component) is invalid or any component can't be
+/// reconfigured with it, the previous config is kept and nothing changes.
+pub struct ReloadReport {
+    /// When the config was reloaded.
+    pub timestamp: DateTime<Utc>,
+    /// Whether the new config was applied.
+    pub applied: bool,
+    /// Why the new config wasn't applied (if it wasn't).
+    pub error: Option<String>,
+    /// What happened to each component (by instance name).
+    pub components: BTreeMap<String, ReloadResult>,
+}
+impl ReloadReport {
+    pub(crate) fn new() -> Self {
+        Self {
+            timestamp: Utc::now(),
+            applied: true,
+            error: None,
+            components: BTreeMap::new(),
+        }
+    }
+    /// A report about the config being rejected.
+    pub(crate) fn rejected(error: String, components: BTreeMap<String, ReloadResult>) -> Self {
+        Self {
+            applied: false,
+            error: Some(error),
+            components,
+            ..Self::new()
+        }
+    }
+    /// Logs the result of the reload.
+    pub(crate) fn log(&self) {
+        for (instance, result) in &self.components {
+            match result {
+                ReloadResult::Applied => info!("reconfigured component {instance}"),
+                ReloadResult::Unchanged => debug!("config of component {instance} didn't change"),
+                ReloadResult::Added => info!("added component {instance}"),
+                ReloadResult::Removed => info!("removed component {instance}"),
+                ReloadResult::Failed(e) => error!("reloading component {instance} failed: {e}"),
+            }
+        }
+        match &self.error {
+            None => info!("reloaded config"),
+            Some(e) => error!("kept the previous config: {e}"),
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use schemars::Schema;
+use serde_json::{json, Map, Value};
+
+/// The extension listing the aliases of a property or enum variant.
+///
+/// schemars ignores `#[serde(alias)]`, so the aliases have to be repeated via
+/// `#[schemars(extend("x-aliases" = [...]))]` for the schema to accept them.
+const ALIASES: &str = "x-aliases";
+
+/// The `include` directive (see [`Config::load`](crate::Config::load)), which is resolved before
+/// the config is parsed.
+pub(crate) fn include() -> Value {
+    json!({
+        "description": "Files (or directories of `*.toml` files) included into this file, relative to it.",
+        "anyOf": [
+            { "type": "string" },
+            { "type": "array", "items": { "type": "string" } },
+        ],
+        "x-aliases": ["includes"],
+    })
+}
+/// The property of the component with the given id and config schema in the schema of the whole
+/// config, together with the condition choosing between its single config and the configs of its
+/// named instances (depending on whether `instances` contains the id).
+pub(crate) fn component(id: &str, config: &Schema) -> ((String, Value), Value) {
+    let instances = json!({
+        "type": "object",
+        "description": format!("The configs of the named instances of `{id}` (see `instances`)."),
+        "additionalProperties": config,
+    });
+    let has_instances = ["instances", "instance"].map(|key| json!({
+        "required": [key],
+        "properties": { key: { "required": [id] } },
+    }));
+    let condition = json!({
+        "if": { "anyOf": has_instances },
+        "then": { "properties": { id: instances } },
+        "else": { "properties": { id: config } },
+    });
+    // the property itself allows both, so that editors can complete either.
+    ((id.to_string(), json!({ "anyOf": [config, instances] })), condition)
+}
+/// Adds the `x-aliases` of the properties and enum variants of the schema as actual properties and
+/// variants (does not touch subschemas).
+pub(crate) fn resolve_aliases(schema: &mut Schema) {
+    let Some(schema) = schema.as_object_mut() else { return };
+    resolve_property_aliases(schema);
+    for key in ["oneOf", "anyOf"] {
+        if let Some(Value::Array(variants)) = schema.get_mut(key) {
+            resolve_variant_aliases(variants);
+        }
+    }
+}
+/// Takes the aliases out of the (sub)schema.
+fn take_aliases(schema: &mut Value) -> Vec<String> {
+    let Some(Value::Array(aliases)) = schema.as_object_mut().and_then(|schema| schema.remove(ALIASES)) else {
+        return Vec::new();
+    };
+    aliases.into_iter()
+        .filter_map(|alias| alias.as_str().map(str::to_string))
+        .collect()
+}
+fn resolve_property_aliases(schema: &mut Map<String, Value>) {
+    let Some(Value::Object(properties)) = schema.get_mut("properties") else { return };
+    let mut with_aliases = Vec::new();
+    for (name, subschema) in properties.iter_mut() {
+        let aliases = take_aliases(subschema);
+        if !aliases.is_empty() {
+            with_aliases.push((name.clone(), subschema.clone(), aliases));
+        }
+    }
+    for (_, subschema, aliases) in &with_aliases {
+        for alias in aliases {
+            properties.insert(alias.clone(), subschema.clone());
+        }
+    }
+    // a required property can be given via any of its names.
+    let Some(Value::Array(required)) = schema.get_mut("required") else { return };
+    let mut alternatives = Vec::new();
+    for (name, _, aliases) in with_aliases {
+        let Some(index) = required.iter().position(|required| required == &name) else { continue };
+        required.remove(index);
+        let names = std::iter::once(name).chain(aliases)
+            .map(|name| json!({ "required": [name] }))
+            .collect::<Vec<_>>();
+        alternatives.push(json!({ "anyOf": names }));
+    }
+    if alternatives.is_empty() { return }
+    if let Value::Array(all_of) = schema.entry("allOf").or_insert_with(|| Value::Array(Vec::new())) {
+        all_of.extend(alternatives);
+    }
+}
+fn resolve_variant_aliases(variants: &mut Vec<Value>) {
+    let mut added = Vec::new();
+    for variant in variants.iter_mut() {
+        for alias in take_aliases(variant) {
+            let mut copy = variant.clone();
+            let Some(schema) = copy.as_object_mut() else { continue };
+            if schema.contains_key("const") {
+                // unit variant
+                schema.insert("const".to_string(), Value::String(alias));
+            } else if let Some(Value::Object(properties)) = schema.get_mut("properties")
+                && properties.len() == 1
+                && let Some(name) = properties.keys().next().cloned()
+                && let Some(content) = properties.remove(&name) {
+                // externally tagged variant with content
+                properties.insert(alias.clone(), content);
+                schema.insert("required".to_string(), json!([alias]));
+            } else {
+                continue;
+            }
+            added.push(copy);
+        }
+    }
+    variants.extend(added);
+}
@@ -1,138 +1,300 @@
 use std::any::TypeId;
-use std::collections::{HashMap, HashSet};
-use std::path::{Path, PathBuf};
-use untyped::{TypeMap, Untyped};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use untyped::Untyped;
 use crate::config::Config;
 use crate::Component;
-use crate::notification::{Notification, NotificationReason};
-use crate::notification_provider::NotificationProvider;
-use crate::state::{AttributeValue, State};
+use crate::component::LifecycleFuture;
+use crate::notification::{HealthChange, Notification, NotificationReason};
+use crate::notification_provider::{DeliveryFuture, NotificationProvider, ReliableNotificationProvider};
+use crate::state::{AttributeValue, Health, State};
+use crate::history::{History, HistoryEntry, HistoryWrite};
+use crate::uptime::Uptime;
+use crate::incident::{Incident, IncidentNote, Acknowledgement, Incidents};
+use crate::maintenance::{self, MaintenanceMode, MaintenanceWindow};
+use crate::flapping::{self, FlapDetector};
+use crate::confirmation::{Confirmation, Confirmations};
+use crate::escalation::{self, Escalation};
+use crate::delivery::{Deliveries, Delivery};
+use crate::update::{AttributeUpdate, ElementUpdate};
+use crate::registry::{ComponentRegistry, Registration};
+use crate::reload::{ReloadReport, ReloadResult};
+use crate::dependency::{Dependencies, DependencyError, DependencyGraph, Node};
+use crate::bus::{Event, NotificationBus, Subscriber, SubscriberMetrics};
+use crate::snapshot::{PublishedStates, States};
+use super::storage;
+use super::components::{Components, Shared};
 
+#[expect(clippy::struct_excessive_bools, reason="these are independent flags, not a state machine.")]
 pub(super) struct Server {
     config_path: PathBuf,
+    /// All files (and directories) the loaded config was read from.
+    config_files: Vec<PathBuf>,
     loaded_config: Config,
-    components: TypeMap<ComponentInfo>,
-    states: HashMap<String, State>,
+    components: Components<ComponentInfo>,
+    states: States,
+    /// The latest snapshot of the states, which is read without locking the server.
+    published_states: PublishedStates,
+    unsaved_changes: bool,
+    history: History,
+    incidents: Incidents,
+    unsaved_incidents: bool,
+    deliveries: Deliveries,
+    unsaved_deliveries: bool,
+    /// The recent online/offline transitions of the elements.
+    flapping: FlapDetector,
+    /// The online status changes that still need to be confirmed.
+    confirmations: Confirmations,
+    /// The health of the elements before notifications about it were first suppressed by the
+    /// current maintenance window and the component that changed it last (by element).
+    suppressed_health: HashMap<String, (String, Health)>,
+    /// The open incidents whose escalation is currently held back by a maintenance window.
+    suppressed_escalations: HashSet<u64>,
+    /// How to send out the deliveries of each reliable provider (by instance).
+    delivery_handlers: HashMap<String, DeliveryHandler>,
+    delivery_inbox: DeliveryInbox,
+    removal_inbox: RemovalInbox,
+    /// The components compiled into the program, used to add components when they are no longer
+    /// ignored.
+    registry: ComponentRegistry,
+    /// The outcome of the last reload of the config.
+    last_reload: Option<ReloadReport>,
+    /// The dependencies declared by the components.
+    dependencies: Dependencies,
+    /// Why the instances that failed to initialize the last time they were added did so.
+    init_failures: HashMap<String, String>,
+    /// Whether the server runs (see [`Server::start_components`]), so that added components are
+    /// started right away.
+    running: bool,
+    /// Hands the notifications to the notification providers.
+    bus: NotificationBus,
+}
+/// Creates the future sending out a delivery.
+type DeliveryHandler = fn(&Server, &str, serde_json::Value) -> Result<DeliveryFuture, String>;
+/// Deliveries queued by providers that still need to be moved into the delivery queue.
+///
+/// This is kept outside the lock of the server, so that providers can queue deliveries while the
+/// server is locked (e.g. in [`NotificationProvider::notify`]).
+pub(super) type DeliveryInbox = std::sync::Arc<parking_lot::Mutex<Vec<QueuedDelivery>>>;
+/// Elements queued for removal by components (by the instance removing them) while the server is
+/// locked (see [`ComponentHandle::queue_element_removal`](crate::ComponentHandle::queue_element_removal)).
+pub(super) type RemovalInbox = Arc<parking_lot::Mutex<Vec<(String, String)>>>;
+pub(super) struct QueuedDelivery {
+    provider: String,
+    handler: DeliveryHandler,
+    payload: serde_json::Value,
+}
+impl QueuedDelivery {
+    pub(super) fn new<P: ReliableNotificationProvider>(instance: &str, payload: serde_json::Value) -> Self {
+        Self {
+            provider: instance.to_string(),
+            handler: deliver_with::<P>,
+            payload,
+        }
+    }
 }
 #[derive(Clone, Debug)]
 struct ComponentInfo {
-    reconfigure: unsafe fn(&mut Untyped, Option<toml::Value>),
+    reconfigure: unsafe fn(&mut Untyped, Option<&toml::Value>) -> Result<(), String>,
+    check: fn(Option<&toml::Value>) -> Result<(), String>,
     try_handle_request: unsafe fn(&Untyped, request: axum::extract::Request) -> Result<crate::component::RequestHandle, axum::extract::Request>,
-    required_by: HashSet<TypeId>,
+    start: unsafe fn(&mut Untyped) -> LifecycleFuture,
+    shutdown: unsafe fn(&mut Untyped) -> LifecycleFuture,
+    /// Whether the component was started (and not shut down since).
+    started: bool,
     type_id: TypeId,
     id: &'static str,
+    instance: String,
     notification_provider_info: Option<NotificationProviderInfo>,
 }
+impl ComponentInfo {
+    /// Whether the component stays loaded with the given config.
+    fn kept_by(&self, config: &Config) -> bool {
+        !config.is_ignored(&self.instance) && config.instances_of(self.id).contains(&self.instance)
+    }
+}
 #[derive(Clone, Debug)]
 struct NotificationProviderInfo {
-    notify: unsafe fn(&Untyped, Notification),
+    provider: unsafe fn(&Untyped) -> Arc<dyn ErasedProvider>,
+}
+impl NotificationProviderInfo {
+    fn of<P: NotificationProvider>() -> Self {
+        Self {
+            provider: erase_provider::<P>,
+        }
+    }
+}
+/// A loaded notification provider, which is called without the server being locked.
+pub(crate) trait ErasedProvider: Send + Sync {
+    /// See [`NotificationProvider::notify_batch`].
+    fn notify_batch(&self, notifications: Vec<Notification>);
+    /// See [`NotificationProvider::escalate`].
+    fn escalate(&self, notification: Notification, target: Option<&str>);
+    /// Hands the event published on the notification bus to the provider.
+    fn dispatch(&self, event: Event) {
+        match event {
+            Event::Notify(notifications) => self.notify_batch(notifications),
+            Event::Escalate(notification, target) => self.escalate(notification, target.as_deref()),
+        }
+    }
+}
+impl<P: NotificationProvider> ErasedProvider for RwLock<P> {
+    fn notify_batch(&self, notifications: Vec<Notification>) {
+        self.read().notify_batch(notifications);
+    }
+    fn escalate(&self, notification: Notification, target: Option<&str>) {
+        self.read().escalate(notification, target);
+    }
 }
 
-/// # SAFETY
-/// The type `P` must be the same as the [`Untyped`].
-unsafe fn reconfigure_component<P: Component + 'static>(this: &mut Untyped, value: Option<toml::Value>) {
+/// Deserializes the config of `C` (or the default if there is none).
+fn deserialize_config<C: Component>(value: Option<&toml::Value>) -> Result<C::Config, String> {
     use serde::Deserialize;
-    let config = match value {
-        None => Default::default(),
-        Some(serialized) => match P::Config::deserialize(serialized) {
-            Ok(v) => v,
-            Err(e) => {
-                error!("couldn't deserialize config of status provider \"{}\": {}", P::ID, e);
-                return;
-            }
-        },
-    };
-    // SAFETY: The correctness of the type is guaranteed by the caller.
-    let this = unsafe { this.read_mut::<P>() };
-    match this.reconfigure(config) {
-        Ok(()) => {},
-        Err(e) => {
-            error!("couldn't reconfigure status provider \"{}\": {}", P::ID, e);
-        }
+    match value {
+        None => Ok(Default::default()),
+        Some(serialized) => C::Config::deserialize(serialized.clone())
+            .map_err(|e| format!("invalid config: {e}")),
     }
 }
+fn check_component_config<C: Component>(value: Option<&toml::Value>) -> Result<(), String> {
+    deserialize_config::<C>(value).map(|_| ())
+}
+/// # SAFETY
+/// The [`Untyped`] MUST be a [`Shared<P>`].
+unsafe fn reconfigure_component<P: Component + 'static>(this: &mut Untyped, value: Option<&toml::Value>) -> Result<(), String> {
+    let config = deserialize_config::<P>(value)?;
+    // SAFETY: The correctness of the type is guaranteed by the caller.
+    let this = unsafe { this.read_mut::<Shared<P>>() };
+    this.write().reconfigure(config)
+        .map_err(|e| format!("couldn't reconfigure: {e}"))
+}
 /// # SAFETY
-/// The type `C` MUST be the same as the [`Untyped`]
+/// The [`Untyped`] MUST be a [`Shared<C>`].
 #[expect(clippy::result_large_err, reason="The error here isn't actually an error, but just the request if we fail to parse it.")]
 unsafe fn try_handle_request<C: Component>(this: &Untyped, request: axum::extract::Request) -> Result<crate::component::RequestHandle, axum::extract::Request> {
     // SAFETY: The correctness of the type is guaranteed by the caller.
     unsafe {
-        this.read::<C>().try_handle(request)
+        this.read::<Shared<C>>().read().try_handle(request)
     }
 }
 /// # SAFETY
-/// The type `P` MUST be the same as the [`Untyped`]
-unsafe fn notify_provider<P: NotificationProvider>(this: &Untyped, notification: Notification) {
+/// The [`Untyped`] MUST be a [`Shared<C>`].
+unsafe fn start_component<C: Component>(this: &mut Untyped) -> LifecycleFuture {
     // SAFETY: The correctness of the type is guaranteed by the caller.
     unsafe {
-        this.read::<P>().notify(notification);
+        this.read_mut::<Shared<C>>().write().start()
     }
 }
-fn read_config(path: impl AsRef<Path>) -> Config {
-    let path = path.as_ref();
-    let config_str = match std::fs::read_to_string(path) {
-        Ok(v) => {
-            trace!("read config file `{}`: {v:?}", path.to_string_lossy());
-            v
-        },
-        Err(e) => {
-            error!("couldn't read config file `{}`: {e}", path.to_string_lossy());
-            return Config::default();
-        }
-    };
-    match toml::from_str(&config_str) {
-        Ok(v) => {
-            debug!("read config: {v:?}");
-            v
-        },
-        Err(e) => {
-            error!("invalid config file: `{}`: {e}", path.to_string_lossy());
-            Config::default()
-        }
+/// # SAFETY
+/// The [`Untyped`] MUST be a [`Shared<C>`].
+unsafe fn shutdown_component<C: Component>(this: &mut Untyped) -> LifecycleFuture {
+    // SAFETY: The correctness of the type is guaranteed by the caller.
+    unsafe {
+        this.read_mut::<Shared<C>>().write().shutdown()
+    }
+}
+/// # SAFETY
+/// The [`Untyped`] MUST be a [`Shared<P>`].
+unsafe fn erase_provider<P: NotificationProvider>(this: &Untyped) -> Arc<dyn ErasedProvider> {
+    // SAFETY: The correctness of the type is guaranteed by the caller.
+    unsafe {
+        this.read::<Shared<P>>().clone()
     }
 }
+/// Hands the delivery to the given instance of the provider `P`.
+fn deliver_with<P: ReliableNotificationProvider>(server: &Server, instance: &str, payload: serde_json::Value) -> Result<DeliveryFuture, String> {
+    let provider = server.components.get::<P>(instance)
+        .ok_or_else(|| format!("provider {instance} isn't loaded"))?;
+    let delivery = serde_json::from_value(payload)
+        .map_err(|e| format!("invalid delivery: {e}"))?;
+    Ok(provider.read().deliver(delivery))
+}
+/// Whether the attribute `key` is the attribute `id` (or one of its subattributes if not `exact`).
+fn matches_attribute(key: &str, id: &str, exact: bool) -> bool {
+    if exact {
+        return key == id;
+    }
+    key.starts_with(id) && key.get(id.len()..)
+        .is_none_or(|v| v.is_empty() || v.starts_with('.'))
+}
 // component management
 impl Server {
-    pub(crate) fn add_notification_provider_dependency<P: NotificationProvider>(&mut self, dependant: TypeId) {
-        if self.loaded_config.ignored.components.contains(P::ID) {
-            error!("dependency {} cannot be satisfied as it is set to be ignored.", P::ID);
-            return;
+    /// Declares that the dependant requires all configured (and not ignored) instances of `C`,
+    /// before they are initialized.
+    pub(crate) fn declare_dependency<C: Component>(&mut self, dependant: &Node) -> Result<(), DependencyError> {
+        let instances = self.instances::<C>().into_iter()
+            .filter(|instance| !self.loaded_config.is_ignored(instance))
+            .collect::<Vec<_>>();
+        if instances.is_empty() {
+            return Err(DependencyError::Ignored(C::ID.to_string()));
         }
-        if !self.components.contains_key::<P>() {
-            error!("component {} not yet loaded!", P::ID);
+        let mut declared = Vec::new();
+        for instance in instances {
+            let dependency = Node::new(&instance, TypeId::of::<C>());
+            if let Err(e) = self.dependencies.add(dependant, dependency.clone()) {
+                for dependency in &declared {
+                    self.dependencies.remove(dependant, dependency);
+                }
+                return Err(e);
+            }
+            declared.push(dependency);
         }
-        self.add_component_dependency::<P>(dependant);
-        let info = NotificationProviderInfo {
-            notify: notify_provider::<P>
-        };
-        self.components.additional_data_mut::<P>()
-            .expect("just inserted it").notification_provider_info = Some(info);
+        Ok(())
     }
-    pub(crate) fn add_component_dependency<C: Component>(&mut self, dependant: TypeId) {
-        if self.loaded_config.ignored.components.contains(C::ID) {
-            error!("dependency {} cannot be satisfied as it is set to be ignored.", C::ID);
-            return;
+    /// Drops the declared dependencies on instances of `C` that aren't loaded (as they couldn't be
+    /// initialized), failing if there are none left.
+    pub(crate) fn settle_dependency<C: Component>(&mut self, dependant: &Node) -> Result<(), DependencyError> {
+        let loaded = self.components.instances::<C>();
+        let mut reason = None;
+        for instance in self.instances::<C>().into_iter().filter(|instance| !loaded.contains(instance)) {
+            self.dependencies.remove(dependant, &Node::new(&instance, TypeId::of::<C>()));
+            reason = reason.or_else(|| self.init_failure(&instance));
         }
-        if !self.components.contains_key::<C>() {
-            error!("component {} not yet loaded!", C::ID);
-            return;
+        if loaded.is_empty() {
+            return Err(DependencyError::Unavailable { dependency: C::ID.to_string(), reason });
         }
-        self.components.additional_data_mut::<C>().expect("just checked its existance")
-            .required_by.insert(dependant);
+        Ok(())
     }
-    pub(crate) fn add_notification_provider<P: NotificationProvider>(&mut self, provider: P) {
-        if self.loaded_config.ignored.components.contains(P::ID) { return; }
-        self.add_component::<P>(provider);
-        let info = NotificationProviderInfo {
-            notify: notify_provider::<P>
-        };
-        self.components.additional_data_mut::<P>()
+    /// Makes the loaded instances of `P` receive notifications, as they were only added as a
+    /// dependency.
+    pub(crate) fn enable_notification_provider<P: NotificationProvider>(&mut self) {
+        for instance in self.components.instances::<P>() {
+            self.components.additional_data_mut::<P>(&instance)
+                .expect("instance is loaded").notification_provider_info = Some(NotificationProviderInfo::of::<P>());
+            self.bus.subscribe(Node::new(&instance, TypeId::of::<P>()), P::RECEIVES_SUPPRESSED);
+        }
+    }
+    /// Forgets the dependencies the instance declared (e.g. as it couldn't be initialized).
+    pub(crate) fn forget_dependencies(&mut self, dependant: &Node) {
+        self.dependencies.remove_dependant(dependant);
+    }
+    /// The dependencies between the loaded components.
+    pub(crate) fn dependency_graph(&self) -> DependencyGraph {
+        self.dependencies.graph(&self.loaded_nodes())
+    }
+    /// The loaded components in the order they are initialized in (see [`Self::dependency_graph`]).
+    fn init_order(&self) -> Vec<Node> {
+        self.dependencies.init_order(&self.loaded_nodes())
+    }
+    fn loaded_nodes(&self) -> BTreeSet<Node> {
+        self.components.entries()
+            .map(|(data, _)| Node::new(&data.instance, data.type_id))
+            .collect()
+    }
+    pub(crate) fn add_notification_provider<P: NotificationProvider>(&mut self, instance: &str, provider: P) {
+        if self.loaded_config.is_ignored(instance) { return; }
+        self.add_component::<P>(instance, provider);
+        let info = NotificationProviderInfo::of::<P>();
+        self.components.additional_data_mut::<P>(instance)
             .expect("just inserted it").notification_provider_info = Some(info);
+        self.bus.subscribe(Node::new(instance, TypeId::of::<P>()), P::RECEIVES_SUPPRESSED);
     }
-    pub(crate) fn add_component<C: Component>(&mut self, component: C) {
-        if self.loaded_config.ignored.components.contains(C::ID) { return; }
+    pub(crate) fn add_component<C: Component>(&mut self, instance: &str, component: C) {
+        if self.loaded_config.is_ignored(instance) { return; }
         
-        if self.components.contains_key::<C>() {
+        if self.components.contains::<C>(instance) {
             debug!("called `add_component` with already registered component! Ignoring.");
             return;
         }
@@ -140,100 +302,346 @@ impl Server {
         // management info
         let data = ComponentInfo {
             reconfigure: reconfigure_component::<C>,
+            check: check_component_config::<C>,
             try_handle_request: try_handle_request::<C>,
-            required_by: HashSet::new(),
+            start: start_component::<C>,
+            shutdown: shutdown_component::<C>,
+            started: false,
             type_id: TypeId::of::<C>(),
             id: C::ID,
+            instance: instance.to_string(),
             notification_provider_info: None,
         };
         
-        assert!(self.components.insert(component, data).is_none(), "checked that the component was not present already, but now it somehow is?");
+        assert!(self.components.insert(instance, component, data).is_none(), "checked that the component was not present already, but now it somehow is?");
+        if self.running {
+            let node = Node::new(instance, TypeId::of::<C>());
+            if let Some(future) = self.start_component(&node) {
+                tokio::spawn(future);
+            }
+        }
+    }
+    /// Calls [`Component::start`] of the loaded instance if it wasn't started yet.
+    fn start_component(&mut self, node: &Node) -> Option<LifecycleFuture> {
+        let (data, component) = self.components.entries_mut()
+            .find(|(data, _)| data.instance == node.instance && data.type_id == node.type_id)?;
+        if data.started { return None; }
+        data.started = true;
+        debug!("starting component {}", data.instance);
+        // SAFETY: That the type is the same is guaranteed by the creation of `data.start` and
+        //         `Components::entries_mut`.
+        Some(unsafe { (data.start)(component) })
+    }
+    /// Calls [`Component::shutdown`] of the loaded instance if it was started.
+    fn shutdown_component(&mut self, node: &Node) -> Option<LifecycleFuture> {
+        let (data, component) = self.components.entries_mut()
+            .find(|(data, _)| data.instance == node.instance && data.type_id == node.type_id)?;
+        if !data.started { return None; }
+        data.started = false;
+        debug!("shutting down component {}", data.instance);
+        // SAFETY: That the type is the same is guaranteed by the creation of `data.shutdown` and
+        //         `Components::entries_mut`.
+        Some(unsafe { (data.shutdown)(component) })
+    }
+    /// Starts all loaded components (dependencies first) and every component added from now on,
+    /// returning the futures of their start hooks.
+    pub(crate) fn start_components(&mut self) -> Vec<LifecycleFuture> {
+        self.running = true;
+        self.init_order().iter()
+            .filter_map(|node| self.start_component(node))
+            .collect()
     }
-    pub(crate) fn remove_component(&mut self, type_id: TypeId) {
-        let Some((info, _)) = self.components.remove_by_type_id(&type_id) else {
+    /// Shuts all started components down (dependants first), returning the futures of their
+    /// shutdown hooks (by instance). Components added from now on aren't started anymore.
+    pub(crate) fn shutdown_components(&mut self) -> Vec<(String, LifecycleFuture)> {
+        self.running = false;
+        self.init_order().iter().rev()
+            .filter_map(|node| Some((node.instance.clone(), self.shutdown_component(node)?)))
+            .collect()
+    }
+    /// Removes the component and everything (transitively) depending on it (dependants first).
+    pub(crate) fn remove_component(&mut self, instance: &str, type_id: TypeId) {
+        if !self.components.contains_type_id(instance, type_id) {
             error!("tried to remove component that wasn't even present");
             return;
-        };
-        for dependant in info.required_by {
-            self.remove_component(dependant);
         }
+        let node = Node::new(instance, type_id);
+        for dependant in self.dependencies.dependants(&node).into_iter().chain([node]) {
+            if let Some(future) = self.shutdown_component(&dependant) {
+                tokio::spawn(future);
+            }
+            self.bus.unsubscribe(&dependant);
+            if self.components.remove(&dependant.instance, dependant.type_id).is_some() {
+                debug!("removed component {}", dependant.instance);
+            }
+            self.dependencies.remove_dependant(&dependant);
+        }
+    }
+    /// The instance named after the id of `C` or (if there is none) the first loaded instance.
+    fn default_instance<C: Component>(&self) -> Option<String> {
+        if self.components.contains::<C>(C::ID) {
+            return Some(C::ID.to_string());
+        }
+        self.components.instances::<C>().into_iter().next()
+    }
+    pub(crate) fn get_component<C: Component>(&self) -> Option<RwLockReadGuard<'_, C>> {
+        Some(self.components.get::<C>(&self.default_instance::<C>()?)?.read())
+    }
+    pub(crate) fn get_component_mut<C: Component>(&mut self) -> Option<RwLockWriteGuard<'_, C>> {
+        Some(self.components.get::<C>(&self.default_instance::<C>()?)?.write())
     }
-    pub(crate) fn get_component<C: Component>(&self) -> Option<&C> {
-        self.components.get::<C>()
+    pub(crate) fn has_instance<C: Component>(&self, instance: &str) -> bool {
+        self.components.contains::<C>(instance)
     }
-    pub(crate) fn get_component_mut<C: Component>(&mut self) -> Option<&mut C> {
-        self.components.get_mut::<C>()
+    pub(crate) fn loaded_instances<C: Component>(&self) -> Vec<String> {
+        self.components.instances::<C>()
     }
-    pub(crate) fn has_component<C: Component>(&self) -> bool {
-        self.components.contains_key::<C>()
+    /// The names of the configured instances of `C`.
+    pub(crate) fn instances<C: Component>(&self) -> Vec<String> {
+        self.loaded_config.instances_of(C::ID)
     }
-    pub(crate) fn get_config<C: Component>(&self) -> C::Config {
+    pub(crate) fn get_config<C: Component>(&self, instance: &str) -> C::Config {
         use serde::Deserialize;
-        match self.loaded_config.configs.get(C::ID) {
+        match self.loaded_config.config_of(instance) {
             None => C::Config::default(),
             Some(serialized) => C::Config::deserialize(serialized.clone())
                 .unwrap_or_else(|e| {
-                    error!("couldn't deserialize config for `{}` due to: {e}", C::ID);
+                    error!("couldn't deserialize config for `{instance}` due to: {e}");
                     C::Config::default()
                 })
         }
     }
-    pub(crate) fn check_config<C: Component>(&self) -> Option<Result<C::Config, toml::de::Error>> {
+    pub(crate) fn check_config<C: Component>(&self, instance: &str) -> Option<Result<C::Config, toml::de::Error>> {
         use serde::Deserialize;
-        let conf = self.loaded_config.configs.get(C::ID)?;
+        let conf = self.loaded_config.config_of(instance)?;
         Some(C::Config::deserialize(conf.clone()))
     }
-    pub(crate) fn ignored<C: Component>(&self) -> bool {
-        self.loaded_config.ignored.components.contains(C::ID)
+    pub(crate) fn ignored(&self, instance: &str) -> bool {
+        self.loaded_config.is_ignored(instance)
+    }
+    pub(crate) fn set_registry(&mut self, registry: ComponentRegistry) {
+        self.registry = registry;
+    }
+    /// The configured instances of registered components that are neither loaded nor ignored.
+    fn missing_instances<'a>(&'a self, config: &'a Config) -> impl Iterator<Item=(&'a Registration, String)> {
+        self.registry.registrations()
+            .flat_map(|registration| config.instances_of(registration.id).into_iter()
+                .map(move |instance| (registration, instance)))
+            .filter(|(_, instance)| !config.is_ignored(instance))
+            .filter(|(registration, instance)| !self.components.entries()
+                .any(|(data, _)| data.id == registration.id && data.instance == *instance))
+    }
+    /// The registered components with configured instances that are neither loaded nor ignored (in
+    /// the order they were registered).
+    pub(crate) fn missing_components(&self) -> Vec<Registration> {
+        let mut missing = Vec::<Registration>::new();
+        for (registration, _) in self.missing_instances(&self.loaded_config) {
+            if !missing.iter().any(|known| known.id == registration.id) {
+                missing.push(*registration);
+            }
+        }
+        missing
+    }
+    /// The names of the configured instances of registered components that are neither loaded nor
+    /// ignored.
+    pub(crate) fn missing_instance_names(&self) -> Vec<String> {
+        self.missing_instances(&self.loaded_config)
+            .map(|(_, instance)| instance)
+            .collect()
     }
 }
 impl Server {
     pub(crate) fn new(config_path: PathBuf) -> Self {
+        let (loaded_config, config_files) = Config::load(&config_path)
+            .unwrap_or_else(|e| {
+                error!("{e}");
+                (Config::default(), vec![config_path.clone()])
+            });
+        Self::with_config(config_path, loaded_config, config_files)
+    }
+    /// Creates a server with an already loaded config (read from the given files).
+    pub(crate) fn with_config(config_path: PathBuf, loaded_config: Config, config_files: Vec<PathBuf>) -> Self {
+        let (states, incidents, deliveries) = if loaded_config.storage.enabled {
+            (
+                storage::load_json(&loaded_config.storage.state_file, "element states"),
+                storage::load_json(&loaded_config.storage.incidents_file, "incidents"),
+                storage::load_json(&loaded_config.storage.deliveries_file, "deliveries"),
+            )
+        } else {
+            (States::new(), Incidents::default(), Deliveries::default())
+        };
+        let history = if loaded_config.storage.history.enabled {
+            storage::load_history(&loaded_config.storage.history.file)
+        } else {
+            History::new()
+        };
+        let mut flapping = FlapDetector::default();
+        let window_start = chrono::Utc::now() - loaded_config.flapping.window;
+        for entry in history.query(None, Some(window_start), None) {
+            if let NotificationReason::HealthChanged(change) = &entry.notification.reason && change.online_changed() {
+                flapping.record(&entry.notification.element_id, entry.timestamp, &loaded_config.flapping);
+            }
+        }
         Self {
-            components: TypeMap::new(),
-            states: HashMap::new(),
-            loaded_config: read_config(&config_path),
+            components: Components::new(),
+            published_states: PublishedStates::new(&states),
+            states,
+            unsaved_changes: false,
+            history,
+            incidents,
+            unsaved_incidents: false,
+            deliveries,
+            unsaved_deliveries: false,
+            flapping,
+            suppressed_health: HashMap::new(),
+            suppressed_escalations: HashSet::new(),
+            confirmations: Confirmations::default(),
+            delivery_handlers: HashMap::new(),
+            delivery_inbox: DeliveryInbox::default(),
+            removal_inbox: RemovalInbox::default(),
+            registry: ComponentRegistry::new(),
+            last_reload: None,
+            init_failures: HashMap::new(),
+            dependencies: Dependencies::default(),
+            running: false,
+            bus: NotificationBus::default(),
+            loaded_config,
             config_path,
+            config_files,
         }
     }
-    pub(crate) fn reload_config(&mut self) {
+    /// Reloads the config from the config file, keeping the previous one if the new one (or the
+    /// config of any component that would be loaded with it) is invalid or any component can't be
+    /// reconfigured with it. Components that were already reconfigured are rolled back then.
+    ///
+    /// Components that need to be added are only reported as failed if their config is invalid.
+    pub(crate) fn reload_config(&mut self) -> ReloadReport {
         info!("reloading config");
-        self.loaded_config = read_config(&self.config_path);
-        for to_remove in self.components.entries_mut()
-            .filter_map(|(data, component)| {
-                if self.loaded_config.ignored.components.contains(data.id) {
-                    return Some(data.type_id);
-                }
-                let config = self.loaded_config.configs.get(data.id).cloned();
-                // SAFETY: That the type is the same is guaranteed by the creation of 
-                //         `data.configure` and `TypeMap::entries_mut`.
-                unsafe {
-                    (data.reconfigure)(component, config);
-                }
-                None
-            }).collect::<Vec<_>>() {
-            self.remove_component(to_remove);
+        let (config, files) = match Config::load(&self.config_path) {
+            Ok(v) => v,
+            Err(e) => return ReloadReport::rejected(e, BTreeMap::new()),
+        };
+        // validate everything first, so that an invalid config doesn't change anything.
+        let mut invalid = self.components.entries()
+            .filter(|(data, _)| data.kept_by(&config))
+            .filter_map(|(data, _)| Some((data.instance.clone(), (data.check)(config.config_of(&data.instance)).err()?)))
+            .collect::<BTreeMap<_, _>>();
+        for (registration, instance) in self.missing_instances(&config) {
+            let Some(value) = config.config_of(&instance) else { continue; };
+            if let Err(e) = registration.check(value.clone()) {
+                invalid.insert(instance, format!("invalid config: {e}"));
+            }
+        }
+        if !invalid.is_empty() {
+            let components = invalid.into_iter()
+                .map(|(instance, e)| (instance, ReloadResult::Failed(e)))
+                .collect();
+            return ReloadReport::rejected("invalid component config".to_string(), components);
+        }
+
+        // removals queued outside of `reconfigure` (see `ComponentHandle::queue_element_removal`).
+        self.drain_removal_inbox();
+        let mut report = ReloadReport::new();
+        let loaded = self.components.loaded_instances();
+        let mut to_remove = Vec::new();
+        let mut reconfigured = Vec::new();
+        let mut failed = None;
+        for (data, component) in self.components.entries_mut() {
+            if !data.kept_by(&config) {
+                to_remove.push((data.instance.clone(), data.type_id));
+                continue;
+            }
+            let new = config.config_of(&data.instance);
+            if new == self.loaded_config.config_of(&data.instance) {
+                report.components.insert(data.instance.clone(), ReloadResult::Unchanged);
+                continue;
+            }
+            // SAFETY: That the type is the same is guaranteed by the creation of 
+            //         `data.configure` and `Components::entries_mut`.
+            match unsafe { (data.reconfigure)(component, new) } {
+                Ok(()) => {
+                    report.components.insert(data.instance.clone(), ReloadResult::Applied);
+                    reconfigured.push(data.instance.clone());
+                },
+                Err(e) => {
+                    failed = Some((data.instance.clone(), e));
+                    break;
+                },
+            }
+        }
+        if let Some((instance, e)) = failed {
+            // the elements the components wanted to remove are kept, as they are rolled back.
+            self.removal_inbox.lock().clear();
+            return ReloadReport::rejected(format!("couldn't reconfigure {instance}"), self.roll_back(&reconfigured, instance, e));
+        }
+        self.drain_removal_inbox();
+        self.config_files = files;
+        self.loaded_config = config;
+        for (instance, type_id) in to_remove {
+            // might have been removed already as a dependant.
+            if self.components.contains_type_id(&instance, type_id) {
+                self.remove_component(&instance, type_id);
+            }
+        }
+        let remaining = self.components.loaded_instances();
+        for instance in loaded.difference(&remaining) {
+            report.components.insert(instance.clone(), ReloadResult::Removed);
+        }
+        report
+    }
+    /// Reconfigures the instances with the config that is still loaded after `failed` couldn't be
+    /// reconfigured (with `error`), returning what happened to them.
+    fn roll_back(&mut self, reconfigured: &[String], failed: String, error: String) -> BTreeMap<String, ReloadResult> {
+        let mut components = BTreeMap::from([(failed, ReloadResult::Failed(error))]);
+        for (data, component) in self.components.entries_mut() {
+            if !reconfigured.contains(&data.instance) { continue; }
+            // SAFETY: That the type is the same is guaranteed by the creation of
+            //         `data.configure` and `Components::entries_mut`.
+            if let Err(e) = unsafe { (data.reconfigure)(component, self.loaded_config.config_of(&data.instance)) } {
+                components.insert(data.instance.clone(), ReloadResult::Failed(format!("couldn't be rolled back: {e}")));
+            }
         }
+        components
+    }
+    pub(crate) fn loaded_instance_names(&self) -> HashSet<String> {
+        self.components.loaded_instances()
+    }
+    pub(crate) fn set_last_reload(&mut self, report: ReloadReport) {
+        self.last_reload = Some(report);
+    }
+    pub(crate) fn get_last_reload(&self) -> Option<ReloadReport> {
+        self.last_reload.clone()
+    }
+    /// Records why the instance couldn't be initialized (or clears it if it could).
+    pub(crate) fn set_init_failure(&mut self, instance: &str, failure: Option<String>) {
+        match failure {
+            Some(e) => self.init_failures.insert(instance.to_string(), e),
+            None => self.init_failures.remove(instance),
+        };
+    }
+    /// Why the instance couldn't be initialized the last time it was added (if it couldn't).
+    pub(crate) fn init_failure(&self, instance: &str) -> Option<String> {
+        self.init_failures.get(instance).cloned()
     }
 }
 // State changes
 impl Server {
-    pub(crate) fn attribute_change(&mut self, component_id: &'static str, element_id: &str, attribute_id: &str, value: AttributeValue) {
-        #[expect(clippy::single_match_else, reason="using a match here actually makes it more readable.")]
-        let state = match self.states.get_mut(element_id) {
-            Some(state) => state,
-            None => {
-                self.states.insert(element_id.to_string(), State::new());
-                self.notify(Notification::new(
-                    component_id.to_string(),
-                    element_id.to_string(),
-                    NotificationReason::NewElement(false)
-                ));
-                self.states.get_mut(element_id)
-                    .expect("just inserted, but not present?")
-            }
-        };
+    pub(crate) fn attribute_change(&mut self, component_id: &str, element_id: &str, attribute_id: &str, value: AttributeValue) {
+        let created = !self.states.contains_key(element_id);
+        if created {
+            self.states.insert(element_id.to_string(), State::new());
+        }
+        let state = self.states.get_mut(element_id).expect("just made sure it exists");
         let old_val = state.attributes.insert(attribute_id.to_string(), value.clone());
+        // publishing first, so that anyone notified sees the change in the states.
+        self.states_changed();
+        if created {
+            self.notify(Notification::new(
+                component_id.to_string(),
+                element_id.to_string(),
+                NotificationReason::NewElement(Health::default())
+            ));
+        }
         let notification = Notification::new(
             component_id.to_string(),
             element_id.to_string(),
@@ -244,14 +652,11 @@ impl Server {
         );
         self.notify(notification);
     }
-    pub(crate) fn get_attribute(&self, element_id: &str, attribute_id: &str) -> Option<AttributeValue> {
-        self.states.get(element_id)?
-            .attributes.get(attribute_id).cloned()
-    }
     pub(crate) fn delete_attribute(&mut self, element_id: &str, attribute_id: &str, component_id: &str, exact: bool) {
         let Some(element) = self.states.get_mut(element_id) else { return; };
         if exact {
             if let Some(old) = element.attributes.remove(attribute_id) {
+                self.states_changed();
                 self.notify(Notification::new(
                     component_id.to_string(),
                     element_id.to_string(),
@@ -260,9 +665,8 @@ impl Server {
             }
             return;
         }
-        element.attributes.keys()
-            .filter(|key| key.starts_with(attribute_id) && key.get(attribute_id.len()..)
-                .is_none_or(|v| v.starts_with('.')))
+        let deleted = element.attributes.keys()
+            .filter(|key| matches_attribute(key, attribute_id, false))
             .cloned()
             .collect::<Vec<_>>()
             .into_iter()
@@ -270,55 +674,579 @@ impl Server {
                 let old = element.attributes.remove(&id).unwrap();
                 (id, old)
             })
-            .collect::<Vec<_>>().into_iter()
-            .for_each(|(id, old)| self.notify(Notification::new(
+            .collect::<Vec<_>>();
+        if deleted.is_empty() { return; }
+        self.states_changed();
+        for (id, old) in deleted {
+            self.notify(Notification::new(
                 component_id.to_string(),
                 element_id.to_string(),
                 NotificationReason::DeleteAttribute(id, old))
-            ));
+            );
+        }
     }
-    pub(crate) fn online_status_changed(&mut self, component_id: &'static str, element_id: &str, new_status: bool) {
+    /// Sets (or removes) an attribute computed from other data without notifying anyone (see
+    /// [`ComponentHandle::set_computed_attribute`](crate::ComponentHandle::set_computed_attribute)).
+    pub(crate) fn computed_attribute_change(&mut self, element_id: &str, attribute_id: &str, value: Option<AttributeValue>) {
+        let Some(element) = self.states.get_mut(element_id) else { return; };
+        let changed = match value {
+            Some(value) => element.attributes.insert(attribute_id.to_string(), value.clone()).is_none_or(|old| old != value),
+            None => element.attributes.remove(attribute_id).is_some(),
+        };
+        if changed {
+            self.states_changed();
+        }
+    }
+    /// Sets the health from the online status, keeping the current health if the online status
+    /// doesn't change (e.g. when the element is degraded and `new_status` is `true`).
+    pub(crate) fn online_status_changed(&mut self, component_id: &str, element_id: &str, new_status: bool) {
+        if self.states.get(element_id).and_then(|state| state.health.online()) == Some(new_status) {
+            warn!("called online_status_changed without changing the online status; ignoring");
+            return;
+        }
+        self.health_changed(component_id, element_id, new_status.into());
+    }
+    pub(crate) fn health_changed(&mut self, component_id: &str, element_id: &str, new_health: Health) {
         if let Some(state) = self.states.get_mut(element_id) {
-            if state.online == new_status {
-                warn!("called online_status_changed without changing the online status; ignoring");
+            if state.health == new_health {
+                trace!("health of {element_id} didn't change; ignoring");
                 return;
             }
-            state.online = new_status;
-            self.notify(Notification::new(
+            let old = std::mem::replace(&mut state.health, new_health.clone());
+            self.states_changed();
+            self.track_incident(component_id, element_id, &new_health);
+            let change = HealthChange { old, new: new_health };
+            let flapping = self.track_flapping(element_id, &change);
+            self.notify_batch(std::iter::once(Notification::new(
                 component_id.to_string(),
                 element_id.to_string(),
-                NotificationReason::OnlineStatusChanged(new_status)
-            ));
+                NotificationReason::HealthChanged(change)
+            )).chain(flapping).collect());
         } else {
-            self.states.insert(element_id.to_string(), State::with_online(new_status));
+            self.states.insert(element_id.to_string(), State::with_health(new_health.clone()));
+            self.states_changed();
+            self.track_new_element_incident(component_id, element_id, &new_health);
             self.notify(Notification::new(
                 component_id.to_string(),
                 element_id.to_string(),
-                NotificationReason::NewElement(new_status),
+                NotificationReason::NewElement(new_health),
             ));
         }
     }
-    pub(crate) fn get_status(&self, element_id: &str) -> Option<bool> {
-        self.states.get(element_id)
-            .map(|state| state.online)
+    /// Opens or closes the incident of the element after its health changed.
+    ///
+    /// Elements with unknown health keep their incident (if any).
+    fn track_incident(&mut self, component_id: &str, element_id: &str, health: &Health) {
+        match health.online() {
+            Some(true) => self.incidents.close(element_id),
+            Some(false) => self.incidents.open(element_id, component_id),
+            None => return,
+        }
+        self.unsaved_incidents = true;
     }
-    #[expect(clippy::needless_pass_by_value, reason="_semantically_ we are passing ownership to the function.")]
-    pub(crate) fn notify(&self, notification: Notification) {
-        trace!("sending out notification: {notification:?}");
-        self.components.entries()
-            .filter_map(|(data, component)| {
-                data.notification_provider_info.as_ref().zip(Some(component))
-            })
-            .for_each(|(data, component)| {
-                // SAFETY: The correctness of the type is guaranteed by the creation of
-                //         `NotificationProviderInfo::notify` and `TypeMap::entries`.
-                unsafe {
-                    (data.notify)(component, notification.clone());
+    /// Opens an incident for an element that is offline as soon as it is first seen (e.g. on the
+    /// first start while it is already down), as it never goes offline.
+    fn track_new_element_incident(&mut self, component_id: &str, element_id: &str, health: &Health) {
+        if health.online() == Some(false) {
+            self.track_incident(component_id, element_id, health);
+        }
+    }
+    /// Returns the health if it doesn't change the online status of the element or the change is
+    /// confirmed.
+    fn confirm_health(&mut self, element_id: &str, health: Health, confirmation: &Confirmation) -> Option<Health> {
+        let current = self.states.get(element_id).and_then(|state| state.health.online());
+        match (current, health.online()) {
+            (Some(current), Some(online)) if current != online => {
+                if self.confirmations.observe(element_id, online, confirmation, chrono::Utc::now()) {
+                    return Some(health);
                 }
+                debug!("change of {element_id} to {health} isn't confirmed yet");
+                None
+            },
+            _ => {
+                self.confirmations.reset(element_id);
+                Some(health)
+            },
+        }
+    }
+    /// Records the health change of the element, marking it as flapping if it changed too often.
+    ///
+    /// Returns the notification about the element starting to flap (if it did).
+    fn track_flapping(&mut self, element_id: &str, change: &HealthChange) -> Option<Notification> {
+        if !change.online_changed() { return None; }
+        let config = &self.loaded_config.flapping;
+        let transitions = self.flapping.record(element_id, chrono::Utc::now(), config);
+        let state = self.states.get_mut(element_id)?;
+        if state.flapping || !config.starts_flapping(transitions) { return None; }
+        warn!("element {element_id} started flapping ({transitions} transitions within {})", config.window);
+        state.flapping = true;
+        let health = state.health.clone();
+        self.states_changed();
+        Some(Notification::new(
+            flapping::COMPONENT_ID.to_string(),
+            element_id.to_string(),
+            NotificationReason::FlappingStarted(health),
+        ))
+    }
+    /// Applies all changes of the [`ElementUpdate`] built by `build` at once, sending out the
+    /// resulting notifications as a single batch.
+    pub(crate) fn update_element<R>(&mut self, component_id: &str, element_id: &str, build: impl FnOnce(&mut ElementUpdate) -> R) -> R {
+        let mut update = ElementUpdate::new(self.states.get(element_id));
+        let result = build(&mut update);
+        let ElementUpdate { health, confirmation, attributes, .. } = update;
+        let health = match (health, confirmation) {
+            (Some(health), Some(confirmation)) => self.confirm_health(element_id, health, &confirmation),
+            // the element was observed in its current online status again (see `ElementUpdate::set_online`).
+            (None, Some(_)) => {
+                self.confirmations.reset(element_id);
+                None
+            },
+            (health, None) => health,
+        };
+        let notification = |reason| Notification::new(component_id.to_string(), element_id.to_string(), reason);
+        let mut notifications = Vec::new();
+        let creates_element = health.is_some() || attributes.iter().any(|change| matches!(change, AttributeUpdate::Set(..)));
+        if !self.states.contains_key(element_id) {
+            if !creates_element { return result; }
+            let health = health.clone().unwrap_or_default();
+            self.states.insert(element_id.to_string(), State::with_health(health.clone()));
+            self.track_new_element_incident(component_id, element_id, &health);
+            notifications.push(notification(NotificationReason::NewElement(health)));
+        }
+        let state = self.states.get_mut(element_id).expect("just made sure it exists");
+        let health = health.filter(|health| state.health != *health);
+        let mut health_change = None;
+        if let Some(health) = &health {
+            let old = std::mem::replace(&mut state.health, health.clone());
+            let change = HealthChange { old, new: health.clone() };
+            notifications.push(notification(NotificationReason::HealthChanged(change.clone())));
+            health_change = Some(change);
+        }
+        for change in attributes {
+            match change {
+                AttributeUpdate::Set(id, value) => match state.attributes.insert(id.clone(), value.clone()) {
+                    Some(old) if old == value => {},
+                    Some(old) => notifications.push(notification(NotificationReason::AttributeChanged(id, old, value))),
+                    None => notifications.push(notification(NotificationReason::AttributeCreated(id, value))),
+                },
+                AttributeUpdate::Delete { id, exact } => {
+                    let deleted = state.attributes.keys()
+                        .filter(|key| matches_attribute(key, &id, exact))
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    for deleted in deleted {
+                        let old = state.attributes.remove(&deleted).expect("key was just taken from the map");
+                        notifications.push(notification(NotificationReason::DeleteAttribute(deleted, old)));
+                    }
+                }
+            }
+        }
+        if let Some(health) = &health {
+            self.track_incident(component_id, element_id, health);
+        }
+        if let Some(flapping) = health_change.and_then(|change| self.track_flapping(element_id, &change)) {
+            notifications.push(flapping);
+        }
+        if !notifications.is_empty() {
+            self.states_changed();
+            self.notify_batch(notifications);
+        }
+        result
+    }
+    pub(crate) fn remove_element(&mut self, component_id: &str, element_id: &str) {
+        let Some(state) = self.states.remove(element_id) else { return; };
+        self.states_changed();
+        self.incidents.close(element_id);
+        self.unsaved_incidents = true;
+        self.flapping.remove(element_id);
+        self.confirmations.reset(element_id);
+        self.suppressed_health.remove(element_id);
+        self.notify(Notification::new(
+            component_id.to_string(),
+            element_id.to_string(),
+            NotificationReason::DeleteElement(state),
+        ));
+    }
+    pub(crate) fn notify(&mut self, notification: Notification) {
+        self.notify_batch(vec![notification]);
+    }
+    /// Publishes notifications that belong together (e.g. changes of a single
+    /// [`ElementUpdate`](crate::ElementUpdate)) as a single batch on the notification bus.
+    pub(crate) fn notify_batch(&mut self, notifications: Vec<Notification>) {
+        let notifications = notifications.into_iter()
+            .map(|mut notification| {
+                let maintenance = self.active_maintenance(&notification.element_id).map(|window| window.mode);
+                notification.maintenance = maintenance.is_some();
+                // individual health changes of flapping elements are replaced by a single notification.
+                let flapping = matches!(notification.reason, NotificationReason::HealthChanged(_))
+                    && self.states.get(&notification.element_id).is_some_and(|state| state.flapping);
+                // notifications about the maintenance itself are only relevant for keeping clients in sync.
+                let suppressed = notification.component_id == maintenance::COMPONENT_ID
+                    || maintenance == Some(MaintenanceMode::Suppress)
+                    || flapping;
+                // announced once the maintenance is over (see `Self::announce_suppressed_health`).
+                if maintenance == Some(MaintenanceMode::Suppress) && let NotificationReason::HealthChanged(change) = &notification.reason {
+                    self.suppressed_health.entry(notification.element_id.clone())
+                        .or_insert_with(|| (String::new(), change.old.clone()))
+                        .0.clone_from(&notification.component_id);
+                }
+                trace!("sending out notification (suppressed: {suppressed}): {notification:?}");
+                if self.loaded_config.storage.history.enabled {
+                    self.history.record(notification.clone());
+                }
+                (notification, suppressed)
+            })
+            .collect::<Vec<_>>();
+        self.bus.publish(&notifications, self.loaded_config.bus.capacity);
+    }
+    /// The provider instance (if it is still loaded), to hand it the events published on the
+    /// notification bus once the server is no longer locked.
+    pub(crate) fn notification_provider(&self, node: &Node) -> Option<Arc<dyn ErasedProvider>> {
+        let (data, component) = self.components.entries()
+            .find(|(data, _)| data.instance == node.instance && data.type_id == node.type_id)?;
+        let info = data.notification_provider_info.as_ref()?;
+        // SAFETY: The correctness of the type is guaranteed by the creation of
+        //         `NotificationProviderInfo::provider` and `Components::entries`.
+        Some(unsafe { (info.provider)(component) })
+    }
+    /// Returns the notification providers that don't have a task consuming their queue of the
+    /// notification bus yet.
+    pub(crate) fn take_unattended_subscribers(&mut self) -> Vec<std::sync::Arc<Subscriber>> {
+        self.bus.take_unattended()
+    }
+    /// How well each notification provider keeps up with the published notifications.
+    pub(crate) fn notification_bus_metrics(&self) -> BTreeMap<String, SubscriberMetrics> {
+        self.bus.metrics()
+    }
+    /// Whether any published notification wasn't handed to its provider yet or any delivery is
+    /// outstanding (see [`Self::has_outstanding_deliveries`]).
+    pub(crate) fn has_pending_notifications(&self) -> bool {
+        !self.bus.is_idle() || self.has_outstanding_deliveries()
+    }
+    /// Marks the states as changed (so that they are persisted) and publishes them as a new
+    /// snapshot. Has to be called after every change of the states.
+    fn states_changed(&mut self) {
+        self.unsaved_changes = true;
+        self.published_states.publish(&self.states);
+    }
+    /// Where the latest snapshot of the states is published.
+    pub(super) fn published_states(&self) -> PublishedStates {
+        self.published_states.clone()
+    }
+    pub(crate) fn get_uptime(&self, element_id: &str, since: chrono::DateTime<chrono::Utc>, until: chrono::DateTime<chrono::Utc>) -> Uptime {
+        Uptime::compute(self.history.online_changes(element_id), since, until)
+    }
+    pub(crate) fn get_history(&self, element_id: Option<&str>, since: Option<chrono::DateTime<chrono::Utc>>, until: Option<chrono::DateTime<chrono::Utc>>) -> Vec<HistoryEntry> {
+        self.history.query(element_id, since, until)
+    }
+}
+// maintenance
+impl Server {
+    /// Returns the maintenance window the element is currently in (if any).
+    pub(crate) fn active_maintenance(&self, element_id: &str) -> Option<&MaintenanceWindow> {
+        let now = chrono::Utc::now();
+        self.loaded_config.maintenance.iter()
+            .find(|window| window.applies_to(element_id) && window.is_active(now))
+    }
+    /// Sets or removes the maintenance attribute of all elements according to the currently active
+    /// maintenance windows.
+    pub(crate) fn update_maintenance(&mut self) {
+        let changes = self.states.iter()
+            .filter_map(|(id, state)| {
+                let value = self.active_maintenance(id).map(MaintenanceWindow::attribute_value);
+                (state.attributes.get(maintenance::ATTRIBUTE_ID) != value.as_ref())
+                    .then(|| (id.clone(), value))
+            })
+            .collect::<Vec<_>>();
+        for (element_id, value) in changes {
+            if let Some(value) = value {
+                info!("element {element_id} is now under maintenance");
+                self.attribute_change(maintenance::COMPONENT_ID, &element_id, maintenance::ATTRIBUTE_ID, value);
+            } else {
+                info!("maintenance of element {element_id} is over");
+                self.delete_attribute(&element_id, maintenance::ATTRIBUTE_ID, maintenance::COMPONENT_ID, true);
+                self.announce_suppressed_health(&element_id);
+            }
+        }
+    }
+    /// Notifies about the health of the element if it changed while notifications about it were
+    /// suppressed by a maintenance window that is over now, so that providers don't miss that e.g.
+    /// the element went offline during the maintenance and stayed offline.
+    fn announce_suppressed_health(&mut self, element_id: &str) {
+        let Some((component_id, before)) = self.suppressed_health.remove(element_id) else { return; };
+        let Some(state) = self.states.get(element_id) else { return; };
+        if state.health == before { return; }
+        let change = HealthChange { old: before, new: state.health.clone() };
+        info!("health of element {element_id} changed during its maintenance ({} -> {})", change.old, change.new);
+        self.notify(Notification::new(component_id, element_id.to_string(), NotificationReason::HealthChanged(change)));
+    }
+    /// Removes the incidents that were closed longer ago than the configured retention.
+    pub(crate) fn prune_incidents(&mut self) {
+        let pruned = self.incidents.prune(chrono::Utc::now() - self.loaded_config.storage.incident_retention);
+        if pruned > 0 {
+            debug!("pruned {pruned} closed incidents");
+            self.unsaved_incidents = true;
+        }
+    }
+    /// Marks flapping elements that calmed down as no longer flapping.
+    pub(crate) fn update_flapping(&mut self) {
+        let now = chrono::Utc::now();
+        let config = &self.loaded_config.flapping;
+        let stopped = self.states.iter()
+            .filter(|(_, state)| state.flapping)
+            .map(|(id, _)| id.clone())
+            .collect::<Vec<_>>().into_iter()
+            .filter(|id| config.stops_flapping(self.flapping.recent(id, now, config)))
+            .collect::<Vec<_>>();
+        for element_id in stopped {
+            let Some(state) = self.states.get_mut(&element_id) else { continue; };
+            info!("element {element_id} stopped flapping");
+            state.flapping = false;
+            let health = state.health.clone();
+            self.states_changed();
+            self.notify(Notification::new(
+                flapping::COMPONENT_ID.to_string(),
+                element_id,
+                NotificationReason::FlappingStopped(health),
+            ));
+        }
+    }
+}
+// incidents
+impl Server {
+    /// Takes the due steps of the escalation policies of all open incidents nobody acknowledged.
+    ///
+    /// Incidents of elements whose notifications are held back by a maintenance window aren't
+    /// escalated either. Once the window is over, the delays of the remaining steps count from
+    /// then on, so that they aren't all taken at once.
+    pub(crate) fn update_escalations(&mut self) {
+        let now = chrono::Utc::now();
+        let (suppressed, escalating) = self.incidents.iter()
+            .filter(|incident| incident.is_open() && incident.acknowledged.is_none())
+            .partition::<Vec<_>, _>(|incident| self.active_maintenance(&incident.element_id)
+                .is_some_and(|window| window.mode == MaintenanceMode::Suppress));
+        let suppressed = suppressed.into_iter().map(|incident| incident.id).collect::<HashSet<_>>();
+        let escalating = escalating.into_iter().map(|incident| incident.id).collect::<HashSet<_>>();
+        for id in self.suppressed_escalations.intersection(&escalating) {
+            if let Some(incident) = self.incidents.get_mut(*id) {
+                debug!("escalation of incident {id} is no longer held back by a maintenance window");
+                incident.escalation_start = Some(now);
+                self.unsaved_incidents = true;
+            }
+        }
+        self.suppressed_escalations = suppressed;
+        let due = self.incidents.iter()
+            .filter(|incident| escalating.contains(&incident.id))
+            .filter_map(|incident| {
+                let policy = self.loaded_config.escalation.iter()
+                    .find(|policy| policy.applies_to(&incident.element_id))?;
+                let start = incident.escalation_start.unwrap_or(incident.start);
+                let steps = policy.due_steps(incident.escalation_steps, start, now);
+                (!steps.is_empty()).then(|| (incident.clone(), policy.name.clone(), steps))
+            })
+            .collect::<Vec<_>>();
+        for (incident, policy, steps) in due {
+            let mut taken = incident.escalation_steps;
+            for (index, step) in steps {
+                info!("escalating incident {} of {} (step {index} of {policy})", incident.id, incident.element_id);
+                self.escalate(&step.provider, step.target.as_deref(), Notification::new(
+                    escalation::COMPONENT_ID.to_string(),
+                    incident.element_id.clone(),
+                    NotificationReason::Escalation(Escalation {
+                        policy: policy.clone(),
+                        step: index,
+                        incident: incident.id,
+                        down_since: incident.start,
+                        target: step.target.clone(),
+                    }),
+                ));
+                taken = index + 1;
+            }
+            if let Some(incident) = self.incidents.get_mut(incident.id) {
+                incident.escalation_steps = taken;
+                self.unsaved_incidents = true;
+            }
+        }
+    }
+    /// Publishes the notification for the given provider only.
+    fn escalate(&mut self, provider_id: &str, target: Option<&str>, notification: Notification) {
+        if self.loaded_config.storage.history.enabled {
+            self.history.record(notification.clone());
+        }
+        if !self.bus.escalate(provider_id, notification, target, self.loaded_config.bus.capacity) {
+            warn!("can't escalate via {provider_id} as it isn't a loaded notification provider");
+        }
+    }
+    pub(crate) fn get_incidents(&self) -> Vec<Incident> {
+        self.incidents.iter().cloned().collect()
+    }
+    pub(crate) fn get_incident(&self, id: u64) -> Option<Incident> {
+        self.incidents.get(id).cloned()
+    }
+    pub(crate) fn acknowledge_incident(&mut self, id: u64, by: &str) -> Option<Incident> {
+        let incident = self.incidents.get_mut(id)?;
+        incident.acknowledged = Some(Acknowledgement {
+            by: by.to_string(),
+            timestamp: chrono::Utc::now(),
+        });
+        self.unsaved_incidents = true;
+        Some(incident.clone())
+    }
+    pub(crate) fn add_incident_note(&mut self, id: u64, author: &str, text: &str) -> Option<Incident> {
+        let incident = self.incidents.get_mut(id)?;
+        incident.notes.push(IncidentNote {
+            author: author.to_string(),
+            timestamp: chrono::Utc::now(),
+            text: text.to_string(),
+        });
+        self.unsaved_incidents = true;
+        Some(incident.clone())
+    }
+}
+// deliveries
+impl Server {
+    pub(crate) fn register_delivery_handler<P: ReliableNotificationProvider>(&mut self) {
+        for instance in self.instances::<P>() {
+            self.delivery_handlers.insert(instance, deliver_with::<P>);
+        }
+    }
+    pub(super) fn delivery_inbox(&self) -> DeliveryInbox {
+        self.delivery_inbox.clone()
+    }
+    pub(super) fn removal_inbox(&self) -> RemovalInbox {
+        self.removal_inbox.clone()
+    }
+    /// Removes the elements queued for removal.
+    pub(super) fn drain_removal_inbox(&mut self) {
+        let queued = std::mem::take(&mut *self.removal_inbox.lock());
+        for (component_id, element_id) in queued {
+            self.remove_element(&component_id, &element_id);
+        }
+    }
+    /// Moves the deliveries queued by providers into the delivery queue.
+    fn drain_delivery_inbox(&mut self) {
+        let queued = std::mem::take(&mut *self.delivery_inbox.lock());
+        for delivery in queued {
+            self.deliveries.queue(&delivery.provider, delivery.payload);
+            self.delivery_handlers.insert(delivery.provider, delivery.handler);
+            self.unsaved_deliveries = true;
+        }
+    }
+    /// Marks all due deliveries as in flight and returns them together with the future sending them out.
+    pub(crate) fn start_due_deliveries(&mut self) -> Vec<(u64, Result<DeliveryFuture, String>)> {
+        self.drain_delivery_inbox();
+        let due = self.deliveries.take_due(chrono::Utc::now(), |provider| self.delivery_handlers.contains_key(provider));
+        due.into_iter()
+            .map(|delivery| {
+                let handler = self.delivery_handlers[delivery.provider.as_str()];
+                (delivery.id, handler(self, &delivery.provider, delivery.payload))
+            })
+            .collect()
+    }
+    /// Whether deliveries were queued, are due or are currently being sent (e.g. to wait for them
+    /// before shutting down). Deliveries waiting for a retry don't count.
+    fn has_outstanding_deliveries(&self) -> bool {
+        !self.delivery_inbox.lock().is_empty()
+            || self.deliveries.is_busy(chrono::Utc::now(), |provider| self.delivery_handlers.contains_key(provider))
+    }
+    /// How long the server waits for things to finish when shutting down.
+    pub(crate) fn shutdown_timeout(&self) -> std::time::Duration {
+        self.loaded_config.shutdown.timeout.to_std().unwrap_or_default()
+    }
+    pub(crate) fn delivery_finished(&mut self, id: u64, result: Result<(), String>) {
+        match result {
+            Ok(()) => self.deliveries.succeeded(id),
+            Err(e) => self.deliveries.failed(id, e, &self.loaded_config.delivery),
+        }
+        self.unsaved_deliveries = true;
+    }
+    /// Returns the pending deliveries and the dead letters.
+    pub(crate) fn get_deliveries(&self) -> (Vec<Delivery>, Vec<Delivery>) {
+        (self.deliveries.pending().to_vec(), self.deliveries.dead().to_vec())
+    }
+    pub(crate) fn retry_delivery(&mut self, id: u64) -> Option<Delivery> {
+        let delivery = self.deliveries.retry(id)?;
+        self.unsaved_deliveries = true;
+        Some(delivery)
+    }
+}
+// persistence
+impl Server {
+    /// Returns the states that need to be persisted (and where to) if they changed since the last
+    /// snapshot.
+    pub(crate) fn take_snapshot(&mut self) -> Option<(PathBuf, States)> {
+        if !self.loaded_config.storage.enabled || !self.unsaved_changes {
+            return None;
+        }
+        self.unsaved_changes = false;
+        Some((self.loaded_config.storage.state_file.clone(), self.states.clone()))
+    }
+    /// Writing the snapshot taken by [`Self::take_snapshot`] failed, so the states are written with
+    /// the next one.
+    pub(crate) fn retry_snapshot(&mut self) {
+        self.unsaved_changes = true;
+    }
+    /// Prunes the history and returns the changes that need to be written to disk (and where to).
+    pub(crate) fn take_history_snapshot(&mut self) -> Option<(PathBuf, HistoryWrite)> {
+        let config = &self.loaded_config.storage.history;
+        if !config.enabled {
+            return None;
+        }
+        self.history.prune(chrono::Utc::now() - config.retention);
+        Some((config.file.clone(), self.history.take_unsaved()?))
+    }
+    /// Writing the changes taken by [`Self::take_history_snapshot`] failed, so the whole history is
+    /// written with the next snapshot.
+    pub(crate) fn retry_history_snapshot(&mut self) {
+        self.history.write_failed();
+    }
+    /// Returns the incidents that need to be persisted (and where to) if they changed since the
+    /// last snapshot.
+    pub(crate) fn take_incidents_snapshot(&mut self) -> Option<(PathBuf, Incidents)> {
+        if !self.loaded_config.storage.enabled || !self.unsaved_incidents {
+            return None;
+        }
+        self.unsaved_incidents = false;
+        Some((self.loaded_config.storage.incidents_file.clone(), self.incidents.clone()))
+    }
+    /// Writing the snapshot taken by [`Self::take_incidents_snapshot`] failed, so the incidents are
+    /// written with the next one.
+    pub(crate) fn retry_incidents_snapshot(&mut self) {
+        self.unsaved_incidents = true;
+    }
+    /// Returns the delivery queue & dead letters that need to be persisted (and where to) if they
+    /// changed since the last snapshot.
+    pub(crate) fn take_deliveries_snapshot(&mut self) -> Option<(PathBuf, Deliveries)> {
+        self.drain_delivery_inbox();
+        if !self.loaded_config.storage.enabled || !self.unsaved_deliveries {
+            return None;
+        }
+        self.unsaved_deliveries = false;
+        Some((self.loaded_config.storage.deliveries_file.clone(), self.deliveries.clone()))
+    }
+    /// Writing the snapshot taken by [`Self::take_deliveries_snapshot`] failed, so the deliveries are
+    /// written with the next one.
+    pub(crate) fn retry_deliveries_snapshot(&mut self) {
+        self.unsaved_deliveries = true;
+    }
+    /// The files to watch for changes and how long to wait for them to settle, or `None` if the
+    /// config shouldn't be reloaded automatically.
+    pub(crate) fn watched_config(&self) -> Option<(Vec<PathBuf>, std::time::Duration)> {
+        let reload = &self.loaded_config.reload;
+        if !reload.watch { return None; }
+        let debounce = reload.debounce.to_std()
+            .unwrap_or_else(|e| {
+                error!("invalid reload debounce: {e}; falling back to 2s");
+                std::time::Duration::from_secs(2)
             });
+        Some((self.config_files.clone(), debounce))
     }
-    pub(crate) fn get_states(&self) -> HashMap<String, State> {
-        self.states.clone()
+    pub(crate) fn snapshot_interval(&self) -> std::time::Duration {
+        self.loaded_config.storage.snapshot_interval.to_std()
+            .unwrap_or_else(|e| {
+                error!("invalid snapshot interval: {e}; falling back to 30s");
+                std::time::Duration::from_secs(30)
+            })
     }
 }
 impl Server {
@@ -333,4 +1261,343 @@ impl Server {
         }
         Err(request)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use super::*;
+    use super::super::ServerHandle;
+    use crate::ComponentHandle;
+    use crate::state::HealthStatus;
+    use crate::test_util::TempDir;
+
+    /// Storage is disabled, so that the servers of the tests don't touch anything but their config.
+    const BASE_CONFIG: &str = "[storage]\nenabled = false\nhistory.enabled = false\n\n[instances]\nelements = [\"a\", \"b\"]\n\n";
+
+    #[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+    struct ElementsConfig {
+        #[serde(default)]
+        elements: Vec<String>,
+        #[serde(default)]
+        fail: bool,
+    }
+    /// A component owning the elements in its config, which fails to reconfigure if told to.
+    struct Elements {
+        handle: ComponentHandle,
+        config: ElementsConfig,
+    }
+    impl Component for Elements {
+        const ID: &'static str = "elements";
+        type Config = ElementsConfig;
+        type ConfigError = std::io::Error;
+        fn init(handle: ComponentHandle, config: Self::Config) -> Result<Self, Self::ConfigError> {
+            for element in &config.elements {
+                handle.change_health(element, true.into());
+            }
+            Ok(Self { handle, config })
+        }
+        fn reconfigure(&mut self, config: Self::Config) -> Result<(), Self::ConfigError> {
+            if config.fail {
+                return Err(std::io::Error::other("told to fail"));
+            }
+            for element in self.config.elements.iter().filter(|element| !config.elements.contains(element)) {
+                self.handle.queue_element_removal(element);
+            }
+            self.config = config;
+            Ok(())
+        }
+    }
+    /// A server (without any of its tasks) with the [`BASE_CONFIG`] followed by `config` and the
+    /// [`Elements`] component added.
+    fn server_with_elements(directory: &TempDir, config: &str) -> ServerHandle {
+        let path = directory.write("config.toml", &format!("{BASE_CONFIG}{config}"));
+        let server = ServerHandle::from_backend(Arc::new(RwLock::new(Server::new(path))));
+        server.add_component::<Elements>();
+        server
+    }
+    fn elements_handle(server: &ServerHandle, instance: &str) -> ComponentHandle {
+        server.0.read().components.get::<Elements>(instance).expect("instance is loaded").read().handle.clone()
+    }
+
+    /// A server that doesn't touch the disk.
+    fn server() -> Server {
+        let mut config = Config::default();
+        config.storage.enabled = false;
+        config.storage.history.enabled = false;
+        Server::with_config(PathBuf::new(), config, Vec::new())
+    }
+    fn set_online(server: &mut Server, element_id: &str, online: bool, confirmation: Option<Confirmation>) {
+        server.update_element("test", element_id, |update| {
+            update.set_online(online);
+            if let Some(confirmation) = confirmation {
+                update.confirm_with(confirmation);
+            }
+        });
+    }
+    fn online(server: &Server, element_id: &str) -> Option<bool> {
+        server.states.get(element_id)?.health.online()
+    }
+
+    #[test]
+    fn only_consecutive_observations_confirm_a_change() {
+        let confirmation = Confirmation { failures: 1, successes: 2, grace: None };
+        let mut server = server();
+        set_online(&mut server, "a", false, None);
+        assert_eq!(server.states.get("a").map(|state| state.health.status), Some(HealthStatus::Down));
+        set_online(&mut server, "a", true, Some(confirmation));
+        assert_eq!(online(&server, "a"), Some(false));
+        // a failed check in between starts the count over.
+        set_online(&mut server, "a", false, Some(confirmation));
+        set_online(&mut server, "a", true, Some(confirmation));
+        assert_eq!(online(&server, "a"), Some(false));
+        set_online(&mut server, "a", false, Some(confirmation));
+        set_online(&mut server, "a", true, Some(confirmation));
+        assert_eq!(online(&server, "a"), Some(false));
+        set_online(&mut server, "a", true, Some(confirmation));
+        assert_eq!(online(&server, "a"), Some(true));
+    }
+
+    #[test]
+    fn new_elements_are_published_once_with_their_attribute() {
+        let mut server = server();
+        let published = server.published_states();
+        server.attribute_change("test", "a", "players", AttributeValue::Count(3));
+        let snapshot = published.load();
+        assert_eq!(snapshot.version(), 1);
+        assert_eq!(snapshot.get("a").and_then(|state| state.attributes.get("players")), Some(&AttributeValue::Count(3)));
+    }
+
+    #[test]
+    fn deleting_subattributes_publishes_once() {
+        let mut server = server();
+        let published = server.published_states();
+        for attribute in ["players", "players.max", "players.online", "version"] {
+            server.attribute_change("test", "a", attribute, AttributeValue::Count(1));
+        }
+        let version = published.load().version();
+        server.delete_attribute("a", "players", "test", false);
+        let snapshot = published.load();
+        assert_eq!(snapshot.version(), version + 1);
+        assert_eq!(snapshot.get("a").map(|state| state.attributes.keys().collect::<Vec<_>>()), Some(vec![&"version".to_string()]));
+        // nothing left to delete, so nothing is published.
+        server.delete_attribute("a", "players", "test", false);
+        assert_eq!(published.load().version(), version + 1);
+    }
+
+    #[test]
+    fn element_removals_are_applied_once_the_server_is_unlocked() {
+        let directory = TempDir::new("queued-removal");
+        let server = server_with_elements(&directory, "[elements.a]\nelements = [\"x\", \"y\"]\n");
+        let handle = elements_handle(&server, "elements.a");
+        handle.queue_element_removal("x");
+        assert!(!server.states().contains("x"));
+        let locked = server.0.read();
+        handle.queue_element_removal("y");
+        drop(locked);
+        assert!(server.states().contains("y"));
+        // as the maintenance task does.
+        server.0.write().drain_removal_inbox();
+        assert!(!server.states().contains("y"));
+    }
+
+    #[test]
+    fn reload_is_rolled_back_when_a_component_fails_to_reconfigure() {
+        let directory = TempDir::new("reload-rollback");
+        let server = server_with_elements(&directory, "[elements.a]\nelements = [\"x\", \"y\"]\n\n[elements.b]\nelements = [\"z\"]\n");
+        // `elements.a` is reconfigured (dropping `y`) before `elements.b` fails.
+        directory.write("config.toml", &format!("{BASE_CONFIG}[elements.a]\nelements = [\"x\"]\n\n[elements.b]\nelements = [\"z\"]\nfail = true\n"));
+        let report = server.reload_config();
+        assert!(!report.applied);
+        assert!(matches!(report.components.get("elements.b"), Some(ReloadResult::Failed(_))));
+        assert!(!report.components.contains_key("elements.a"));
+        {
+            let backend = server.0.read();
+            let elements_of = |instance| backend.loaded_config.config_of(instance)
+                .and_then(|config| config.get("elements"))
+                .and_then(toml::Value::as_array)
+                .map(Vec::len);
+            assert_eq!(elements_of("elements.a"), Some(2));
+            assert!(backend.loaded_config.config_of("elements.b").and_then(|config| config.get("fail")).is_none());
+            let rolled_back = backend.components.get::<Elements>("elements.a").expect("instance is loaded").read().config.elements.clone();
+            assert_eq!(rolled_back, ["x", "y"]);
+            assert!(backend.removal_inbox.lock().is_empty());
+        }
+        // the element `elements.a` dropped while it was reconfigured is kept.
+        for element in ["x", "y", "z"] {
+            assert!(server.states().contains(element), "{element} was removed");
+        }
+        // a reload that succeeds afterward applies the removal.
+        directory.write("config.toml", &format!("{BASE_CONFIG}[elements.a]\nelements = [\"x\"]\n\n[elements.b]\nelements = [\"z\"]\n"));
+        let report = server.reload_config();
+        assert!(report.applied);
+        assert_eq!(report.components.get("elements.a"), Some(&ReloadResult::Applied));
+        assert_eq!(report.components.get("elements.b"), Some(&ReloadResult::Unchanged));
+        assert!(!server.states().contains("y"));
+    }
+
+    #[test]
+    fn elements_first_seen_offline_have_an_incident() {
+        let mut server = server();
+        set_online(&mut server, "down", false, None);
+        set_online(&mut server, "up", true, None);
+        server.health_changed("test", "also-down", false.into());
+        server.update_element("test", "degraded", |update| {
+            update.set_health(Health::with_reason(HealthStatus::Degraded, "slow"));
+        });
+        let open = server.incidents.iter()
+            .filter(|incident| incident.is_open())
+            .map(|incident| incident.element_id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(open, ["down", "also-down"]);
+        // the incident is closed like any other once the element comes back.
+        set_online(&mut server, "down", true, None);
+        assert!(server.incidents.iter().all(|incident| incident.is_open() == (incident.element_id == "also-down")));
+    }
+
+    #[test]
+    fn escalation_resumes_after_suppressing_maintenance() {
+        let mut server = server();
+        let step = |minutes, provider: &str| crate::EscalationStep {
+            after: chrono::Duration::minutes(minutes),
+            provider: provider.to_string(),
+            target: None,
+        };
+        server.loaded_config.escalation.push(crate::EscalationPolicy {
+            name: "default".to_string(),
+            elements: vec!["*".to_string()],
+            steps: vec![step(0, "first"), step(30, "second")],
+        });
+        server.loaded_config.maintenance.push(MaintenanceWindow {
+            elements: vec!["*".to_string()],
+            start: chrono::Utc::now() - chrono::Duration::minutes(1),
+            duration: chrono::Duration::hours(1),
+            repeat: None,
+            description: None,
+            mode: MaintenanceMode::Suppress,
+        });
+        set_online(&mut server, "a", false, None);
+        let id = server.incidents.iter().next().expect("incident is opened").id;
+        // the element has been offline long enough for both steps.
+        server.incidents.get_mut(id).expect("exists").start -= chrono::Duration::hours(1);
+        server.update_escalations();
+        assert_eq!(server.incidents.get(id).expect("exists").escalation_steps, 0);
+        server.loaded_config.maintenance.clear();
+        server.update_escalations();
+        let incident = server.incidents.get(id).expect("exists");
+        assert_eq!(incident.escalation_steps, 1);
+        assert!(incident.escalation_start.is_some());
+        // without a maintenance window in between, the delays count from when the element went offline.
+        set_online(&mut server, "b", false, None);
+        let id = server.incidents.iter().last().expect("incident is opened").id;
+        server.incidents.get_mut(id).expect("exists").start -= chrono::Duration::hours(1);
+        server.update_escalations();
+        assert_eq!(server.incidents.get(id).expect("exists").escalation_steps, 2);
+    }
+
+    /// A server that persists everything into the directory.
+    fn persisting_server(directory: &Path) -> Server {
+        let mut config = Config::default();
+        config.storage.state_file = directory.join("states.json");
+        config.storage.incidents_file = directory.join("incidents.json");
+        config.storage.deliveries_file = directory.join("deliveries.json");
+        config.storage.history.file = directory.join("history.jsonl");
+        Server::with_config(PathBuf::new(), config, Vec::new())
+    }
+
+    #[test]
+    fn persisted_state_is_restored() {
+        let directory = TempDir::new("persisted-state");
+        let server = ServerHandle::from_backend(Arc::new(RwLock::new(persisting_server(directory.path()))));
+        set_online(&mut server.0.write(), "a", false, None);
+        set_online(&mut server.0.write(), "b", true, None);
+        server.persist();
+        let restored = persisting_server(directory.path());
+        assert_eq!(online(&restored, "a"), Some(false));
+        assert_eq!(online(&restored, "b"), Some(true));
+        let open = restored.incidents.iter().filter(|incident| incident.is_open()).collect::<Vec<_>>();
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].element_id, "a");
+        assert_eq!(restored.history.query(Some("a"), None, None).len(), 1);
+    }
+
+    #[test]
+    fn failed_snapshots_are_written_with_the_next_one() {
+        let directory = TempDir::new("failed-snapshot");
+        let missing = directory.path().join("missing");
+        let server = ServerHandle::from_backend(Arc::new(RwLock::new(persisting_server(&missing))));
+        set_online(&mut server.0.write(), "a", false, None);
+        // the directory doesn't exist, so nothing can be written.
+        server.persist();
+        assert!(std::fs::read_dir(directory.path()).expect("exists").next().is_none());
+        std::fs::create_dir(&missing).expect("couldn't create the directory");
+        server.persist();
+        let restored = persisting_server(&missing);
+        assert_eq!(online(&restored, "a"), Some(false));
+        assert_eq!(restored.incidents.iter().count(), 1);
+        assert_eq!(restored.history.query(Some("a"), None, None).len(), 1);
+        // nothing changed since, so nothing is taken.
+        assert!(server.0.write().take_snapshot().is_none());
+        assert!(server.0.write().take_incidents_snapshot().is_none());
+    }
+
+    /// Subscribes to the notification bus of the server, receiving suppressed notifications as well.
+    fn observe(server: &mut Server) -> Arc<Subscriber> {
+        server.bus.subscribe(Node::new("observer", TypeId::of::<()>()), true);
+        server.bus.take_unattended().pop().expect("just subscribed")
+    }
+    /// Takes the batches of notifications queued for the subscriber, describing each notification.
+    fn batches(server: &Server, subscriber: &Subscriber) -> Vec<Vec<String>> {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().expect("couldn't create the runtime");
+        let mut batches = Vec::new();
+        while server.bus.metrics()["observer"].queued > 0 {
+            let Some(Event::Notify(batch)) = runtime.block_on(subscriber.next()) else { panic!("expected a batch") };
+            subscriber.done();
+            batches.push(batch.iter()
+                .map(|notification| match &notification.reason {
+                    NotificationReason::NewElement(health) => format!("new {health}"),
+                    NotificationReason::HealthChanged(change) => format!("{} -> {}", change.old, change.new),
+                    NotificationReason::AttributeCreated(id, _) => format!("created {id}"),
+                    NotificationReason::AttributeChanged(id, _, _) => format!("changed {id}"),
+                    NotificationReason::DeleteAttribute(id, _) => format!("deleted {id}"),
+                    reason => format!("{reason:?}"),
+                })
+                .collect());
+        }
+        batches
+    }
+
+    #[test]
+    fn element_updates_are_published_as_one_batch() {
+        let mut server = server();
+        let subscriber = observe(&mut server);
+        let published = server.published_states();
+        server.update_element("test", "a", |update| {
+            update.set_online(false)
+                .set_attribute("players", AttributeValue::Count(1))
+                .set_attribute("version", AttributeValue::Count(2));
+        });
+        assert_eq!(published.load().version(), 1);
+        server.update_element("test", "a", |update| {
+            update.set_online(true)
+                .set_attribute("players", AttributeValue::Count(1))
+                .set_attribute("version", AttributeValue::Count(3))
+                .delete_attribute("players", true);
+        });
+        assert_eq!(published.load().version(), 2);
+        // nothing changes, so nothing is published.
+        server.update_element("test", "a", |update| {
+            update.set_online(true).delete_attribute("players", true);
+        });
+        server.update_element("test", "b", |update| {
+            update.delete_attribute("players", true);
+        });
+        assert_eq!(published.load().version(), 2);
+        assert!(!server.states.contains_key("b"));
+        let offline = Health::from(false);
+        let online = Health::from(true);
+        assert_eq!(batches(&server, &subscriber), [
+            vec![format!("new {offline}"), "created players".to_string(), "created version".to_string()],
+            vec![format!("{offline} -> {online}"), "changed version".to_string(), "deleted players".to_string()],
+        ]);
+    }
+}
@@ -0,0 +1,70 @@
+use std::any::TypeId;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::btree_map::Entry;
+use std::sync::Arc;
+use parking_lot::RwLock;
+use untyped::{TypeMap, Untyped};
+
+/// How a loaded component is stored, so that it can be shared with whoever calls it without the
+/// server being locked (e.g. the dispatchers of the notification bus).
+pub(super) type Shared<C> = Arc<RwLock<C>>;
+
+/// The loaded components, keyed by their type and the name of their instance.
+///
+/// Components are stored as [`Shared`], so the [`Untyped`]s returned are of that type.
+pub(super) struct Components<D> {
+    instances: BTreeMap<String, TypeMap<D>>,
+    /// The type id of the [`Shared`] component by the type id of the component.
+    storage_ids: HashMap<TypeId, TypeId>,
+}
+impl<D> Components<D> {
+    pub(super) fn new() -> Self {
+        Self { instances: BTreeMap::new(), storage_ids: HashMap::new() }
+    }
+    pub(super) fn contains<C: 'static>(&self, instance: &str) -> bool {
+        self.instances.get(instance).is_some_and(TypeMap::contains_key::<Shared<C>>)
+    }
+    pub(super) fn contains_type_id(&self, instance: &str, type_id: TypeId) -> bool {
+        let Some(storage_id) = self.storage_ids.get(&type_id) else { return false; };
+        self.instances.get(instance).is_some_and(|components| components.contains_type_id(storage_id))
+    }
+    /// The names of all instances with any loaded component.
+    pub(super) fn loaded_instances(&self) -> HashSet<String> {
+        self.instances.iter()
+            .filter(|(_, components)| components.entries().next().is_some())
+            .map(|(instance, _)| instance.clone())
+            .collect()
+    }
+    pub(super) fn get<C: 'static>(&self, instance: &str) -> Option<&Shared<C>> {
+        self.instances.get(instance)?.get::<Shared<C>>()
+    }
+    /// The names of the loaded instances of the type (ordered by name).
+    pub(super) fn instances<C: 'static>(&self) -> Vec<String> {
+        self.instances.iter()
+            .filter(|(_, components)| components.contains_key::<Shared<C>>())
+            .map(|(instance, _)| instance.clone())
+            .collect()
+    }
+    pub(super) fn additional_data_mut<C: 'static>(&mut self, instance: &str) -> Option<&mut D> {
+        self.instances.get_mut(instance)?.additional_data_mut::<Shared<C>>()
+    }
+    pub(super) fn insert<C: Send + Sync + 'static>(&mut self, instance: &str, component: C, data: D) -> Option<(D, Untyped)> {
+        self.storage_ids.insert(TypeId::of::<C>(), TypeId::of::<Shared<C>>());
+        // NOTE: not using `or_default`, as `TypeMap` only guarantees `new`.
+        let components = match self.instances.entry(instance.to_string()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(TypeMap::new()),
+        };
+        components.insert(Arc::new(RwLock::new(component)), data)
+    }
+    pub(super) fn remove(&mut self, instance: &str, type_id: TypeId) -> Option<(D, Untyped)> {
+        let storage_id = self.storage_ids.get(&type_id)?;
+        self.instances.get_mut(instance)?.remove_by_type_id(storage_id)
+    }
+    pub(super) fn entries(&self) -> impl Iterator<Item=(&D, &Untyped)> {
+        self.instances.values().flat_map(TypeMap::entries)
+    }
+    pub(super) fn entries_mut(&mut self) -> impl Iterator<Item=(&mut D, &mut Untyped)> {
+        self.instances.values_mut().flat_map(TypeMap::entries_mut)
+    }
+}
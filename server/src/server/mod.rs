@@ -2,6 +2,36 @@ mod backend;
 use backend::Server;
 mod owner_handle;
 mod provider_handle;
+mod storage;
+mod components;
 
-pub use owner_handle::ServerHandle;
-pub use provider_handle::ComponentHandle;
\ No newline at end of file
+use std::sync::Arc;
+use parking_lot::RwLock;
+
+pub use owner_handle::{DryRun, ServerHandle};
+pub use provider_handle::ComponentHandle;
+
+/// Spawns a task for every notification provider that subscribed to the notification bus but
+/// doesn't have one yet, handing the provider the notifications published for it.
+///
+/// The provider is called without the server being locked, so a slow provider doesn't hold up
+/// anyone changing the states. It is called on a thread meant for blocking, so that a provider
+/// e.g. talking to a mail server directly doesn't hold up the tasks of the runtime either.
+fn spawn_dispatchers(backend: &Arc<RwLock<Server>>) {
+    let subscribers = backend.write().take_unattended_subscribers();
+    for subscriber in subscribers {
+        let backend = Arc::downgrade(backend);
+        tokio::spawn(async move {
+            while let Some(event) = subscriber.next().await {
+                let Some(server) = backend.upgrade() else { break; };
+                let provider = server.read().notification_provider(subscriber.node());
+                drop(server);
+                if let Some(provider) = provider
+                    && let Err(e) = tokio::task::spawn_blocking(move || provider.dispatch(event)).await {
+                    error!("notification provider {} panicked: {e}", subscriber.node().instance);
+                }
+                subscriber.done();
+            }
+        });
+    }
+}
@@ -1,14 +1,26 @@
-use super::Server;
-use crate::notification_provider::NotificationProvider;
-use crate::{Component, ComponentHandle};
+use super::{storage, Server};
+use crate::notification_provider::{NotificationProvider, ReliableNotificationProvider};
+use crate::{Component, ComponentHandle, Config};
 use parking_lot::RwLock;
 use std::any::TypeId;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::panic::AssertUnwindSafe;
 use std::path::PathBuf;
 use std::pin::Pin;
 use std::sync::Arc;
 use axum::extract::Request;
 use crate::state::State;
+use crate::history::HistoryEntry;
+use crate::uptime::Uptime;
+use crate::incident::Incident;
+use crate::delivery::Delivery;
+use crate::registry::ComponentRegistry;
+use crate::reload::{ReloadReport, ReloadResult};
+use crate::dependency::{DependencyGraph, Node};
+use crate::bus::SubscriberMetrics;
+use crate::snapshot::{PublishedStates, StatesSnapshot};
+use chrono::{DateTime, Utc};
+use std::time::{Instant, SystemTime};
 
 #[derive(Clone)]
 /// A Handle to a Server, used to configure said server or interact with it outside of [`Component`]s.
@@ -18,97 +30,510 @@ use crate::state::State;
 /// # Note
 /// This is also an [`axum::handler::Handle`], so that you can use it in a [`axum::Router::route`]
 /// call.
-pub struct ServerHandle(Arc<RwLock<Server>>);
+pub struct ServerHandle(pub(super) Arc<RwLock<Server>>, PublishedStates);
+#[derive(Clone, Debug)]
+/// The outcome of initializing the components without running them (see [`ServerHandle::dry_run`]).
+pub struct DryRun {
+    /// Whether each configured (and not ignored) instance could be initialized (by instance name).
+    pub components: BTreeMap<String, Result<(), String>>,
+    /// The dependencies between the initialized components.
+    pub dependencies: DependencyGraph,
+}
 impl ServerHandle {
+    pub(super) fn from_backend(backend: Arc<RwLock<Server>>) -> Self {
+        let states = backend.read().published_states();
+        Self(backend, states)
+    }
     /// creates a new Server loading the config from the given path.
+    ///
+    /// Restores the persisted element states (if enabled) and starts periodically snapshotting them
+    /// to disk. Also starts keeping track of which elements are under maintenance or stopped flapping,
+    /// escalating incidents nobody acknowledged, pruning old incidents and reloading the config when
+    /// the file changes.
+    ///
+    /// # Panics
+    /// If called outside a tokio runtime.
     #[must_use]
     pub fn new(config_path: PathBuf) -> Self {
-        Self(Arc::new(RwLock::new(Server::new(config_path))))
+        let this = Self::from_backend(Arc::new(RwLock::new(Server::new(config_path))));
+        this.spawn_snapshot_task();
+        this.spawn_maintenance_task();
+        this.spawn_delivery_task();
+        this.spawn_config_watch_task();
+        this
+    }
+    /// Initializes all configured (and not ignored) instances of the components of the registry
+    /// with the given config without actually running them, returning whether each instance could
+    /// be initialized and the dependencies between them.
+    ///
+    /// Nothing is read from or written to disk and tasks spawned by the components never run. A
+    /// component panicking during initialization counts as all of its instances failing.
+    ///
+    /// # Panics
+    /// If the (never driven) tokio runtime the components are initialized in can't be created.
+    #[must_use]
+    pub fn dry_run(mut config: Config, registry: ComponentRegistry) -> DryRun {
+        config.storage.enabled = false;
+        config.storage.history.enabled = false;
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("couldn't create a runtime for the dry run");
+        let _guard = runtime.enter();
+        let this = Self::from_backend(Arc::new(RwLock::new(Server::with_config(PathBuf::new(), config, Vec::new()))));
+        this.0.write().set_registry(registry);
+        let missing = this.0.read().missing_components();
+        let mut panicked = HashMap::new();
+        for registration in missing {
+            let added = std::panic::catch_unwind(AssertUnwindSafe(|| (registration.add)(&this)));
+            if let Err(payload) = added {
+                let message = payload.downcast_ref::<&str>().map(ToString::to_string)
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_default();
+                panicked.insert(registration.id, format!("panicked during initialization: {message}"));
+            }
+        }
+        let server = this.0.read();
+        let components = server.missing_instance_names().into_iter()
+            .map(|instance| {
+                let id = instance.split_once('.').map_or(instance.as_str(), |(id, _)| id);
+                let failure = panicked.get(id).cloned()
+                    .or_else(|| server.init_failure(&instance))
+                    .unwrap_or_else(|| "couldn't be initialized".to_string());
+                (instance, Err(failure))
+            })
+            .chain(server.loaded_instance_names().into_iter().map(|instance| (instance, Ok(()))))
+            .collect();
+        DryRun { components, dependencies: server.dependency_graph() }
+    }
+    fn spawn_snapshot_task(&self) {
+        let backend = Arc::downgrade(&self.0);
+        tokio::spawn(async move {
+            while let Some(interval) = backend.upgrade().map(|server| server.read().snapshot_interval()) {
+                tokio::time::sleep(interval).await;
+                let Some(server) = backend.upgrade() else { break; };
+                Self::from_backend(server).persist();
+            }
+        });
+    }
+    fn spawn_maintenance_task(&self) {
+        let backend = Arc::downgrade(&self.0);
+        tokio::spawn(async move {
+            while let Some(server) = backend.upgrade() {
+                {
+                    let mut server = server.write();
+                    // removals queued while the server was locked (see `ComponentHandle::queue_element_removal`).
+                    server.drain_removal_inbox();
+                    server.update_maintenance();
+                    server.update_flapping();
+                    server.update_escalations();
+                    server.prune_incidents();
+                }
+                drop(server);
+                tokio::time::sleep(std::time::Duration::from_secs(10)).await;
+            }
+        });
+    }
+    fn spawn_delivery_task(&self) {
+        let backend = Arc::downgrade(&self.0);
+        tokio::spawn(async move {
+            while let Some(server) = backend.upgrade() {
+                let due = server.write().start_due_deliveries();
+                drop(server);
+                for (id, delivery) in due {
+                    let backend = backend.clone();
+                    tokio::spawn(async move {
+                        let result = match delivery {
+                            Ok(future) => future.await,
+                            Err(e) => Err(e),
+                        };
+                        if let Err(e) = &result {
+                            warn!("delivery {id} failed: {e}");
+                        }
+                        if let Some(server) = backend.upgrade() {
+                            server.write().delivery_finished(id, result);
+                        }
+                    });
+                }
+                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            }
+        });
+    }
+    fn spawn_config_watch_task(&self) {
+        fn modified(files: &[PathBuf]) -> Vec<Option<SystemTime>> {
+            files.iter()
+                .map(|file| std::fs::metadata(file).and_then(|metadata| metadata.modified()).ok())
+                .collect()
+        }
+        let backend = Arc::downgrade(&self.0);
+        tokio::spawn(async move {
+            // the modification times the config was last loaded with & the ones seen last.
+            let mut loaded = None;
+            let mut last_seen = None;
+            let mut unchanged_since = Instant::now();
+            while let Some(server) = backend.upgrade() {
+                let watched = server.read().watched_config();
+                if let Some((files, debounce)) = watched {
+                    let current = Some(modified(&files));
+                    if loaded.is_none() {
+                        loaded.clone_from(&current);
+                    }
+                    if current != last_seen {
+                        last_seen = current;
+                        unchanged_since = Instant::now();
+                    } else if last_seen != loaded && unchanged_since.elapsed() >= debounce {
+                        info!("config file changed");
+                        Self::from_backend(server.clone()).reload_config();
+                        // the reloaded config may watch different files.
+                        loaded = None;
+                        last_seen = None;
+                    }
+                } else {
+                    loaded = None;
+                }
+                drop(server);
+                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            }
+        });
+    }
+    /// Starts all loaded components (see [`Component::start`]), dependencies first. Components
+    /// added afterward (e.g. when reloading the config) are started right away.
+    ///
+    /// Until this is called, components are only initialized (which is enough to e.g. check
+    /// whether they can be).
+    #[expect(clippy::must_use_candidate, reason="returning something here is more just for chaining.")]
+    pub fn start(&self) -> &Self {
+        let started = self.0.write().start_components();
+        for future in started {
+            tokio::spawn(future);
+        }
+        self
+    }
+    /// Shuts the server down gracefully, waiting at most for the configured timeout (see
+    /// [`Self::shutdown_timeout`]):
+    /// 1. shuts all started components down (dependants first, see [`Component::shutdown`]),
+    ///    awaiting each of them
+    /// 2. waits until all published notifications were handed to the notification providers and
+    ///    all queued or due deliveries are sent (or failed). Deliveries waiting for a retry are
+    ///    kept for the next start.
+    /// 3. writes a final snapshot of everything to disk (see [`Self::persist`])
+    ///
+    /// The final snapshot is written even if the timeout passed.
+    pub async fn shutdown(&self) {
+        let deadline = tokio::time::Instant::now() + self.shutdown_timeout();
+        let hooks = self.0.write().shutdown_components();
+        for (instance, future) in hooks {
+            if tokio::time::timeout_at(deadline, future).await.is_err() {
+                warn!("component {instance} didn't shut down in time");
+            }
+        }
+        // the notification providers & the delivery task keep working in the meantime.
+        while self.0.read().has_pending_notifications() {
+            if tokio::time::Instant::now() >= deadline {
+                warn!("not all notifications were sent in time; sending the remaining deliveries after the next start");
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+        self.persist();
+        info!("shut down");
+    }
+    /// How long the server waits for components and deliveries when shutting down (see
+    /// [`Self::shutdown`]), as configured in [`Config::shutdown`](crate::Config::shutdown).
+    #[must_use]
+    pub fn shutdown_timeout(&self) -> std::time::Duration {
+        self.0.read().shutdown_timeout()
+    }
+    /// Writes everything that changed since the last snapshot (states, incidents, deliveries &
+    /// history) to disk.
+    ///
+    /// This also happens periodically on its own; calling this is only necessary to force a snapshot
+    /// (e.g. before exiting).
+    pub fn persist(&self) {
+        self.save_states();
+        self.save_incidents();
+        self.save_deliveries();
+        self.save_history();
+    }
+    /// Writes the element states to disk if they changed since the last snapshot.
+    ///
+    /// This also happens periodically on its own; calling this is only necessary to force a snapshot
+    /// (e.g. before exiting).
+    pub fn save_states(&self) {
+        // NOTE: taking the snapshot first, so that the lock isn't held while writing to disk.
+        let Some((path, states)) = self.0.write().take_snapshot() else { return; };
+        if let Err(e) = storage::save_json(&path, &states) {
+            error!("couldn't persist element states to `{}`: {e}", path.to_string_lossy());
+            self.0.write().retry_snapshot();
+        }
+    }
+    /// Writes the incidents to disk if they changed since the last snapshot.
+    ///
+    /// This also happens periodically on its own; calling this is only necessary to force a snapshot.
+    pub fn save_incidents(&self) {
+        let Some((path, incidents)) = self.0.write().take_incidents_snapshot() else { return; };
+        if let Err(e) = storage::save_json(&path, &incidents) {
+            error!("couldn't persist incidents to `{}`: {e}", path.to_string_lossy());
+            self.0.write().retry_incidents_snapshot();
+        }
+    }
+    /// Writes the delivery queue & dead letters to disk if they changed since the last snapshot.
+    ///
+    /// This also happens periodically on its own; calling this is only necessary to force a snapshot.
+    pub fn save_deliveries(&self) {
+        let Some((path, deliveries)) = self.0.write().take_deliveries_snapshot() else { return; };
+        if let Err(e) = storage::save_json(&path, &deliveries) {
+            error!("couldn't persist deliveries to `{}`: {e}", path.to_string_lossy());
+            self.0.write().retry_deliveries_snapshot();
+        }
+    }
+    /// Writes all recorded notifications that weren't written yet to disk and removes outdated ones.
+    ///
+    /// This also happens periodically on its own; calling this is only necessary to force a write.
+    pub fn save_history(&self) {
+        let Some((path, write)) = self.0.write().take_history_snapshot() else { return; };
+        if let Err(e) = storage::save_history(&path, &write) {
+            error!("couldn't persist history to `{}`: {e}", path.to_string_lossy());
+            self.0.write().retry_history_snapshot();
+        }
+    }
+    /// Initializes the instance of `C`, returning `None` if it is ignored, already loaded or
+    /// couldn't be initialized.
+    fn init_instance<C: Component>(&self, instance: &str) -> Option<C> {
+        let config = {
+            let server = self.0.read();
+            if server.ignored(instance) {
+                info!("ignored component {instance}");
+                return None;
+            }
+            if server.has_instance::<C>(instance) { return None; }
+            server.get_config::<C>(instance)
+        };
+        let result = C::init(self.provider_handle::<C>(instance), config);
+        let failure = result.as_ref().err().map(ToString::to_string);
+        let mut server = self.0.write();
+        if let Some(e) = &failure {
+            error!("couldn't initialize component {instance}: {e}");
+            server.forget_dependencies(&Node::new(instance, TypeId::of::<C>()));
+        }
+        server.set_init_failure(instance, failure);
+        drop(server);
+        result.ok()
     }
     /// Adds a new [`Component`] (& dependencies) to the server.
     ///
+    /// One instance is added for every name in [`Config::instances`](crate::Config::instances)
+    /// (or a single one named after its id).
+    ///
     /// # Note
     /// Notification providers need to be registered via [`Self::add_notification_provider`],
     /// so that the server knows that they can send notifications.
-    // #[expect(clippy::must_use_candidate, reason="returning something here is more just for chaining.")]
+    #[expect(clippy::must_use_candidate, reason="returning something here is more just for chaining.")]
     pub fn add_component<C: Component>(&self) -> &Self {
-        if self.0.read().ignored::<C>() {
-            info!("ignored component {}", C::ID);
-            return self;
+        let instances = self.0.read().instances::<C>();
+        for instance in instances {
+            let Some(component) = self.init_instance::<C>(&instance) else { continue; };
+            self.0.write().add_component::<C>(&instance, component);
         }
-        let config = self.0.read().get_config::<C>();
-        let component = match C::init(self.provider_handle::<C>(), config) {
-            Ok(v) => v,
-            Err(e) => {
-                error!("couldn't initialize component {}: {e}", C::ID);
-                return self
-            }
-        };
-        self.0.write().add_component::<C>(component);
         self
     }
-    /// Removes a component (& dependant components) from the server.
+    /// Removes a component (all instances & dependant components) from the server.
     #[expect(clippy::must_use_candidate, reason="returning something here is more just for chaining.")]
     pub fn remove_component<C: Component>(&self) -> &Self {
-        self.0.write().remove_component(TypeId::of::<C>());
+        let mut server = self.0.write();
+        for instance in server.loaded_instances::<C>() {
+            server.remove_component(&instance, TypeId::of::<C>());
+        }
+        drop(server);
         self
     }
-    /// Adds a new [`NotificationProvider`] to the server.
-    // #[expect(clippy::must_use_candidate, reason="returning something here is more just for chaining.")]
+    /// Adds a new [`NotificationProvider`] (one instance per configured name) to the server.
+    #[expect(clippy::must_use_candidate, reason="returning something here is more just for chaining.")]
     pub fn add_notification_provider<P: NotificationProvider>(&self) -> &Self {
-        if self.0.read().ignored::<P>() {
-            info!("ignored component {}", P::ID);
-            return self;
+        let instances = self.0.read().instances::<P>();
+        for instance in instances {
+            let Some(provider) = self.init_instance::<P>(&instance) else { continue; };
+            self.0.write().add_notification_provider::<P>(&instance, provider);
         }
-        let config = self.0.read().get_config::<P>();
-        let provider = match P::init(self.provider_handle::<P>(), config) {
-            Ok(v) => v,
-            Err(e) => {
-                error!("couldn't initialize component {}: {e}", P::ID);
-                return self
-            }
-        };
-        self.0.write().add_notification_provider::<P>(provider);
+        super::spawn_dispatchers(&self.0);
         self
     }
-    /// checks the config, returning the deserialized config if it exists or the error if it doesn't.
+    /// Adds a new [`ReliableNotificationProvider`] to the server.
+    ///
+    /// Other than [`Self::add_notification_provider`] this also allows the server to send out
+    /// deliveries of the provider that were queued before a restart.
+    #[expect(clippy::must_use_candidate, reason="returning something here is more just for chaining.")]
+    pub fn add_reliable_notification_provider<P: ReliableNotificationProvider>(&self) -> &Self {
+        self.0.write().register_delivery_handler::<P>();
+        self.add_notification_provider::<P>()
+    }
+    /// checks the config of the given instance of the component, returning the deserialized config
+    /// if it exists or the error if it doesn't.
     #[must_use]
-    pub fn check_config<C: Component>(&self) -> Option<Result<C::Config, toml::de::Error>> {
-        self.0.read().check_config::<C>()
+    pub fn check_config<C: Component>(&self, instance: &str) -> Option<Result<C::Config, toml::de::Error>> {
+        self.0.read().check_config::<C>(instance)
     }
-    /// reload the config from the config file.
+    /// Adds all components of the registry to the server (in the order they were registered).
+    ///
+    /// The registry is kept, so that components that are no longer ignored after reloading the
+    /// config (see [`Self::reload_config`]) are added as well.
     #[expect(clippy::must_use_candidate, reason="returning something here is more just for chaining.")]
-    pub fn reload_config(&self) -> &Self {
-        self.0.write().reload_config();
+    pub fn add_components(&self, registry: ComponentRegistry) -> &Self {
+        self.0.write().set_registry(registry);
+        self.add_missing_components();
         self
     }
+    fn add_missing_components(&self) {
+        // NOTE: collecting first, so that the lock isn't held while initializing the components.
+        let missing = self.0.read().missing_components();
+        for registration in missing {
+            debug!("adding registered component {}", registration.id);
+            (registration.add)(self);
+        }
+    }
+    /// reload the config from the config file.
+    ///
+    /// Reconfigures the components whose config changed, removes the ones that are now ignored and
+    /// adds the registered ones (see [`Self::add_components`]) that are no longer ignored.
+    ///
+    /// If the new config (or the config of any component) is invalid or any component can't be
+    /// reconfigured with it, the previous config is kept (and components that were already
+    /// reconfigured are reconfigured with it again).
+    #[expect(clippy::must_use_candidate, reason="the report is also logged, so it can be ignored.")]
+    pub fn reload_config(&self) -> ReloadReport {
+        let mut report = self.0.write().reload_config();
+        if report.applied {
+            let (loaded, missing) = {
+                let server = self.0.read();
+                (server.loaded_instance_names(), server.missing_instance_names())
+            };
+            self.add_missing_components();
+            let added = self.0.read().loaded_instance_names();
+            for instance in added.difference(&loaded) {
+                report.components.insert(instance.clone(), ReloadResult::Added);
+            }
+            for instance in missing.into_iter().filter(|instance| !added.contains(instance)) {
+                let failure = self.0.read().init_failure(&instance)
+                    .unwrap_or_else(|| "couldn't be initialized".to_string());
+                report.components.insert(instance, ReloadResult::Failed(failure));
+            }
+        }
+        report.log();
+        self.0.write().set_last_reload(report.clone());
+        report
+    }
+    /// Returns the outcome of the last reload of the config (if it was reloaded at all).
+    #[must_use]
+    pub fn last_reload(&self) -> Option<ReloadReport> {
+        self.0.read().get_last_reload()
+    }
+    /// The dependencies between the loaded components (see
+    /// [`ComponentHandle::add_component_dependency`]), including the order they are initialized
+    /// and shut down in.
+    #[must_use]
+    pub fn dependency_graph(&self) -> DependencyGraph {
+        self.0.read().dependency_graph()
+    }
+    /// How well each notification provider keeps up with the published notifications (by
+    /// instance).
+    ///
+    /// Notifications are published on a bus instead of being handed to the providers directly, so
+    /// that a slow provider doesn't hold up whoever changed the state. Every provider consumes its
+    /// own bounded queue (see [`Config::bus`](crate::Config::bus)) from its own task.
+    #[must_use]
+    pub fn notification_bus(&self) -> BTreeMap<String, SubscriberMetrics> {
+        self.0.read().notification_bus_metrics()
+    }
     /// retrieves a reference to a component from the server and applies the map function to it.
     ///
+    /// If the component has multiple instances, the one named after its id (or the first one) is
+    /// used.
+    ///
     /// # Note
     /// This is this way because the actual server is behind an [`Arc`] reference and if we didn't
     /// do it this way there would be lifetime issues.
     // NOTE: I could build a custom struct that houses the reference to the component & the lock.
     //       Might do that in the future.
     pub fn component_map<C: Component, F: FnOnce(Option<&C>) -> V, V>(&self, func: F) -> V {
-        func(self.0.read().get_component())
+        func(self.0.read().get_component().as_deref())
     }
     /// retrieves a mutable reference to a component from the server and applies the map function to it.
     ///
+    /// If the component has multiple instances, the one named after its id (or the first one) is
+    /// used.
+    ///
     /// # Note
     /// This is this way because the actual server is behind an [`Arc`] reference and if we didn't
     /// do it this way there would be lifetime issues.
     // NOTE: I could build a custom struct that houses the reference to the component & the lock.
     //       Might do that in the future.
     pub fn component_map_mut<C: Component, F: FnOnce(Option<&mut C>) -> V, V>(&self, func: F) -> V {
-        func(self.0.write().get_component_mut())
+        func(self.0.write().get_component_mut().as_deref_mut())
     }
-    fn provider_handle<P: Component>(&self) -> ComponentHandle {
-        ComponentHandle::new::<P>(self.0.clone())
+    fn provider_handle<P: Component>(&self, instance: &str) -> ComponentHandle {
+        ComponentHandle::new::<P>(self.0.clone(), instance)
+    }
+    /// Returns an immutable snapshot of all elements and their states.
+    ///
+    /// This neither locks the server nor copies any state (see [`StatesSnapshot`]), so it is cheap
+    /// enough to be called for every request.
+    #[must_use]
+    pub fn states(&self) -> Arc<StatesSnapshot> {
+        self.1.load()
     }
     /// Returns a copy of all elements and their states.
+    ///
+    /// Prefer [`Self::states`], which doesn't copy them.
     #[must_use]
     pub fn get_states(&self) -> HashMap<String, State> {
-        self.0.read().get_states()
+        self.states().to_map()
+    }
+    /// Returns all incidents (open & closed).
+    #[must_use]
+    pub fn get_incidents(&self) -> Vec<Incident> {
+        self.0.read().get_incidents()
+    }
+    /// Returns the incident with the given id.
+    #[must_use]
+    pub fn get_incident(&self, id: u64) -> Option<Incident> {
+        self.0.read().get_incident(id)
+    }
+    /// Marks the incident as acknowledged by the given person.
+    ///
+    /// Returns the updated incident or `None` if there is no incident with the given id.
+    #[expect(clippy::must_use_candidate, reason="the returned incident is just for convenience.")]
+    pub fn acknowledge_incident(&self, id: u64, by: &str) -> Option<Incident> {
+        self.0.write().acknowledge_incident(id, by)
+    }
+    /// Adds a note to the incident.
+    ///
+    /// Returns the updated incident or `None` if there is no incident with the given id.
+    #[expect(clippy::must_use_candidate, reason="the returned incident is just for convenience.")]
+    pub fn add_incident_note(&self, id: u64, author: &str, text: &str) -> Option<Incident> {
+        self.0.write().add_incident_note(id, author, text)
+    }
+    /// Returns the pending deliveries and the dead letters (deliveries that ultimately failed).
+    #[must_use]
+    pub fn get_deliveries(&self) -> (Vec<Delivery>, Vec<Delivery>) {
+        self.0.read().get_deliveries()
+    }
+    /// Moves the dead letter with the given id back into the delivery queue.
+    ///
+    /// Returns the delivery or `None` if there is no dead letter with the given id.
+    #[expect(clippy::must_use_candidate, reason="the returned delivery is just for convenience.")]
+    pub fn retry_delivery(&self, id: u64) -> Option<Delivery> {
+        self.0.write().retry_delivery(id)
+    }
+    /// Computes the [`Uptime`] of the given element in the given time frame from the recorded history.
+    #[must_use]
+    pub fn get_uptime(&self, element_id: &str, since: DateTime<Utc>, until: DateTime<Utc>) -> Uptime {
+        self.0.read().get_uptime(element_id, since, until)
+    }
+    /// Returns the recorded history of the given element (or of all elements if `None`) in the
+    /// given time frame (both ends inclusive, `None` meaning unbounded).
+    #[must_use]
+    pub fn get_history(&self, element_id: Option<&str>, since: Option<DateTime<Utc>>, until: Option<DateTime<Utc>>) -> Vec<HistoryEntry> {
+        self.0.read().get_history(element_id, since, until)
     }
 }
 impl axum::handler::Handler<(), ()> for ServerHandle {
@@ -1,10 +1,14 @@
-use super::Server;
-use crate::notification_provider::NotificationProvider;
+use super::{Server, ServerHandle};
+use super::backend::{DeliveryInbox, QueuedDelivery, RemovalInbox};
+use crate::notification_provider::{NotificationProvider, ReliableNotificationProvider};
 use crate::state::AttributeValue;
-use crate::{Component, State};
+use crate::dependency::{DependencyError, DependencyGraph, Node};
+use crate::snapshot::{PublishedStates, StatesSnapshot};
+use crate::{Component, Confirmation, ReloadReport, Delivery, ElementUpdate, Health, HistoryEntry, Incident, State, SubscriberMetrics, Uptime};
+use chrono::{DateTime, Utc};
 use parking_lot::RwLock;
 use std::any::TypeId;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 
 #[derive(Clone)]
@@ -16,103 +20,279 @@ use std::sync::Arc;
 /// sent by the wrong component.
 pub struct ComponentHandle {
     backend: Arc<RwLock<Server>>,
-    id: &'static str,
+    instance: String,
     type_id: TypeId,
+    delivery_inbox: DeliveryInbox,
+    removal_inbox: RemovalInbox,
+    states: PublishedStates,
 }
 impl ComponentHandle {
-    pub(super) fn new<P: Component>(backend: Arc<RwLock<Server>>) -> Self {
+    pub(super) fn new<P: Component>(backend: Arc<RwLock<Server>>, instance: &str) -> Self {
+        let (delivery_inbox, removal_inbox, states) = {
+            let backend = backend.read();
+            (backend.delivery_inbox(), backend.removal_inbox(), backend.published_states())
+        };
         Self { 
             backend, 
-            id: P::ID, 
-            type_id: TypeId::of::<P>() 
+            instance: instance.to_string(), 
+            type_id: TypeId::of::<P>(),
+            delivery_inbox,
+            removal_inbox,
+            states,
         }
     }
-    /// Add a [`NotificationProvider`] dependency.
+    /// The name of the instance of the component this handle belongs to.
     ///
-    /// # Note
-    /// There is no check for recursive dependencies. Do not use recursive dependencies.
-    pub fn add_notification_provider_dependency<P: NotificationProvider>(&self) {
-        if self.backend.read().ignored::<P>() {
-            error!("ignored dependency {} for {}!", P::ID, self.id);
-            return;
-        }
-        if !self.backend.read().has_component::<P>() {
-            let handle = Self::new::<P>(self.backend.clone());
-            let config = self.backend.read().get_config::<P>();
-            let provider = match P::init(handle, config) {
-                Ok(v) => v,
+    /// This is the id of the component unless it has [named instances](crate::Config::instances).
+    #[must_use]
+    pub fn instance(&self) -> &str {
+        &self.instance
+    }
+    fn node(&self) -> Node {
+        Node::new(&self.instance, self.type_id)
+    }
+    /// Declares the dependency on `C` and initializes all instances of it that aren't loaded yet.
+    fn init_dependency<C: Component>(&self) -> Result<Vec<(String, C)>, DependencyError> {
+        self.backend.write().declare_dependency::<C>(&self.node())?;
+        let instances = self.backend.read().instances::<C>();
+        let mut initialized = Vec::new();
+        for instance in instances {
+            let config = {
+                let backend = self.backend.read();
+                if backend.ignored(&instance) || backend.has_instance::<C>(&instance) { continue; }
+                backend.get_config::<C>(&instance)
+            };
+            match C::init(Self::new::<C>(self.backend.clone(), &instance), config) {
+                Ok(v) => {
+                    self.backend.write().set_init_failure(&instance, None);
+                    initialized.push((instance, v));
+                }
                 Err(e) => {
-                    error!("error initializing component: {e}; skipping...");
-                    return;
+                    error!("error initializing component {instance}: {e}; skipping...");
+                    let mut backend = self.backend.write();
+                    backend.forget_dependencies(&Node::new(&instance, TypeId::of::<C>()));
+                    backend.set_init_failure(&instance, Some(e.to_string()));
                 }
-            };
-            self.backend.write().add_notification_provider(provider);
+            }
         }
-        self.backend.write().add_notification_provider_dependency::<P>(
-            self.type_id,
-        );
+        Ok(initialized)
     }
-    /// Add a [`Component`] dependency.
+    /// Add a [`NotificationProvider`] dependency.
     ///
-    /// # Note
-    /// There is no check for recursive dependencies. Do not use recursive dependencies.
-    pub fn add_component_dependency<C: Component>(&self) {
-        if self.backend.read().ignored::<C>() {
-            error!("ignored dependency {} for {}!", C::ID, self.id);
-            return;
+    /// All instances of the provider are required by this component, so they are initialized (if
+    /// they aren't loaded yet) and removing any of them removes this component as well.
+    ///
+    /// # Errors
+    /// If the provider is ignored, couldn't be initialized at all or (directly or indirectly)
+    /// depends on this component.
+    pub fn add_notification_provider_dependency<P: NotificationProvider>(&self) -> Result<(), DependencyError> {
+        let initialized = self.init_dependency::<P>()?;
+        let mut backend = self.backend.write();
+        for (instance, provider) in initialized {
+            backend.add_notification_provider(&instance, provider);
         }
-        if !self.backend.read().has_component::<C>() {
-            let handle = Self::new::<C>(self.backend.clone());
-            let component = match C::init(handle, self.backend.read().get_config::<C>()) {
-                Ok(v) => v,
-                Err(e) => {
-                    error!("error initializing component: {e}; skipping...");
-                    return;
-                }
-            };
-            self.backend.write().add_component(component);
+        let result = backend.settle_dependency::<P>(&self.node())
+            .map(|()| backend.enable_notification_provider::<P>());
+        drop(backend);
+        super::spawn_dispatchers(&self.backend);
+        result
+    }
+    /// Add a [`Component`] dependency.
+    ///
+    /// All instances of the component are required by this component, so they are initialized (if
+    /// they aren't loaded yet) and removing any of them removes this component as well.
+    ///
+    /// # Errors
+    /// If the component is ignored, couldn't be initialized at all or (directly or indirectly)
+    /// depends on this component.
+    pub fn add_component_dependency<C: Component>(&self) -> Result<(), DependencyError> {
+        let initialized = self.init_dependency::<C>()?;
+        let mut backend = self.backend.write();
+        for (instance, component) in initialized {
+            backend.add_component(&instance, component);
         }
-        self.backend.write().add_component_dependency::<C>(
-            self.type_id,
-        );
+        backend.settle_dependency::<C>(&self.node())
+    }
+    /// The dependencies between the loaded components (see [`ServerHandle::dependency_graph`](crate::Server::dependency_graph)).
+    #[must_use]
+    pub fn dependency_graph(&self) -> DependencyGraph {
+        self.backend.read().dependency_graph()
+    }
+    /// How well each notification provider keeps up with the published notifications (see
+    /// [`ServerHandle::notification_bus`](crate::Server::notification_bus)).
+    #[must_use]
+    pub fn notification_bus(&self) -> BTreeMap<String, SubscriberMetrics> {
+        self.backend.read().notification_bus_metrics()
     }
     /// retrieves a reference to a component from the server and applies the map function to it.
     ///
+    /// If the component has multiple instances, the one named after its id (or the first one) is
+    /// used.
+    ///
     /// # Note
     /// This is this way because the actual server is behind an [`Arc`] reference and if we didn't
     /// do it this way there would be lifetime issues.
     // NOTE: I could build a custom struct that houses the reference to the component & the lock.
     //       Might do that in the future.
     pub fn component_map<C: Component, F: FnOnce(Option<&C>) -> V, V>(&self, func: F) -> V {
-        func(self.backend.read().get_component())
+        func(self.backend.read().get_component().as_deref())
     }
     /// retrieves a mutable reference to a component from the server and applies the map function to it.
     ///
+    /// If the component has multiple instances, the one named after its id (or the first one) is
+    /// used.
+    ///
     /// # Note
     /// This is this way because the actual server is behind an [`Arc`] reference and if we didn't
     /// do it this way there would be lifetime issues.
     // NOTE: I could build a custom struct that houses the reference to the component & the lock.
     //       Might do that in the future.
     pub fn component_map_mut<C: Component, F: FnOnce(Option<&mut C>) -> V, V>(&self, func: F) -> V {
-        func(self.backend.write().get_component_mut())
+        func(self.backend.write().get_component_mut().as_deref_mut())
     }
     /// Changes the online state of an element.
     ///
     /// # Note
     /// Please make sure that the state actually changes via [`Self::get_online_state`].
     /// Calling this function without checking the online state first is a lot slower.
+    ///
+    /// If the element is [degraded](crate::HealthStatus::Degraded) and `status` is `true`, its
+    /// health is kept. Use [`Self::change_health`] for more fine-grained control.
     pub fn change_online_state(&self, element_id: &str, status: bool) {
-        self.backend.write().online_status_changed(self.id, element_id, status);
+        self.backend.write().online_status_changed(&self.instance, element_id, status);
+    }
+    /// Changes the health of an element.
+    ///
+    /// Does nothing if the element already has this health.
+    pub fn change_health(&self, element_id: &str, health: Health) {
+        self.backend.write().health_changed(&self.instance, element_id, health);
+    }
+    /// Changes the health of an element once a change of its online status is confirmed.
+    ///
+    /// Should be called with every observation (including those that match the current status), so
+    /// that only consecutive observations are counted. See [`Confirmation`] for more infos.
+    pub fn change_health_confirmed(&self, element_id: &str, health: Health, confirmation: Confirmation) {
+        self.update_element(element_id, |update| {
+            update.set_health(health).confirm_with(confirmation);
+        });
+    }
+    /// Removes an element (and all its attributes) from the server.
+    ///
+    /// Does nothing if the element doesn't exist.
+    ///
+    /// # Note
+    /// The server is locked during [`Component::reconfigure`], so calling this in there would
+    /// deadlock. Use [`Self::queue_element_removal`] instead.
+    pub fn remove_element(&self, element_id: &str) {
+        self.backend.write().remove_element(&self.instance, element_id);
+    }
+    /// Removes an element (like [`Self::remove_element`]) once [`Component::reconfigure`] returned,
+    /// without the server being unlocked in between. Meant to be called in `reconfigure`, so that
+    /// nothing (e.g. a task of the component that is still running) can change the element before
+    /// it is removed.
+    ///
+    /// Removals queued outside of `reconfigure` are applied right away if the server isn't locked
+    /// (or else within a few seconds).
+    pub fn queue_element_removal(&self, element_id: &str) {
+        self.removal_inbox.lock().push((self.instance.clone(), element_id.to_string()));
+        // during `reconfigure` the server is locked, so this only applies removals queued elsewhere.
+        if let Some(mut backend) = self.backend.try_write() {
+            backend.drain_removal_inbox();
+        }
     }
     /// Retrieves the online state of an element.
     #[must_use]
     pub fn get_online_state(&self, element_id: &str) -> Option<bool> {
-        self.backend.read().get_status(element_id)
+        self.states.load().get(element_id).map(State::online)
+    }
+    /// Retrieves the health of an element.
+    #[must_use]
+    pub fn get_health(&self, element_id: &str) -> Option<Health> {
+        self.states.load().get(element_id).map(|state| state.health.clone())
+    }
+    /// Returns an immutable snapshot of all elements and their states (see
+    /// [`ServerHandle::states`](crate::Server::states)).
+    #[must_use]
+    pub fn states(&self) -> Arc<StatesSnapshot> {
+        self.states.load()
     }
     /// Returns a copy of all elements and their states.
+    ///
+    /// Prefer [`Self::states`], which doesn't copy them.
     #[must_use]
     pub fn get_states(&self) -> HashMap<String, State> {
-        self.backend.read().get_states()
+        self.states().to_map()
+    }
+    /// Whether the element is currently under maintenance.
+    #[must_use]
+    pub fn in_maintenance(&self, element_id: &str) -> bool {
+        self.backend.read().active_maintenance(element_id).is_some()
+    }
+    /// Returns all incidents (open & closed).
+    #[must_use]
+    pub fn get_incidents(&self) -> Vec<Incident> {
+        self.backend.read().get_incidents()
+    }
+    /// Returns the incident with the given id.
+    #[must_use]
+    pub fn get_incident(&self, id: u64) -> Option<Incident> {
+        self.backend.read().get_incident(id)
+    }
+    /// Marks the incident as acknowledged by the given person.
+    ///
+    /// Returns the updated incident or `None` if there is no incident with the given id.
+    #[expect(clippy::must_use_candidate, reason="the returned incident is just for convenience.")]
+    pub fn acknowledge_incident(&self, id: u64, by: &str) -> Option<Incident> {
+        self.backend.write().acknowledge_incident(id, by)
+    }
+    /// Adds a note to the incident.
+    ///
+    /// Returns the updated incident or `None` if there is no incident with the given id.
+    #[expect(clippy::must_use_candidate, reason="the returned incident is just for convenience.")]
+    pub fn add_incident_note(&self, id: u64, author: &str, text: &str) -> Option<Incident> {
+        self.backend.write().add_incident_note(id, author, text)
+    }
+    /// Queues a delivery of the [`ReliableNotificationProvider`] `P` (which should be the component
+    /// this handle belongs to).
+    ///
+    /// The delivery is sent out via [`ReliableNotificationProvider::deliver`] as soon as possible
+    /// and retried if that fails.
+    ///
+    /// # Note
+    /// Other than most methods of this handle, this doesn't lock the server, so it is safe to call
+    /// in [`NotificationProvider::notify`].
+    pub fn queue_delivery<P: ReliableNotificationProvider>(&self, delivery: &P::Delivery) {
+        let payload = match serde_json::to_value(delivery) {
+            Ok(v) => v,
+            Err(e) => {
+                error!("couldn't serialize delivery of {}: {e}", P::ID);
+                return;
+            }
+        };
+        self.delivery_inbox.lock().push(QueuedDelivery::new::<P>(&self.instance, payload));
+    }
+    /// Returns the pending deliveries and the dead letters (deliveries that ultimately failed).
+    #[must_use]
+    pub fn get_deliveries(&self) -> (Vec<Delivery>, Vec<Delivery>) {
+        self.backend.read().get_deliveries()
+    }
+    /// Moves the dead letter with the given id back into the delivery queue.
+    ///
+    /// Returns the delivery or `None` if there is no dead letter with the given id.
+    #[expect(clippy::must_use_candidate, reason="the returned delivery is just for convenience.")]
+    pub fn retry_delivery(&self, id: u64) -> Option<Delivery> {
+        self.backend.write().retry_delivery(id)
+    }
+    /// Computes the [`Uptime`] of the given element in the given time frame from the recorded history.
+    #[must_use]
+    pub fn get_uptime(&self, element_id: &str, since: DateTime<Utc>, until: DateTime<Utc>) -> Uptime {
+        self.backend.read().get_uptime(element_id, since, until)
+    }
+    /// Returns the recorded history of the given element (or of all elements if `None`) in the
+    /// given time frame (both ends inclusive, `None` meaning unbounded).
+    #[must_use]
+    pub fn get_history(&self, element_id: Option<&str>, since: Option<DateTime<Utc>>, until: Option<DateTime<Utc>>) -> Vec<HistoryEntry> {
+        self.backend.read().get_history(element_id, since, until)
     }
     /// Changes the attribute of an element.
     ///
@@ -121,12 +301,29 @@ impl ComponentHandle {
     /// Calling this function without checking the online state first is a lot slower and sends
     /// unnecessary notifications.
     pub fn change_attribute(&self, element_id: &str, attribute_id: &str, value: AttributeValue) {
-        self.backend.write().attribute_change(self.id, element_id, attribute_id, value);
+        self.backend.write().attribute_change(&self.instance, element_id, attribute_id, value);
+    }
+    /// Sets an attribute that is computed from other data (e.g. the uptime of the element) or
+    /// removes it if `value` is `None`. Does nothing if the element doesn't exist.
+    ///
+    /// Unlike [`Self::change_attribute`], this neither notifies anyone nor records the change in
+    /// the history, as the value would change with every computation.
+    pub fn set_computed_attribute(&self, element_id: &str, attribute_id: &str, value: Option<AttributeValue>) {
+        self.backend.write().computed_attribute_change(element_id, attribute_id, value);
+    }
+    /// Applies multiple changes to an element at once.
+    ///
+    /// All changes made to the [`ElementUpdate`] in `build` are applied while the server is locked
+    /// once and the resulting notifications are sent out as a single batch. Changes that don't change
+    /// anything are skipped.
+    pub fn update_element<R>(&self, element_id: &str, build: impl FnOnce(&mut ElementUpdate) -> R) -> R {
+        self.backend.write().update_element(&self.instance, element_id, build)
     }
     /// Retrieves the given attribute of an element.
     #[must_use]
     pub fn get_attribute(&self, element_id: &str, attribute_id: &str) -> Option<AttributeValue> {
-        self.backend.read().get_attribute(element_id, attribute_id)
+        self.states.load().get(element_id)?
+            .attributes.get(attribute_id).cloned()
     }
     #[expect(clippy::doc_overindented_list_items, reason="this is for easier reading while editing.")]
     /// Deletes an attribute for an element.
@@ -139,12 +336,20 @@ impl ComponentHandle {
     ///                  any subattributes to set this, as it currently has to check every single
     ///                  attribute of the element.
     pub fn delete_attribute(&self, element_id: &str, attribute_id: &str, exact: bool) {
-        self.backend.write().delete_attribute(element_id, attribute_id, self.id, exact);
+        self.backend.write().delete_attribute(element_id, attribute_id, &self.instance, exact);
     }
-    /// reload the config from the config file.
-    #[expect(clippy::must_use_candidate, reason="returning something here is more just for chaining.")]
-    pub fn reload_config(&self) -> &Self {
-        self.backend.write().reload_config();
-        self
+    /// reload the config from the config file (see [`ServerHandle::reload_config`](crate::Server::reload_config)).
+    ///
+    /// # Note
+    /// The server is locked during [`Component::reconfigure`], so calling this in there would
+    /// deadlock.
+    #[expect(clippy::must_use_candidate, reason="the report is also logged, so it can be ignored.")]
+    pub fn reload_config(&self) -> ReloadReport {
+        ServerHandle::from_backend(self.backend.clone()).reload_config()
+    }
+    /// Returns the outcome of the last reload of the config (if it was reloaded at all).
+    #[must_use]
+    pub fn last_reload(&self) -> Option<ReloadReport> {
+        self.backend.read().get_last_reload()
     }
 }
@@ -0,0 +1,93 @@
+use std::io::Write;
+use std::path::Path;
+use crate::history::{History, HistoryEntry, HistoryWrite};
+
+/// Loads persisted data (e.g. the element states) from the given JSON file.
+///
+/// A missing or invalid file results in the default value, `what` is only used for logging.
+pub(super) fn load_json<T: Default + serde::de::DeserializeOwned>(path: &Path, what: &str) -> T {
+    let content = match std::fs::read_to_string(path) {
+        Ok(v) => v,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            info!("no persisted {what} found at `{}`", path.to_string_lossy());
+            return T::default();
+        }
+        Err(e) => {
+            error!("couldn't read persisted {what} from `{}`: {e}", path.to_string_lossy());
+            return T::default();
+        }
+    };
+    match serde_json::from_str::<T>(&content) {
+        Ok(v) => {
+            info!("restored {what} from `{}`", path.to_string_lossy());
+            v
+        },
+        Err(e) => {
+            error!("persisted {what} in `{}` are invalid: {e}", path.to_string_lossy());
+            T::default()
+        }
+    }
+}
+/// Writes the data as JSON to the given file.
+///
+/// The data is first written to a temporary file which then replaces the old one, so that a
+/// crash while writing never leaves a half-written file behind.
+pub(super) fn save_json<T: serde::Serialize>(path: &Path, value: &T) -> std::io::Result<()> {
+    let serialized = serde_json::to_string(value)?;
+    let tmp_path = path.with_added_extension("tmp");
+    std::fs::write(&tmp_path, serialized)?;
+    std::fs::rename(&tmp_path, path)?;
+    trace!("saved `{}`", path.to_string_lossy());
+    Ok(())
+}
+/// Loads the history from the given file.
+///
+/// Invalid lines are skipped, a missing or unreadable file results in an empty history.
+pub(super) fn load_history(path: &Path) -> History {
+    let content = match std::fs::read_to_string(path) {
+        Ok(v) => v,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            info!("no history found at `{}`", path.to_string_lossy());
+            return History::new();
+        }
+        Err(e) => {
+            error!("couldn't read history from `{}`: {e}", path.to_string_lossy());
+            return History::new();
+        }
+    };
+    let entries = content.lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str::<HistoryEntry>(line)
+            .map_err(|e| warn!("skipping invalid history entry `{line}`: {e}"))
+            .ok())
+        .collect::<Vec<_>>();
+    info!("restored {} history entries from `{}`", entries.len(), path.to_string_lossy());
+    History::from_entries(entries)
+}
+/// Writes the changes to the history to the given file.
+pub(super) fn save_history(path: &Path, write: &HistoryWrite) -> std::io::Result<()> {
+    fn write_entries(mut file: impl Write, entries: &[HistoryEntry]) -> std::io::Result<()> {
+        for entry in entries {
+            serde_json::to_writer(&mut file, entry)?;
+            file.write_all(b"\n")?;
+        }
+        file.flush()
+    }
+    match write {
+        HistoryWrite::Append(entries) => {
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?;
+            write_entries(std::io::BufWriter::new(file), entries)?;
+            trace!("appended {} history entries to `{}`", entries.len(), path.to_string_lossy());
+        }
+        HistoryWrite::Rewrite(entries) => {
+            let tmp_path = path.with_added_extension("tmp");
+            write_entries(std::io::BufWriter::new(std::fs::File::create(&tmp_path)?), entries)?;
+            std::fs::rename(&tmp_path, path)?;
+            trace!("rewrote history `{}` with {} entries", path.to_string_lossy(), entries.len());
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use arc_swap::ArcSwap;
+use crate::state::State;
+
+/// The states of all elements (by element id) in a persistent map, so that cloning it is cheap and
+/// changing a clone only copies what changed.
+pub(crate) type States = imbl::HashMap<String, State>;
+
+#[derive(Clone, Debug, Default)]
+/// An immutable snapshot of the states of all elements (see
+/// [`ServerHandle::states`](crate::Server::states)).
+///
+/// Taking a snapshot neither locks the server nor copies any state: every change publishes a new
+/// version, which shares everything it didn't change with the previous one. A snapshot therefore
+/// never changes, no matter how long it is kept around.
+pub struct StatesSnapshot {
+    version: u64,
+    states: States,
+}
+impl StatesSnapshot {
+    /// The version of the states. Every change of the states increases it.
+    #[must_use]
+    pub const fn version(&self) -> u64 {
+        self.version
+    }
+    /// The state of the element (if it exists).
+    #[must_use]
+    pub fn get(&self, element_id: &str) -> Option<&State> {
+        self.states.get(element_id)
+    }
+    /// Whether the element exists.
+    #[must_use]
+    pub fn contains(&self, element_id: &str) -> bool {
+        self.states.contains_key(element_id)
+    }
+    /// All elements and their states (in no particular order).
+    pub fn iter(&self) -> impl Iterator<Item=(&String, &State)> {
+        self.states.iter()
+    }
+    /// The ids of all elements (in no particular order).
+    pub fn ids(&self) -> impl Iterator<Item=&String> {
+        self.states.keys()
+    }
+    /// The number of elements.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+    /// Whether there are no elements.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+    /// Copies all elements and their states into a [`HashMap`].
+    #[must_use]
+    pub fn to_map(&self) -> HashMap<String, State> {
+        self.states.iter()
+            .map(|(id, state)| (id.clone(), state.clone()))
+            .collect()
+    }
+}
+
+#[derive(Clone)]
+/// The latest snapshot of the states, which is read without locking the server.
+///
+/// Only the server publishes new versions (while it is write-locked), so versions never go back.
+pub(crate) struct PublishedStates(Arc<ArcSwap<StatesSnapshot>>);
+impl PublishedStates {
+    pub(crate) fn new(states: &States) -> Self {
+        Self(Arc::new(ArcSwap::from_pointee(StatesSnapshot { version: 0, states: states.clone() })))
+    }
+    /// Publishes the states as the next version.
+    pub(crate) fn publish(&self, states: &States) {
+        let version = self.0.load().version + 1;
+        self.0.store(Arc::new(StatesSnapshot { version, states: states.clone() }));
+    }
+    /// The latest snapshot.
+    pub(crate) fn load(&self) -> Arc<StatesSnapshot> {
+        self.0.load_full()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshots_dont_change_after_publishing() {
+        let mut states = States::new();
+        states.insert("a".to_string(), State::new());
+        let published = PublishedStates::new(&states);
+        let first = published.load();
+        assert_eq!(first.version(), 0);
+
+        states.insert("b".to_string(), State::new());
+        states.remove("a");
+        published.publish(&states);
+        let second = published.load();
+        assert_eq!(second.version(), 1);
+        assert!(first.contains("a") && !first.contains("b"));
+        assert!(!second.contains("a") && second.contains("b"));
+        assert_eq!(first.len(), 1);
+        assert_eq!(second.to_map().keys().collect::<Vec<_>>(), ["b"]);
+    }
+
+    #[test]
+    fn versions_increase_with_every_publish() {
+        let published = PublishedStates::new(&States::new());
+        for version in 1..=3 {
+            published.publish(&States::new());
+            assert_eq!(published.load().version(), version);
+            assert!(published.load().is_empty());
+        }
+    }
+}
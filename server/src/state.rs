@@ -4,31 +4,144 @@ use std::fmt::Formatter;
 #[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
 /// The state that an element has.
 pub struct State {
-    /// whether the element is currently online.
-    pub online: bool,
+    /// The health of the element.
+    #[serde(alias="online")]
+    pub health: Health,
     /// The attributes of the element.
-    pub attributes: HashMap<String, AttributeValue>
+    pub attributes: HashMap<String, AttributeValue>,
+    /// Whether the element keeps going online and offline.
+    ///
+    /// While an element is flapping, notifications about its health changes are suppressed.
+    #[serde(default)]
+    pub flapping: bool,
 }
 impl State {
-    /// creates a new offline state
+    /// creates a new state with unknown health.
     #[must_use]
     pub fn new() -> Self {
         Self {
-            online: false,
+            health: Health::default(),
             attributes: HashMap::new(),
+            flapping: false,
         }
     }
     /// creates a new state.
     #[must_use]
-    pub fn init(online: bool, attributes: HashMap<String, AttributeValue>) -> Self {
-        Self { online, attributes }
+    pub const fn init(health: Health, attributes: HashMap<String, AttributeValue>) -> Self {
+        Self { health, attributes, flapping: false }
     }
     /// creates a new state with the given online status.
     #[must_use]
     pub fn with_online(online: bool) -> Self {
+        Self::with_health(Health::from(online))
+    }
+    /// creates a new state with the given health.
+    #[must_use]
+    pub fn with_health(health: Health) -> Self {
         Self {
-            online,
+            health,
             attributes: HashMap::new(),
+            flapping: false,
+        }
+    }
+    /// whether the element is currently online (see [`Health::is_online`]).
+    #[must_use]
+    pub const fn online(&self) -> bool {
+        self.health.is_online()
+    }
+}
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(from="HealthRepr")]
+/// The health of an element.
+///
+/// For compatibility with components that only know whether an element is online, this can be
+/// converted from (and deserialized from) a `bool` (`true` being [`HealthStatus::Up`] and `false`
+/// being [`HealthStatus::Down`]).
+pub struct Health {
+    /// The overall status.
+    pub status: HealthStatus,
+    /// Why the element has this status (e.g. "slow response").
+    pub reason: Option<String>,
+}
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all="snake_case")]
+/// The overall status of an element.
+pub enum HealthStatus {
+    /// Everything is fine.
+    Up,
+    /// The element is online, but something isn't quite right (e.g. it is slow or full).
+    Degraded,
+    /// The element is offline.
+    Down,
+    #[default]
+    /// The status of the element isn't known.
+    Unknown,
+}
+impl Health {
+    /// creates a new health without a reason.
+    #[must_use]
+    pub const fn new(status: HealthStatus) -> Self {
+        Self { status, reason: None }
+    }
+    /// creates a new health with the given reason.
+    #[must_use]
+    pub fn with_reason(status: HealthStatus, reason: impl Into<String>) -> Self {
+        Self { status, reason: Some(reason.into()) }
+    }
+    /// whether the element is online ([`HealthStatus::Up`] or [`HealthStatus::Degraded`]).
+    #[must_use]
+    pub const fn is_online(&self) -> bool {
+        matches!(self.status, HealthStatus::Up | HealthStatus::Degraded)
+    }
+    /// The online status if it is known.
+    #[must_use]
+    pub const fn online(&self) -> Option<bool> {
+        match self.status {
+            HealthStatus::Up | HealthStatus::Degraded => Some(true),
+            HealthStatus::Down => Some(false),
+            HealthStatus::Unknown => None,
+        }
+    }
+}
+impl From<bool> for Health {
+    fn from(online: bool) -> Self {
+        Self::new(if online { HealthStatus::Up } else { HealthStatus::Down })
+    }
+}
+impl std::fmt::Display for HealthStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Up => write!(f, "up"),
+            Self::Degraded => write!(f, "degraded"),
+            Self::Down => write!(f, "down"),
+            Self::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+impl std::fmt::Display for Health {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.reason {
+            Some(reason) => write!(f, "{} ({reason})", self.status),
+            None => self.status.fmt(f),
+        }
+    }
+}
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+/// Allows deserializing [`Health`] from the old `bool` based online state.
+enum HealthRepr {
+    Online(bool),
+    Health {
+        status: HealthStatus,
+        #[serde(default)]
+        reason: Option<String>,
+    },
+}
+impl From<HealthRepr> for Health {
+    fn from(value: HealthRepr) -> Self {
+        match value {
+            HealthRepr::Online(online) => online.into(),
+            HealthRepr::Health { status, reason } => Self { status, reason },
         }
     }
 }
@@ -0,0 +1,31 @@
+//! Helpers shared by the tests of the crate.
+use std::path::{Path, PathBuf};
+
+/// A directory in the temp directory that is removed again when dropped.
+pub(crate) struct TempDir(PathBuf);
+impl TempDir {
+    /// Creates an empty directory (unique to the test process) with the given name.
+    pub(crate) fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("status-server-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).expect("couldn't create the temp directory");
+        Self(path)
+    }
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+    /// Writes the file (relative to the directory), creating its directories if needed.
+    pub(crate) fn write(&self, file: &str, content: &str) -> PathBuf {
+        let path = self.0.join(file);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).expect("couldn't create the directory");
+        }
+        std::fs::write(&path, content).expect("couldn't write the file");
+        path
+    }
+}
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
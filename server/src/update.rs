@@ -0,0 +1,76 @@
+use crate::confirmation::Confirmation;
+use crate::state::{AttributeValue, Health, State};
+
+/// Changes to a single element that are applied at once via
+/// [`ComponentHandle::update_element`](crate::ComponentHandle::update_element).
+///
+/// Changes that don't actually change anything (e.g. setting an attribute to the value it already
+/// has) are skipped, so there is no need to check the current state first.
+pub struct ElementUpdate<'a> {
+    state: Option<&'a State>,
+    pub(crate) health: Option<Health>,
+    pub(crate) confirmation: Option<Confirmation>,
+    pub(crate) attributes: Vec<AttributeUpdate>,
+}
+/// A single change to an attribute.
+pub(crate) enum AttributeUpdate {
+    Set(String, AttributeValue),
+    Delete {
+        id: String,
+        exact: bool,
+    },
+}
+impl<'a> ElementUpdate<'a> {
+    pub(crate) const fn new(state: Option<&'a State>) -> Self {
+        Self {
+            state,
+            health: None,
+            confirmation: None,
+            attributes: Vec::new(),
+        }
+    }
+    /// The state of the element before the update (`None` if it doesn't exist yet).
+    #[must_use]
+    pub const fn state(&self) -> Option<&State> {
+        self.state
+    }
+    /// Sets the online state of the element.
+    ///
+    /// If the element already has a health with the same online state (e.g. it is
+    /// [degraded](crate::HealthStatus::Degraded) and `online` is `true`), that health is kept.
+    pub fn set_online(&mut self, online: bool) -> &mut Self {
+        let current = self.health.as_ref().or(self.state.map(|state| &state.health));
+        if current.and_then(Health::online) != Some(online) {
+            self.health = Some(online.into());
+        }
+        self
+    }
+    /// Sets the health of the element.
+    pub fn set_health(&mut self, health: Health) -> &mut Self {
+        self.health = Some(health);
+        self
+    }
+    /// Only applies a change of the online status (set via [`Self::set_online`] or
+    /// [`Self::set_health`]) once it is confirmed according to the given [`Confirmation`].
+    ///
+    /// Until then, the element keeps its current health. An update that doesn't change the online
+    /// status (e.g. a failed check of an element that is already offline) interrupts the
+    /// observations of a pending change, so that only consecutive observations confirm it.
+    pub fn confirm_with(&mut self, confirmation: Confirmation) -> &mut Self {
+        self.confirmation = Some(confirmation);
+        self
+    }
+    /// Sets the attribute of the element.
+    pub fn set_attribute(&mut self, attribute_id: &str, value: AttributeValue) -> &mut Self {
+        self.attributes.push(AttributeUpdate::Set(attribute_id.to_string(), value));
+        self
+    }
+    /// Deletes the attribute (and all subattributes unless `exact` is set) of the element.
+    pub fn delete_attribute(&mut self, attribute_id: &str, exact: bool) -> &mut Self {
+        self.attributes.push(AttributeUpdate::Delete {
+            id: attribute_id.to_string(),
+            exact,
+        });
+        self
+    }
+}
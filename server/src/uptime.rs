@@ -0,0 +1,173 @@
+use chrono::{DateTime, Utc};
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+/// Availability statistics of a single element over a time frame.
+///
+/// Computed from the online status changes recorded in the history.
+pub struct Uptime {
+    /// The start of the time frame.
+    pub start: DateTime<Utc>,
+    /// The end of the time frame.
+    pub end: DateTime<Utc>,
+    /// The fraction (`0.0..=1.0`) of the time frame the element was online.
+    ///
+    /// Time where the online status of the element is unknown (e.g. before it was first seen) is not
+    /// taken into account. `None` if the status is unknown for the whole time frame.
+    pub uptime: Option<f32>,
+    /// How long the element was online.
+    pub online: chrono::Duration,
+    /// How long the element was offline.
+    pub offline: chrono::Duration,
+    /// The number of times the element went offline.
+    pub outages: usize,
+    /// The average time it took the element to come back online.
+    ///
+    /// Only takes outages into account that ended in the time frame.
+    pub mean_time_to_recovery: Option<chrono::Duration>,
+}
+impl Uptime {
+    /// The rolling time frames the uptime is usually reported for (name, length).
+    pub const ROLLING_WINDOWS: [(&'static str, chrono::Duration); 3] = [
+        ("24h", chrono::Duration::hours(24)),
+        ("7d", chrono::Duration::days(7)),
+        ("30d", chrono::Duration::days(30)),
+    ];
+    /// Computes the statistics for the time frame from the given online status changes (`None`
+    /// meaning the status became unknown).
+    ///
+    /// The changes are expected to be ordered by their timestamp.
+    pub(crate) fn compute(changes: impl IntoIterator<Item=(DateTime<Utc>, Option<bool>)>, start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        let mut status = None;
+        let mut offline_since = None;
+        let mut cursor = start;
+        let mut online = chrono::Duration::zero();
+        let mut offline = chrono::Duration::zero();
+        let mut outages = 0;
+        let mut recoveries = Vec::new();
+        for (timestamp, new_status) in changes {
+            if timestamp > end { break; }
+            if timestamp > start {
+                match status {
+                    Some(true) => online += timestamp - cursor,
+                    Some(false) => offline += timestamp - cursor,
+                    None => {}
+                }
+                cursor = timestamp;
+            }
+            match new_status {
+                // the element stays offline while it is unknown in between.
+                Some(false) if offline_since.is_none() => {
+                    if timestamp > start { outages += 1; }
+                    offline_since = Some(timestamp);
+                },
+                Some(true) => if let Some(since) = offline_since.take() && timestamp > start {
+                    recoveries.push(timestamp - since);
+                },
+                Some(false) | None => {}
+            }
+            status = new_status;
+        }
+        match status {
+            Some(true) => online += end - cursor,
+            Some(false) => offline += end - cursor,
+            None => {}
+        }
+        let known = online + offline;
+        #[expect(clippy::cast_precision_loss, clippy::cast_possible_truncation, reason="the precision of an f32 is plenty for a percentage.")]
+        let uptime = (!known.is_zero())
+            .then(|| (online.num_milliseconds() as f64 / known.num_milliseconds() as f64) as f32);
+        let mean_time_to_recovery = (!recoveries.is_empty()).then(|| {
+            recoveries.iter().copied().sum::<chrono::Duration>() / i32::try_from(recoveries.len()).unwrap_or(i32::MAX)
+        });
+        Self {
+            start,
+            end,
+            uptime,
+            online,
+            offline,
+            outages,
+            mean_time_to_recovery,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(hours: i64) -> DateTime<Utc> {
+        DateTime::<Utc>::UNIX_EPOCH + chrono::Duration::hours(hours)
+    }
+
+    #[test]
+    fn unknown_without_changes() {
+        let uptime = Uptime::compute([], at(0), at(24));
+        assert_eq!(uptime.uptime, None);
+        assert_eq!(uptime.outages, 0);
+        assert_eq!(uptime.mean_time_to_recovery, None);
+    }
+
+    #[test]
+    fn status_before_the_time_frame_carries_over() {
+        let uptime = Uptime::compute([(at(-5), Some(true))], at(0), at(24));
+        assert_eq!(uptime.uptime, Some(1.0));
+        assert_eq!(uptime.online, chrono::Duration::hours(24));
+        assert_eq!(uptime.offline, chrono::Duration::zero());
+    }
+
+    #[test]
+    fn counts_outages_and_recoveries() {
+        let uptime = Uptime::compute([
+            (at(0), Some(true)),
+            (at(6), Some(false)),
+            (at(8), Some(true)),
+            (at(12), Some(false)),
+            (at(16), Some(true)),
+        ], at(0), at(24));
+        assert_eq!(uptime.online, chrono::Duration::hours(18));
+        assert_eq!(uptime.offline, chrono::Duration::hours(6));
+        assert_eq!(uptime.uptime, Some(0.75));
+        assert_eq!(uptime.outages, 2);
+        assert_eq!(uptime.mean_time_to_recovery, Some(chrono::Duration::hours(3)));
+    }
+
+    #[test]
+    fn unknown_time_is_ignored() {
+        let uptime = Uptime::compute([
+            (at(12), Some(true)),
+            (at(18), None),
+            (at(20), Some(false)),
+        ], at(0), at(24));
+        assert_eq!(uptime.online, chrono::Duration::hours(6));
+        assert_eq!(uptime.offline, chrono::Duration::hours(4));
+        assert_eq!(uptime.uptime, Some(0.6));
+        assert_eq!(uptime.outages, 1);
+        // the outage hasn't ended yet
+        assert_eq!(uptime.mean_time_to_recovery, None);
+    }
+
+    #[test]
+    fn outage_spanning_the_start_isnt_counted_but_its_recovery_is() {
+        let uptime = Uptime::compute([
+            (at(-2), Some(false)),
+            // staying offline while the status is unknown in between
+            (at(1), None),
+            (at(2), Some(false)),
+            (at(4), Some(true)),
+        ], at(0), at(24));
+        assert_eq!(uptime.outages, 0);
+        assert_eq!(uptime.offline, chrono::Duration::hours(3));
+        assert_eq!(uptime.online, chrono::Duration::hours(20));
+        assert_eq!(uptime.mean_time_to_recovery, Some(chrono::Duration::hours(6)));
+    }
+
+    #[test]
+    fn changes_after_the_time_frame_are_ignored() {
+        let uptime = Uptime::compute([
+            (at(0), Some(true)),
+            (at(30), Some(false)),
+        ], at(0), at(24));
+        assert_eq!(uptime.uptime, Some(1.0));
+        assert_eq!(uptime.outages, 0);
+    }
+}
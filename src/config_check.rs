@@ -1,46 +1,226 @@
-use std::path::PathBuf;
-use toml::macros::Deserialize;
-use server::{Component, Config};
+mod source;
+mod unknown;
 
-pub fn check(path_: &PathBuf) -> Result<(), ()> {
-    let path = path_.to_string_lossy();
-    let content = std::fs::read_to_string(path_)
-        .map_err(|e| error!("couldn't read file `{path}` due to: {e}"))?;
-    let toml: Config = toml::from_str(&content)
-        .map_err(|e| error!("`{path}` contains invalid TOML: {e}"))?;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+use server::{ComponentRegistry, Config};
+use crate::Format;
+use source::{Location, Sources};
+use unknown::{Schema, UnknownKey};
 
-    let mut ok = true;
-    macro_rules! component {
-        (if $feature:literal: $component:ident) => {
-            #[cfg(feature = $feature)]
-            if let Some(config) = toml.configs.get(::default_components::$component::ID) {
-                match <::default_components::$component as ::server::Component>::Config::deserialize(config.clone()) {
-                    Ok(v) => {
-                        info!("successfully parsed config for `{}`", ::default_components::$component::ID);
-                        debug!("config for component `{}` is: `{v:?}`", ::default_components::$component::ID);
-                    }
-                    Err(e) => {
-                        error!("Invalid config for `{}`: {e}", ::default_components::$component::ID);
-                        ok &= !toml.ignored.components.contains(::default_components::$component::ID);
-                    }
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Options {
+    /// Fail on warnings as well.
+    pub strict: bool,
+    /// Initialize the components (without starting them).
+    pub dry_run: bool,
+    pub format: Format,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A segment of the path to a value in the config.
+pub enum Key {
+    Name(String),
+    Index(usize),
+}
+/// Formats the path as it would be written in a dotted key (e.g. `ntfy[0].topic`).
+fn display_path(path: &[Key]) -> String {
+    let mut display = String::new();
+    for key in path {
+        match key {
+            Key::Name(name) if display.is_empty() => display.push_str(name),
+            Key::Name(name) => { display.push('.'); display.push_str(name); }
+            Key::Index(index) => { let _ = write!(display, "[{index}]"); }
+        }
+    }
+    display
+}
+/// The path of the config of the instance (see [`Config::instances_of`]).
+fn instance_path(instance: &str) -> Vec<Key> {
+    instance.split('.').map(|key| Key::Name(key.to_string())).collect()
+}
+/// The candidate most similar to the (unknown) value, if any is similar enough.
+fn suggest<'a>(value: &str, candidates: impl IntoIterator<Item=&'a String>) -> Option<String> {
+    candidates.into_iter()
+        .map(|candidate| (strsim::jaro(value, candidate), candidate))
+        .filter(|(similarity, _)| *similarity > 0.7)
+        .max_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, candidate)| candidate.clone())
+}
+/// The hint to add to a message about an unknown value if there is a suggestion.
+fn did_you_mean(suggestion: Option<String>) -> String {
+    suggestion.map(|suggestion| format!(" (did you mean `{suggestion}`?)")).unwrap_or_default()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all="lowercase")]
+enum Severity {
+    Error,
+    Warning,
+}
+#[derive(Debug, serde::Serialize)]
+/// A single problem of the config.
+struct Diagnostic {
+    severity: Severity,
+    message: String,
+    /// The path of the value the problem is about (e.g. `website.timeout`).
+    #[serde(skip_serializing_if="Option::is_none")]
+    path: Option<String>,
+    #[serde(flatten)]
+    location: Option<Location>,
+}
+#[derive(Debug, serde::Serialize)]
+/// The outcome of checking the config.
+struct Report {
+    ok: bool,
+    /// All files (and directories) the config was read from.
+    files: Vec<PathBuf>,
+    diagnostics: Vec<Diagnostic>,
+}
+impl Report {
+    fn add(&mut self, severity: Severity, message: String, path: Option<&[Key]>, location: Option<Location>) {
+        self.diagnostics.push(Diagnostic { severity, message, path: path.map(display_path), location });
+    }
+    /// Whether the config passed the check.
+    fn passed(&self, strict: bool) -> bool {
+        self.diagnostics.iter()
+            .all(|diagnostic| diagnostic.severity == Severity::Warning && !strict)
+    }
+}
+
+/// Checks the config (and the configs of all compiled-in components) for errors, warning about
+/// things it doesn't know about (e.g. misspelled keys).
+pub fn check(path: &Path, options: Options) -> Result<(), ()> {
+    let mut report = Report { ok: false, files: Vec::new(), diagnostics: Vec::new() };
+    check_into(path, options, &mut report);
+    report.ok = report.passed(options.strict);
+    match options.format {
+        Format::Text => {
+            for diagnostic in &report.diagnostics {
+                let location = diagnostic.location.as_ref().map(|location| format!("{location}: ")).unwrap_or_default();
+                match diagnostic.severity {
+                    Severity::Error => error!("{location}{}", diagnostic.message),
+                    Severity::Warning => warn!("{location}{}", diagnostic.message),
                 }
-            } else {
-                info!("no config found for `{}`", ::default_components::$component::ID);
             }
-        };
+            if report.ok {
+                info!("all config is OK");
+            }
+        }
+        Format::Json => println!("{:#}", serde_json::to_value(&report).unwrap_or_default()),
     }
-    component!(if "api": Api);
-    component!(if "websockets": Websockets);
-    component!(if "frontend": Frontend);
+    if report.ok { Ok(()) } else { Err(()) }
+}
+fn check_into(path: &Path, options: Options, report: &mut Report) {
+    let (table, files) = match Config::read(path) {
+        Ok(v) => v,
+        Err(e) => {
+            let message = format!("`{}` contains an invalid config: {e}", path.to_string_lossy());
+            return report.add(Severity::Error, message, None, None);
+        }
+    };
+    debug!("read config from {files:?}");
+    let sources = Sources::read(&files);
+    report.files = files;
+    let config = match Config::deserialize(toml::Value::Table(table.clone())) {
+        Ok(config) => config,
+        Err(e) => {
+            // parsing the files one by one to find the one (and the span) causing the error.
+            let location = sources.search(&[], |_, value| Config::deserialize(toml::de::ValueDeserializer::from(value.clone())).err()
+                .filter(|error| error.message() == e.message())
+                .and_then(|error| error.span()));
+            return report.add(Severity::Error, format!("invalid config: {}", e.message()), None, location);
+        }
+    };
 
-    component!(if "ntfy-notifications": NtfyNotificationProvider);
-    component!(if "email-notifications": EmailNotificationProvider);
+    let registry = default_components::registry();
+    check_unknown_keys(&table, &config, &registry, &sources, report);
+    check_components(&config, &registry, &sources, report);
+    if options.dry_run && report.passed(false) {
+        for (instance, result) in server::Server::dry_run(config, registry).components {
+            match result {
+                Ok(()) => info!("successfully initialized `{instance}`"),
+                Err(e) => {
+                    let path = instance_path(&instance);
+                    let location = sources.locate(&path);
+                    report.add(Severity::Error, format!("couldn't initialize `{instance}`: {e}"), Some(&path), location);
+                }
+            }
+        }
+    }
+}
+/// Warns about components, instances and settings that don't exist.
+fn check_unknown_keys(table: &toml::Table, config: &Config, registry: &ComponentRegistry, sources: &Sources, report: &mut Report) {
+    let schema = registry.config_schema();
+    let schema = Schema::new(&schema);
+    let mut unknown = Vec::new();
+    for (key, value) in table {
+        let with_instances = registry.contains(key).then(|| config.instances.contains_key(key));
+        let Some(key_schema) = schema.top_level(key, with_instances) else {
+            let path = [Key::Name(key.clone())];
+            let message = format!(
+                "`{key}` is neither a setting of the server nor a (compiled-in) component{}",
+                did_you_mean(suggest(key, schema.top_level_keys()))
+            );
+            report.add(Severity::Warning, message, Some(&path), sources.locate(&path));
+            continue;
+        };
+        schema.unknown_keys(key_schema, value, &mut vec![Key::Name(key.clone())], &mut unknown);
+    }
+    for UnknownKey { path, suggestion } in unknown {
+        let message = format!("unknown setting `{}`{}", display_path(&path), did_you_mean(suggestion));
+        report.add(Severity::Warning, message, Some(&path), sources.locate(&path));
+    }
 
-    component!(if "dataminer-status": DataminerStatus);
-    component!(if "minecraft-status": MinecraftStatus);
-    component!(if "website-status": WebsiteStatuse);
-    if ok {
-        info!("all config is OK");
-        Ok(())
-    } else { Err(()) }
-}
\ No newline at end of file
+    for (id, names) in &config.instances {
+        let path = [Key::Name("instances".to_string()), Key::Name(id.clone())];
+        if !registry.contains(id) {
+            let location = sources.locate(&path)
+                .or_else(|| sources.locate(&[Key::Name("instance".to_string()), Key::Name(id.clone())]));
+            report.add(Severity::Warning, format!("instances configured for unknown component `{id}`"), Some(&path), location);
+            continue;
+        }
+        let configured = table.get(id).and_then(toml::Value::as_table).into_iter().flat_map(toml::Table::keys);
+        for name in configured.filter(|name| !names.contains(name)) {
+            let path = [Key::Name(id.clone()), Key::Name(name.clone())];
+            let message = format!("`{id}.{name}` is configured, but isn't an instance of `{id}` (see `instances.{id}`)");
+            report.add(Severity::Warning, message, Some(&path), sources.locate(&path));
+        }
+    }
+    let ids = registry.ids().map(str::to_string).collect::<Vec<_>>();
+    for ignored in &config.ignored.components {
+        let id = ignored.split_once('.').map_or(ignored.as_str(), |(id, _)| id);
+        if !registry.contains(id) {
+            let message = format!("ignoring unknown component `{ignored}`{}", did_you_mean(suggest(id, &ids)));
+            report.add(Severity::Warning, message, None, None);
+        }
+    }
+}
+/// Parses the config of every configured instance of the registered components.
+fn check_components(config: &Config, registry: &ComponentRegistry, sources: &Sources, report: &mut Report) {
+    for id in registry.ids() {
+        for instance in config.instances_of(id) {
+            let Some(value) = config.config_of(&instance) else {
+                info!("no config found for `{instance}`");
+                continue;
+            };
+            match registry.check_config(id, value.clone()).expect("id is registered") {
+                // NOTE: not logging the parsed config, as it contains the resolved secrets.
+                Ok(_) => info!("successfully parsed config for `{instance}`"),
+                Err(e) => {
+                    let path = instance_path(&instance);
+                    // parsing the instance's config from the files to find the span causing the error.
+                    let location = sources.search(&path, |_, value| registry.check_config_source(id, toml::de::ValueDeserializer::from(value.clone()))
+                            .and_then(Result::err)
+                            .filter(|error| error.message() == e.message())
+                            .and_then(|error| error.span()))
+                        .or_else(|| sources.locate(&path));
+                    // ignored instances don't need a valid config.
+                    let severity = if config.is_ignored(&instance) { Severity::Warning } else { Severity::Error };
+                    report.add(severity, format!("invalid config for `{instance}`: {}", e.message()), Some(&path), location);
+                }
+            }
+        }
+    }
+}
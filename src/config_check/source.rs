@@ -0,0 +1,97 @@
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use toml::de::{DeTable, DeValue};
+use toml::Spanned;
+use super::Key;
+
+#[derive(Clone, Debug, serde::Serialize)]
+/// A position in a config file.
+pub struct Location {
+    pub file: PathBuf,
+    pub line: usize,
+    pub column: usize,
+}
+impl std::fmt::Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.file.to_string_lossy(), self.line, self.column)
+    }
+}
+
+/// The content of all files the config was read from (in the order they were merged), used to find
+/// out where in them something was configured.
+pub struct Sources {
+    files: Vec<(PathBuf, String)>,
+}
+impl Sources {
+    /// Reads the files, skipping directories (and files that can't be read anymore).
+    pub fn read(files: &[PathBuf]) -> Self {
+        let files = files.iter()
+            .filter(|file| file.is_file())
+            .filter_map(|file| Some((file.clone(), std::fs::read_to_string(file).ok()?)))
+            .collect();
+        Self { files }
+    }
+    /// Where the value at the path is configured (the key if the path ends with one).
+    pub fn locate(&self, path: &[Key]) -> Option<Location> {
+        self.search(path, |key, value| Some(key.unwrap_or_else(|| value.span())))
+    }
+    /// Calls `found` with the value at the path (and the span of its key) in every file containing
+    /// it, until it returns a span.
+    ///
+    /// As arrays are concatenated when merging the files, an index in the path refers to the
+    /// elements of all files (in order).
+    pub fn search<F>(&self, path: &[Key], mut found: F) -> Option<Location>
+    where F: FnMut(Option<Range<usize>>, &Spanned<DeValue<'_>>) -> Option<Range<usize>> {
+        let mut path = path.to_vec();
+        for (file, content) in &self.files {
+            let Ok(root) = DeTable::parse(content) else { continue; };
+            let root = Spanned::new(root.span(), DeValue::Table(root.into_inner()));
+            match lookup(&root, &path) {
+                Lookup::Found(key, value) => if let Some(span) = found(key, value) {
+                    return Some(location(file, content, span.start));
+                },
+                Lookup::OutOfBounds(segment, len) => if let Key::Index(index) = &mut path[segment] {
+                    *index -= len;
+                },
+                Lookup::Missing => {}
+            }
+        }
+        None
+    }
+}
+enum Lookup<'a, 'i> {
+    /// The value at the path and the span of its key (if the path ends with a key).
+    Found(Option<Range<usize>>, &'a Spanned<DeValue<'i>>),
+    /// The array at the segment of the path only has the given number of elements.
+    OutOfBounds(usize, usize),
+    Missing,
+}
+fn lookup<'a, 'i>(mut value: &'a Spanned<DeValue<'i>>, path: &[Key]) -> Lookup<'a, 'i> {
+    let mut key_span = None;
+    for (segment, key) in path.iter().enumerate() {
+        match (value.get_ref(), key) {
+            (DeValue::Table(table), Key::Name(name)) => {
+                let Some((key, child)) = table.get_key_value(name.as_str()) else { return Lookup::Missing; };
+                key_span = Some(key.span());
+                value = child;
+            }
+            (DeValue::Array(array), Key::Index(index)) => {
+                let Some(child) = array.get(*index) else { return Lookup::OutOfBounds(segment, array.len()); };
+                key_span = None;
+                value = child;
+            }
+            _ => return Lookup::Missing,
+        }
+    }
+    Lookup::Found(key_span, value)
+}
+/// The line & column (both starting at 1) of the byte offset in the content of the file.
+fn location(file: &Path, content: &str, offset: usize) -> Location {
+    let before = content.get(..offset).unwrap_or(content);
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    Location {
+        file: file.to_path_buf(),
+        line: before.matches('\n').count() + 1,
+        column: before[line_start..].chars().count() + 1,
+    }
+}
@@ -0,0 +1,118 @@
+use serde_json::{Map, Value};
+use super::Key;
+
+/// A key of the config that its schema doesn't know about.
+pub struct UnknownKey {
+    pub path: Vec<Key>,
+    /// The known key that is most similar to it (if any is similar enough).
+    pub suggestion: Option<String>,
+}
+
+/// Finds the keys that aren't known to the JSON Schema of the config (see
+/// [`ComponentRegistry::config_schema`](server::ComponentRegistry::config_schema)).
+pub struct Schema<'a> {
+    root: &'a Value,
+}
+impl<'a> Schema<'a> {
+    pub const fn new(root: &'a Value) -> Self {
+        Self { root }
+    }
+    /// The schema of the top-level key (a setting of the server or a component) if it is known.
+    ///
+    /// The property of a component allows both its config and the configs of its named instances,
+    /// so the one that applies has to be picked.
+    pub fn top_level(&self, key: &str, component_with_instances: Option<bool>) -> Option<&'a Value> {
+        let property = self.root.get("properties")?.get(key)?;
+        match component_with_instances {
+            None => Some(property),
+            Some(with_instances) => property.get("anyOf")?.get(usize::from(with_instances)),
+        }
+    }
+    /// The names of all top-level keys.
+    pub fn top_level_keys(&self) -> impl Iterator<Item=&'a String> {
+        self.root.get("properties")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flat_map(Map::keys)
+    }
+    /// Collects the unknown keys of the value (located at the path), which should match the schema.
+    pub fn unknown_keys(&self, schema: &'a Value, value: &toml::Value, path: &mut Vec<Key>, unknown: &mut Vec<UnknownKey>) {
+        let mut schemas = Vec::new();
+        self.alternatives(schema, &mut schemas);
+        self.walk(&schemas, value, path, unknown);
+    }
+    /// The object schemas a value matching the schema is matched against: the schema itself, the
+    /// schema it references and (recursively) all of its subschemas combining others.
+    ///
+    /// Alternatives are simply united, so a key is only unknown if none of them knows it.
+    fn alternatives(&self, schema: &'a Value, schemas: &mut Vec<&'a Map<String, Value>>) {
+        let Value::Object(object) = schema else { return; };
+        if schemas.iter().any(|known| std::ptr::eq(*known, object)) { return; }
+        schemas.push(object);
+        if let Some(Value::String(reference)) = object.get("$ref")
+            && let Some(name) = reference.strip_prefix("#/$defs/")
+            && let Some(definition) = self.root.get("$defs").and_then(|definitions| definitions.get(name)) {
+            self.alternatives(definition, schemas);
+        }
+        for key in ["anyOf", "oneOf", "allOf"] {
+            for subschema in object.get(key).and_then(Value::as_array).into_iter().flatten() {
+                self.alternatives(subschema, schemas);
+            }
+        }
+        for key in ["then", "else"] {
+            if let Some(subschema) = object.get(key) {
+                self.alternatives(subschema, schemas);
+            }
+        }
+    }
+    fn walk(&self, schemas: &[&'a Map<String, Value>], value: &toml::Value, path: &mut Vec<Key>, unknown: &mut Vec<UnknownKey>) {
+        match value {
+            toml::Value::Table(table) => self.walk_table(schemas, table, path, unknown),
+            toml::Value::Array(array) => for (index, value) in array.iter().enumerate() {
+                let items = schemas.iter()
+                    .filter_map(|schema| schema.get("prefixItems")
+                        .and_then(|prefix| prefix.get(index))
+                        .or_else(|| schema.get("items")));
+                let mut item_schemas = Vec::new();
+                for item in items {
+                    self.alternatives(item, &mut item_schemas);
+                }
+                path.push(Key::Index(index));
+                self.walk(&item_schemas, value, path, unknown);
+                path.pop();
+            },
+            _ => {}
+        }
+    }
+    fn walk_table(&self, schemas: &[&'a Map<String, Value>], table: &toml::Table, path: &mut Vec<Key>, unknown: &mut Vec<UnknownKey>) {
+        let properties = schemas.iter()
+            .filter_map(|schema| schema.get("properties").and_then(Value::as_object))
+            .collect::<Vec<_>>();
+        let additional = schemas.iter()
+            .filter_map(|schema| schema.get("additionalProperties"))
+            .filter(|additional| *additional != &Value::Bool(false))
+            .collect::<Vec<_>>();
+        // schemas that don't restrict the keys at all (e.g. of arbitrary values).
+        if properties.is_empty() && additional.is_empty() { return; }
+        for (key, value) in table {
+            let known = properties.iter()
+                .filter_map(|properties| properties.get(key))
+                .collect::<Vec<_>>();
+            let subschemas = if known.is_empty() { &additional } else { &known };
+            path.push(Key::Name(key.clone()));
+            if subschemas.is_empty() {
+                unknown.push(UnknownKey {
+                    path: path.clone(),
+                    suggestion: super::suggest(key, properties.iter().flat_map(|properties| properties.keys())),
+                });
+            } else {
+                let mut value_schemas = Vec::new();
+                for subschema in subschemas {
+                    self.alternatives(subschema, &mut value_schemas);
+                }
+                self.walk(&value_schemas, value, path, unknown);
+            }
+            path.pop();
+        }
+    }
+}
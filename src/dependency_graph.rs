@@ -0,0 +1,28 @@
+use std::path::Path;
+use server::Config;
+use crate::Format;
+
+/// Initializes the configured components (without starting them) and prints the dependencies
+/// between them in the order they are initialized in.
+pub fn print(path_: &Path, format: Format) -> Result<(), ()> {
+    let path = path_.to_string_lossy();
+    let (config, _) = Config::load(path_)
+        .map_err(|e| error!("`{path}` contains an invalid config: {e}"))?;
+    let dry_run = server::Server::dry_run(config, default_components::registry());
+    for (instance, result) in &dry_run.components {
+        if let Err(e) = result {
+            warn!("couldn't initialize `{instance}`: {e}");
+        }
+    }
+    let graph = dry_run.dependencies;
+    match format {
+        Format::Text => for instance in &graph.init_order {
+            match graph.dependencies.get(instance).filter(|dependencies| !dependencies.is_empty()) {
+                Some(dependencies) => println!("{instance} -> {}", dependencies.join(", ")),
+                None => println!("{instance}"),
+            }
+        },
+        Format::Json => println!("{:#}", serde_json::to_value(&graph).unwrap_or_default()),
+    }
+    Ok(())
+}
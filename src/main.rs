@@ -19,6 +19,7 @@ reason = "don't want these lints."
 
 mod start_server;
 mod config_check;
+mod dependency_graph;
 
 #[macro_use]
 extern crate tracing;
@@ -34,16 +35,25 @@ const LEVEL: LevelFilter = LevelFilter::TRACE;
 const LEVEL: LevelFilter = LevelFilter::INFO;
 
 fn main() -> ExitCode {
+    // logging to stderr keeps stdout clean for the output of commands (e.g. `config-schema`).
     tracing_subscriber::fmt()
         .with_max_level(LEVEL)
+        .with_writer(std::io::stderr)
         .init();
 
     let args = Args::parse();
     debug!("parsed args: {args:?}");
     match args.command {
         Command::Run { host, port } => start_server::start(args.config_file, &host, port),
-        Command::CheckConfig => {
-            if let Err(()) = config_check::check(&args.config_file) {
+        Command::CheckConfig { strict, dry_run, format } => {
+            let options = config_check::Options { strict, dry_run, format };
+            if let Err(()) = config_check::check(&args.config_file, options) {
+                return ExitCode::FAILURE
+            }
+        }
+        Command::ConfigSchema => println!("{:#}", default_components::registry().config_schema()),
+        Command::Dependencies { format } => {
+            if let Err(()) = dependency_graph::print(&args.config_file, format) {
                 return ExitCode::FAILURE
             }
         }
@@ -74,5 +84,40 @@ enum Command {
         #[arg(short, long, default_value_t=5000)]
         port: u16,
     },
-    CheckConfig,
+    /// Checks the config (including the configs of all compiled-in components) for errors.
+    CheckConfig {
+        /// Also fail if there are warnings (e.g. unknown settings or components).
+        #[arg(long)]
+        strict: bool,
+
+        /// Also initialize the components (without starting them), catching errors that only
+        /// occur then.
+        #[arg(long)]
+        dry_run: bool,
+
+        /// How the result is reported.
+        #[arg(long, value_enum, default_value_t)]
+        format: Format,
+    },
+    /// Prints the JSON Schema of the config (including all compiled-in components).
+    #[clap(alias="schema")]
+    ConfigSchema,
+    /// Prints the dependencies between the configured components and the order they are
+    /// initialized in (initializing them without starting them).
+    #[clap(alias="deps")]
+    Dependencies {
+        /// How the graph is printed.
+        #[arg(long, value_enum, default_value_t)]
+        format: Format,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Default, clap::ValueEnum)]
+/// How the output of a command is printed.
+pub enum Format {
+    /// Human-readable (problems are logged).
+    #[default]
+    Text,
+    /// JSON (see the README) on stdout.
+    Json,
 }
\ No newline at end of file
@@ -1,7 +1,9 @@
 use std::path::PathBuf;
+use std::sync::Arc;
 use axum::routing::any;
 use tokio::signal::unix::{signal, SignalKind};
-use tracing::info;
+use tokio::sync::Notify;
+use tracing::{info, warn};
 
 pub fn start(config_file: PathBuf, host: &str, port: u16) {
     tokio::runtime::Runtime::new().unwrap()
@@ -17,32 +19,40 @@ pub fn start(config_file: PathBuf, host: &str, port: u16) {
             });
             let server = server_;
 
-            macro_rules! component {
-                (if $feature:literal: $component:ident) => {
-                    #[cfg(feature = $feature)]
-                    server.add_component::<::default_components::$component>();
-                };
-                (if $feature:literal: notify $component:ident) => {
-                    #[cfg(feature = $feature)]
-                    server.add_notification_provider::<::default_components::$component>();
-                };
-            }
-            component!(if "api": Api);
-            component!(if "websockets": notify Websockets);
-            component!(if "frontend": Frontend);
-
-            component!(if "ntfy-notifications": notify NtfyNotificationProvider);
-            component!(if "email-notifications": notify EmailNotificationProvider);
-
-            component!(if "dataminer-status": DataminerStatus);
-            component!(if "minecraft-status": MinecraftStatus);
-            component!(if "website-status": WebsiteStatuse);
+            server.add_components(default_components::registry())
+                .start();
 
             let router = axum::Router::new()
                 .route("/", any(server.clone()))
                 .route("/{*any}", any(server.clone()));
             let listener = tokio::net::TcpListener::bind((host, port)).await.unwrap();
             info!("listening on http://{}:{}", host, port);
-            axum::serve(listener, router).await.unwrap();
+            let stopping = Arc::new(Notify::new());
+            let serve = axum::serve(listener, router)
+                .with_graceful_shutdown({
+                    let stopping = stopping.clone();
+                    async move {
+                        shutdown_signal().await;
+                        stopping.notify_one();
+                    }
+                });
+            // open connections (e.g. websockets) might never close on their own.
+            let timeout = server.shutdown_timeout();
+            tokio::select! {
+                result = serve => result.unwrap(),
+                () = async { stopping.notified().await; tokio::time::sleep(timeout).await; } => {
+                    warn!("not all connections closed in time");
+                }
+            }
+            server.shutdown().await;
         });
-}
\ No newline at end of file
+}
+
+/// Waits for `SIGTERM` or `SIGINT` (e.g. Ctrl+C).
+async fn shutdown_signal() {
+    let mut terminate = signal(SignalKind::terminate()).expect("unable to register SIGTERM signal handler");
+    tokio::select! {
+        _ = terminate.recv() => info!("received SIGTERM, shutting down"),
+        _ = tokio::signal::ctrl_c() => info!("received SIGINT, shutting down"),
+    }
+}